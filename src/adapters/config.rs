@@ -6,9 +6,11 @@ use crate::{adapters::AppCapabilities, capabilities::*};
 
 impl StoreConfigValue for AppCapabilities {
     fn store_config_value<T: Serialize>(&self, key: &str, value: T) -> Result<()> {
-        let conn = self.db.get()?;
+        let mut conn = self.db.get()?;
 
-        let _ = conn.execute(
+        // Readers never see the key missing, and two writers can't both insert
+        let tx = conn.transaction()?;
+        let _ = tx.execute(
             r#"
                     DELETE FROM config 
                     WHERE key=?1
@@ -16,13 +18,14 @@ impl StoreConfigValue for AppCapabilities {
             params![key],
         )?;
 
-        let _ = conn.execute(
+        let _ = tx.execute(
             r#"
                     INSERT INTO config (key, value)
                     VALUES (?1, ?2)
             "#,
             params![key, serde_json::to_string(&value)?],
         )?;
+        tx.commit()?;
 
         Ok(())
     }
@@ -49,6 +52,34 @@ impl LoadConfigValue for AppCapabilities {
     }
 }
 
+impl LoadConfigValues for AppCapabilities {
+    fn load_config_values_as<T: DeserializeOwned>(&self, prefix: &str) -> Result<Vec<T>> {
+        let conn = self.db.get()?;
+
+        let mut stmt = conn.prepare("SELECT value FROM config WHERE substr(key, 1, ?1) = ?2")?;
+        let values = stmt
+            .query_map(params![prefix.chars().count() as u32, prefix], |row| {
+                let value: String = row.get(0)?;
+                Ok(value)
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(values
+            .iter()
+            .map(|value| serde_json::from_str(value))
+            .collect::<Result<_, _>>()?)
+    }
+}
+
+impl DeleteConfigValue for AppCapabilities {
+    fn delete_config_value(&self, key: &str) -> Result<()> {
+        let conn = self.db.get()?;
+        conn.execute("DELETE FROM config WHERE key = ?1", params![key])?;
+
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 pub mod test {
     use super::*;
@@ -64,4 +95,20 @@ pub mod test {
 
         assert_eq!(got, want);
     }
+
+    #[test]
+    fn load_and_delete_by_prefix() {
+        let app = crate::adapters::test::setup();
+        app.store_config_value("range:1", 1).unwrap();
+        app.store_config_value("range:2", 2).unwrap();
+        app.store_config_value("other", 3).unwrap();
+
+        let mut got: Vec<i32> = app.load_config_values_as("range:").unwrap();
+        got.sort_unstable();
+        assert_eq!(got, vec![1, 2]);
+
+        app.delete_config_value("range:1").unwrap();
+        let got: Vec<i32> = app.load_config_values_as("range:").unwrap();
+        assert_eq!(got, vec![2]);
+    }
 }
@@ -0,0 +1,187 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use duckdb::{params, OptionalExt};
+use std::str::FromStr;
+
+use crate::{
+    adapters::AppCapabilities,
+    capabilities::*,
+    domain::crawl::{CrawlProgress, CrawlStatus, UserCrawl},
+};
+
+impl StoreUserCrawl for AppCapabilities {
+    fn store_user_crawl(&self, crawl: UserCrawl) -> Result<()> {
+        let mut conn = self.db.get()?;
+        let tx = conn.transaction()?;
+
+        tx.execute("DELETE FROM user_crawl WHERE id = ?1", [&crawl.username])?;
+        tx.execute(
+            r#"
+                INSERT INTO user_crawl (id, status, submitted, stored, unavailable, failed, error, updated)
+                VALUES
+                (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+            "#,
+            params![
+                crawl.username,
+                crawl.status.to_string(),
+                crawl.progress.submitted as u32,
+                crawl.progress.stored as u32,
+                crawl.progress.unavailable as u32,
+                crawl.progress.failed as u32,
+                crawl.error,
+                crawl.updated
+            ],
+        )?;
+        tx.commit()?;
+
+        Ok(())
+    }
+}
+
+impl LoadUserCrawl for AppCapabilities {
+    fn load_user_crawl(&self, username: &str) -> Result<Option<UserCrawl>> {
+        let conn = self.db.get()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT
+                id, status, submitted, stored, unavailable, failed, error, updated
+            FROM
+                user_crawl
+            WHERE
+                id = ?1
+            "#,
+        )?;
+
+        Ok(stmt
+            .query_row([username], |row| {
+                let status: String = row.get(1)?;
+                let submitted: u32 = row.get(2)?;
+                let stored: u32 = row.get(3)?;
+                let unavailable: u32 = row.get(4)?;
+                let failed: u32 = row.get(5)?;
+                let updated: DateTime<Utc> = row.get(7)?;
+
+                Ok(UserCrawl {
+                    username: row.get(0)?,
+                    status: CrawlStatus::from_str(&status)
+                        .map_err(|_| duckdb::Error::InvalidQuery)?,
+                    progress: CrawlProgress {
+                        submitted: submitted as usize,
+                        stored: stored as usize,
+                        unavailable: unavailable as usize,
+                        failed: failed as usize,
+                    },
+                    error: row.get(6)?,
+                    updated,
+                })
+            })
+            .optional()?)
+    }
+}
+
+impl LoadUnfinishedUserCrawls for AppCapabilities {
+    fn load_unfinished_user_crawls(&self) -> Result<Vec<String>> {
+        let conn = self.db.get()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT
+                id
+            FROM
+                user_crawl
+            WHERE
+                status IN (?1, ?2)
+            ORDER BY
+                updated
+            "#,
+        )?;
+
+        let usernames = stmt
+            .query_map(
+                params![
+                    CrawlStatus::Queued.to_string(),
+                    CrawlStatus::Running.to_string()
+                ],
+                |row| row.get(0),
+            )?
+            .collect::<Result<_, _>>()?;
+
+        Ok(usernames)
+    }
+}
+
+impl QueueUserCrawl for AppCapabilities {
+    fn queue_user_crawl(&self, username: &str) -> Result<bool> {
+        // Recorded before the worker can pick it up, so it can't land on top
+        // of the running state
+        self.crawls.push(username.to_string(), || {
+            self.store_user_crawl(UserCrawl::new(username, CrawlStatus::Queued, &Utc::now()))
+        })
+    }
+}
+
+impl WorkUserCrawls for AppCapabilities {
+    fn work_user_crawls(&self, mut crawl: impl FnMut(&str)) -> Result<()> {
+        self.crawls.work(|username| crawl(username))
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let app = crate::adapters::test::setup();
+        let crawl = UserCrawl {
+            username: "pg".into(),
+            status: CrawlStatus::Failed,
+            progress: CrawlProgress {
+                submitted: 3,
+                stored: 1,
+                unavailable: 1,
+                failed: 0,
+            },
+            error: Some("timed out".into()),
+            updated: Utc::now(),
+        };
+
+        app.store_user_crawl(crawl.clone()).unwrap();
+        let got = app.load_user_crawl("pg").unwrap().unwrap();
+
+        assert_eq!(got.status, crawl.status);
+        assert_eq!(got.progress, crawl.progress);
+        assert_eq!(got.error, crawl.error);
+        assert_eq!(app.load_user_crawl("nobody").unwrap(), None);
+    }
+
+    #[test]
+    fn queue_each_user_once() {
+        let app = crate::adapters::test::setup();
+
+        assert!(app.queue_user_crawl("pg").unwrap());
+        assert!(!app.queue_user_crawl("pg").unwrap());
+
+        let got = app.load_user_crawl("pg").unwrap().unwrap();
+        assert_eq!(got.status, CrawlStatus::Queued);
+    }
+
+    #[test]
+    fn load_unfinished() {
+        let app = crate::adapters::test::setup();
+        let now = Utc::now();
+        for (username, status) in vec![
+            ("pg", CrawlStatus::Running),
+            ("tel", CrawlStatus::Done),
+            ("norvig", CrawlStatus::Queued),
+            ("dhouston", CrawlStatus::Failed),
+        ] {
+            app.store_user_crawl(UserCrawl::new(username, status, &now))
+                .unwrap();
+        }
+
+        let mut got = app.load_unfinished_user_crawls().unwrap();
+        got.sort();
+
+        assert_eq!(got, vec!["norvig".to_string(), "pg".to_string()]);
+    }
+}
@@ -0,0 +1,287 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use duckdb::{params, DropBehavior};
+
+use crate::{
+    adapters::AppCapabilities,
+    capabilities::{LoadDecodeReport, LoadRawItems, StoreRawItems},
+    domain::{
+        decode::{IssueCount, RawItem, EXAMPLES},
+        fetch::FetchOutcome,
+    },
+    infra::hn::decode::decode,
+};
+
+impl AppCapabilities {
+    /// Decode fetched payloads, quarantining any that had issues.
+    pub(crate) fn decode_items(
+        &self,
+        raw: Vec<(u32, serde_json::Value)>,
+    ) -> Result<Vec<FetchOutcome>> {
+        let ts = Utc::now();
+        let mut outcomes = vec![];
+        let mut raw_items = vec![];
+
+        for (id, value) in raw {
+            let (item, issues) = decode(&value);
+
+            if !issues.is_empty() {
+                raw_items.push(RawItem {
+                    id,
+                    ts,
+                    raw: value.to_string(),
+                    issues,
+                    decoded: item.is_some(),
+                });
+            }
+            outcomes.push(match item {
+                Some(item) => FetchOutcome::Found(item),
+                None => FetchOutcome::Quarantined(id),
+            });
+        }
+
+        self.store_raw_items(raw_items)?;
+
+        Ok(outcomes)
+    }
+}
+
+impl StoreRawItems for AppCapabilities {
+    fn store_raw_items(&self, raw_items: Vec<RawItem>) -> Result<()> {
+        let mut conn = self.db.get()?;
+        let mut tx = conn.transaction()?;
+        tx.set_drop_behavior(DropBehavior::Commit);
+
+        for raw_item in raw_items.into_iter() {
+            // Hot items are polled over and over, only new payloads are kept
+            let inserted = tx.execute(
+                r#"
+                INSERT INTO raw_item (id, ts, raw, issues, decoded)
+                SELECT ?1, ?2, ?3, ?4, ?5
+                WHERE NOT EXISTS (SELECT 1 FROM raw_item WHERE id = ?6 AND raw = ?7)
+                "#,
+                params![
+                    raw_item.id,
+                    raw_item.ts,
+                    raw_item.raw,
+                    serde_json::to_string(&raw_item.issues)?,
+                    raw_item.decoded,
+                    raw_item.id,
+                    raw_item.raw,
+                ],
+            )?;
+            if inserted == 0 {
+                continue;
+            }
+
+            for issue in raw_item.issues {
+                tx.execute(
+                    "INSERT INTO raw_item_issue (id, ts, field, problem) VALUES (?1, ?2, ?3, ?4)",
+                    params![raw_item.id, raw_item.ts, issue.field, issue.problem],
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl LoadRawItems for AppCapabilities {
+    fn load_raw_items(&self, limit: u32) -> Result<Vec<RawItem>> {
+        let conn = self.db.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, ts, raw, issues, decoded FROM raw_item ORDER BY ts DESC LIMIT ?1",
+        )?;
+
+        let results = stmt
+            .query_map(params![limit], |row| {
+                let ts: DateTime<Utc> = row.get(1)?;
+                let issues: String = row.get(3)?;
+
+                Ok(RawItem {
+                    id: row.get(0)?,
+                    ts,
+                    raw: row.get(2)?,
+                    issues: serde_json::from_str(&issues)
+                        .map_err(|_| duckdb::Error::InvalidQuery)?,
+                    decoded: row.get(4)?,
+                })
+            })?
+            .filter_map(Result::ok)
+            .collect();
+
+        Ok(results)
+    }
+}
+
+impl LoadDecodeReport for AppCapabilities {
+    fn load_decode_report(&self) -> Result<Vec<IssueCount>> {
+        let conn = self.db.get()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT
+                i.field,
+                i.problem,
+                count(*) AS count,
+                CAST(sum(CASE WHEN r.decoded THEN 0 ELSE 1 END) AS BIGINT) AS lost
+            FROM
+                raw_item_issue i
+            JOIN
+                raw_item r ON r.id = i.id AND r.ts = i.ts
+            GROUP BY
+                i.field, i.problem
+            ORDER BY
+                count DESC, i.field, i.problem
+            "#,
+        )?;
+        let mut results = stmt
+            .query_map([], |row| {
+                let count: i64 = row.get(2)?;
+                let lost: i64 = row.get(3)?;
+
+                Ok(IssueCount {
+                    field: row.get(0)?,
+                    problem: row.get(1)?,
+                    count: count as usize,
+                    lost: lost as usize,
+                    example_ids: vec![],
+                })
+            })?
+            .filter_map(Result::ok)
+            .collect::<Vec<_>>();
+
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT
+                field, problem, id
+            FROM (
+                SELECT
+                    field, problem, id,
+                    row_number() OVER (PARTITION BY field, problem ORDER BY id) AS n
+                FROM
+                    (SELECT DISTINCT field, problem, id FROM raw_item_issue) issues
+            ) examples
+            WHERE
+                n <= ?1
+            ORDER BY
+                id
+            "#,
+        )?;
+        let examples = stmt
+            .query_map(params![EXAMPLES as u32], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get(2)?,
+                ))
+            })?
+            .filter_map(Result::ok);
+
+        for (field, problem, id) in examples {
+            if let Some(count) = results
+                .iter_mut()
+                .find(|count| count.field == field && count.problem == problem)
+            {
+                count.example_ids.push(id);
+            }
+        }
+
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::{domain::decode::FieldIssue, infra::hn::types::tests::sample_items};
+    use serde_json::json;
+
+    #[test]
+    fn decode_and_quarantine() {
+        let app = crate::adapters::test::setup();
+        let raw = vec![
+            (8863, serde_json::to_value(&sample_items()[0]).unwrap()),
+            (
+                2,
+                json!({"id": 2, "type": "comment", "by": "pg", "text": "Hi", "time": 3}),
+            ),
+        ];
+
+        let got = app.decode_items(raw).unwrap();
+        let want = vec![
+            FetchOutcome::Found(sample_items()[0].clone()),
+            FetchOutcome::Quarantined(2),
+        ];
+        assert_eq!(got, want);
+
+        let got = app.load_raw_items(10).unwrap();
+        assert_eq!(got.len(), 1);
+        assert_eq!(got[0].id, 2);
+        assert!(!got[0].decoded);
+        assert_eq!(got[0].issues[0].field, "parent");
+    }
+
+    #[test]
+    fn unchanged_payloads_are_stored_once() {
+        let app = crate::adapters::test::setup();
+        let raw = || {
+            vec![(
+                1,
+                json!({"id": 1, "by": "pg", "time": 3, "type": "story", "title": "Hi", "score": 1}),
+            )]
+        };
+
+        app.decode_items(raw()).unwrap();
+        app.decode_items(raw()).unwrap();
+
+        assert_eq!(app.load_raw_items(10).unwrap().len(), 1);
+    }
+
+    fn raw_item(id: u32, decoded: bool, issues: &[(&str, &str)]) -> RawItem {
+        RawItem {
+            id,
+            ts: Utc::now(),
+            raw: format!("{{\"id\": {}}}", id),
+            issues: issues
+                .iter()
+                .map(|(field, problem)| FieldIssue {
+                    field: field.to_string(),
+                    problem: problem.to_string(),
+                })
+                .collect(),
+            decoded,
+        }
+    }
+
+    #[test]
+    fn report_groups_issues() {
+        let app = crate::adapters::test::setup();
+        app.store_raw_items(vec![
+            raw_item(4, false, &[("time", "missing")]),
+            raw_item(2, true, &[("flair", "unknown field")]),
+            raw_item(3, false, &[("time", "missing"), ("flair", "unknown field")]),
+            raw_item(1, false, &[("time", "missing")]),
+        ])
+        .unwrap();
+
+        let got = app.load_decode_report().unwrap();
+        let want = vec![
+            IssueCount {
+                field: "time".into(),
+                problem: "missing".into(),
+                count: 3,
+                lost: 3,
+                example_ids: vec![1, 3, 4],
+            },
+            IssueCount {
+                field: "flair".into(),
+                problem: "unknown field".into(),
+                count: 2,
+                lost: 1,
+                example_ids: vec![2, 3],
+            },
+        ];
+
+        assert_eq!(got, want);
+    }
+}
@@ -0,0 +1,21 @@
+use anyhow::Result;
+
+use crate::{
+    adapters::AppCapabilities, capabilities::*, domain::Event, infra::events::Subscription,
+};
+
+impl PublishEvents for AppCapabilities {
+    fn publish_events(&self, events: Vec<Event>) -> Result<()> {
+        for event in events {
+            self.events.publish(event)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl SubscribeEvents for AppCapabilities {
+    fn subscribe_events(&self) -> Result<Option<Subscription>> {
+        self.events.subscribe()
+    }
+}
@@ -0,0 +1,334 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use duckdb::params;
+use std::{collections::HashMap, str::FromStr};
+
+use crate::{
+    adapters::AppCapabilities,
+    capabilities::{FetchItemOutcomes, LoadConfigValue, LoadDueFetchFailures},
+    domain::fetch::{FailureKind, FetchFailure, FetchOutcome, RetryPolicy},
+    infra::hn::{block_on, failure_kind},
+};
+
+impl FetchItemOutcomes for AppCapabilities {
+    fn fetch_item_outcomes(&self, ids: Vec<u32>) -> Result<Vec<FetchOutcome>> {
+        let mut outcomes = vec![];
+        let mut found = vec![];
+
+        for (id, result) in block_on(self.client.get_raw_items(ids))? {
+            match result {
+                Ok(Some(value)) => found.push((id, value)),
+                Ok(None) => outcomes.push(FetchOutcome::Missing(id)),
+                Err(e) => outcomes.push(FetchOutcome::Failed {
+                    id,
+                    kind: failure_kind(&e),
+                    message: e.to_string(),
+                }),
+            }
+        }
+        outcomes.extend(self.decode_items(found)?);
+
+        self.update_fetch_ledger(&outcomes, &Utc::now())?;
+
+        Ok(outcomes)
+    }
+}
+
+impl AppCapabilities {
+    /// Record failures and ids HN has nothing for, and clear out ids that
+    /// have now been resolved.
+    fn update_fetch_ledger(&self, outcomes: &[FetchOutcome], now: &DateTime<Utc>) -> Result<()> {
+        let policy: RetryPolicy = self
+            .load_config_value_as("retry_policy")?
+            .unwrap_or_default();
+
+        // An id fetched twice in one go counts by its last outcome
+        let latest = outcomes
+            .iter()
+            .map(|outcome| (outcome.id(), outcome))
+            .collect::<HashMap<_, _>>();
+        if latest.is_empty() {
+            return Ok(());
+        }
+
+        let mut conn = self.db.get()?;
+        let tx = conn.transaction()?;
+
+        let failed = latest
+            .values()
+            .filter(|outcome| matches!(outcome, FetchOutcome::Failed { .. }))
+            .map(|outcome| outcome.id())
+            .collect::<Vec<_>>();
+        let previous = load_fetch_failures(&tx, &failed)?;
+
+        // Every outcome resolves what was recorded before, and the failures
+        // and missing ids are then recorded again
+        let ids = id_list(&latest.keys().copied().collect::<Vec<_>>());
+        tx.execute_batch(&format!(
+            r#"
+            DELETE FROM fetch_failure WHERE id IN ({0});
+            DELETE FROM missing_item WHERE id IN ({0});
+            "#,
+            ids
+        ))?;
+
+        let mut failure_appender = tx.appender("fetch_failure")?;
+        let mut missing_appender = tx.appender("missing_item")?;
+        for outcome in latest.values() {
+            match outcome {
+                FetchOutcome::Failed { id, kind, message } => {
+                    let failure =
+                        policy.record(previous.get(id), *id, kind.clone(), message.clone(), now);
+                    failure_appender.append_row(params![
+                        failure.id,
+                        failure.kind.to_string(),
+                        failure.message,
+                        failure.attempts,
+                        failure.first_failed,
+                        failure.next_attempt,
+                        failure.abandoned
+                    ])?;
+                }
+                FetchOutcome::Missing(id) => missing_appender.append_row(params![id, now])?,
+                _ => {}
+            }
+        }
+
+        // A failed flush aborts the transaction, which the commit reports
+        failure_appender.flush();
+        missing_appender.flush();
+        drop(failure_appender);
+        drop(missing_appender);
+        tx.commit()?;
+
+        Ok(())
+    }
+}
+
+impl LoadDueFetchFailures for AppCapabilities {
+    fn load_due_fetch_failures(&self, now: DateTime<Utc>, limit: u32) -> Result<Vec<FetchFailure>> {
+        let conn = self.db.get()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT
+                id, kind, message, attempts, first_failed, next_attempt, abandoned
+            FROM
+                fetch_failure
+            WHERE
+                next_attempt <= ?1
+            AND
+                NOT abandoned
+            ORDER BY
+                next_attempt
+            LIMIT ?2
+            "#,
+        )?;
+
+        let results = stmt
+            .query_map(params![now, limit], to_fetch_failure)?
+            .filter_map(Result::ok)
+            .collect();
+
+        Ok(results)
+    }
+}
+
+fn load_fetch_failures(
+    conn: &duckdb::Connection,
+    ids: &[u32],
+) -> Result<HashMap<u32, FetchFailure>> {
+    if ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let mut stmt = conn.prepare(&format!(
+        r#"
+        SELECT
+            id, kind, message, attempts, first_failed, next_attempt, abandoned
+        FROM
+            fetch_failure
+        WHERE
+            id IN ({})
+        "#,
+        id_list(ids)
+    ))?;
+
+    let failures = stmt
+        .query_map([], to_fetch_failure)?
+        .map(|failure| failure.map(|failure| (failure.id, failure)))
+        .collect::<Result<_, _>>()?;
+
+    Ok(failures)
+}
+
+// Integers, safe to inline like `load_items`
+fn id_list(ids: &[u32]) -> String {
+    ids.iter()
+        .map(|id| id.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn to_fetch_failure(row: &duckdb::Row) -> duckdb::Result<FetchFailure> {
+    let kind: String = row.get(1)?;
+    let first_failed: DateTime<Utc> = row.get(4)?;
+    let next_attempt: DateTime<Utc> = row.get(5)?;
+
+    Ok(FetchFailure {
+        id: row.get(0)?,
+        kind: FailureKind::from_str(&kind).map_err(|_| duckdb::Error::InvalidQuery)?,
+        message: row.get(2)?,
+        attempts: row.get(3)?,
+        first_failed,
+        next_attempt,
+        abandoned: row.get(6)?,
+    })
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use chrono::Duration;
+    use mockito::mock;
+
+    #[test]
+    fn ledger_records_and_resolves() {
+        let app = crate::adapters::test::setup();
+        let now = Utc::now();
+        let failed = FetchOutcome::Failed {
+            id: 1,
+            kind: FailureKind::Status(503),
+            message: "unavailable".into(),
+        };
+
+        app.update_fetch_ledger(&[failed.clone()], &now).unwrap();
+        app.update_fetch_ledger(&[failed], &now).unwrap();
+
+        // Not due until the backoff has passed
+        let got = app.load_due_fetch_failures(now, 10).unwrap();
+        assert_eq!(got, vec![]);
+
+        let got = app
+            .load_due_fetch_failures(now + Duration::hours(1), 10)
+            .unwrap();
+        assert_eq!(got.len(), 1);
+        assert_eq!(got[0].attempts, 2);
+        assert_eq!(got[0].kind, FailureKind::Status(503));
+
+        app.update_fetch_ledger(&[FetchOutcome::Missing(1)], &now)
+            .unwrap();
+        let got = app
+            .load_due_fetch_failures(now + Duration::hours(1), 10)
+            .unwrap();
+        assert_eq!(got, vec![]);
+    }
+
+    #[test]
+    fn ledger_takes_a_batch() {
+        let app = crate::adapters::test::setup();
+        let now = Utc::now();
+        let failed = |id| FetchOutcome::Failed {
+            id,
+            kind: FailureKind::Status(503),
+            message: "unavailable".into(),
+        };
+
+        app.update_fetch_ledger(&[failed(1), failed(2), FetchOutcome::Missing(3)], &now)
+            .unwrap();
+        app.update_fetch_ledger(
+            &[
+                failed(1),
+                FetchOutcome::Quarantined(2),
+                failed(3),
+                failed(3),
+            ],
+            &now,
+        )
+        .unwrap();
+
+        let mut got = app
+            .load_due_fetch_failures(now + Duration::hours(1), 10)
+            .unwrap()
+            .into_iter()
+            .map(|failure| (failure.id, failure.attempts))
+            .collect::<Vec<_>>();
+        got.sort_unstable();
+        assert_eq!(got, vec![(1, 2), (3, 1)]);
+    }
+
+    #[test]
+    fn abandoned_failures_are_not_due() {
+        let app = crate::adapters::test::setup();
+        let now = Utc::now();
+        let not_found = FetchOutcome::Failed {
+            id: 1,
+            kind: FailureKind::Status(404),
+            message: "not found".into(),
+        };
+
+        app.update_fetch_ledger(&[not_found], &now).unwrap();
+
+        let got = app
+            .load_due_fetch_failures(now + Duration::hours(1), 10)
+            .unwrap();
+        assert_eq!(got, vec![]);
+    }
+
+    #[test]
+    fn gaps_skip_accounted_ids() {
+        use crate::{capabilities::LoadItemGaps, domain::backfill::IdRange};
+
+        let app = crate::adapters::test::setup();
+        let now = Utc::now();
+        let failed = FetchOutcome::Failed {
+            id: 5,
+            kind: FailureKind::Status(404),
+            message: "not found".into(),
+        };
+
+        app.update_fetch_ledger(&[FetchOutcome::Missing(3), failed], &now)
+            .unwrap();
+
+        let got = app.load_item_gaps(6).unwrap();
+        let want = vec![
+            IdRange { start: 1, end: 2 },
+            IdRange { start: 4, end: 4 },
+            IdRange { start: 6, end: 6 },
+        ];
+
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn fetch_outcomes() {
+        let _found = mock("GET", "/item/19001.json")
+            .with_status(200)
+            .with_body(r#"{"id": 19001, "by": "pg", "parent": 1, "text": "Hi", "time": 3, "type": "comment"}"#)
+            .create();
+        let _missing = mock("GET", "/item/19002.json")
+            .with_status(200)
+            .with_body("null")
+            .create();
+        let _failed = mock("GET", "/item/19003.json").with_status(503).create();
+
+        let app = crate::adapters::test::setup();
+        let got = app.fetch_item_outcomes(vec![19001, 19002, 19003]).unwrap();
+
+        assert!(matches!(&got[0], FetchOutcome::Found(item) if item.id() == 19001));
+        assert_eq!(got[1], FetchOutcome::Missing(19002));
+        assert!(matches!(
+            &got[2],
+            FetchOutcome::Failed {
+                id: 19003,
+                kind: FailureKind::Status(503),
+                ..
+            }
+        ));
+
+        let failures = app
+            .load_due_fetch_failures(Utc::now() + Duration::hours(1), 10)
+            .unwrap();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].id, 19003);
+    }
+}
@@ -1,112 +1,391 @@
-use anyhow::Result;
-use chrono::Utc;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, TimeZone, Utc};
 use duckdb::{params, OptionalExt};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::atomic::Ordering,
+};
 
-use crate::{adapters::AppCapabilities, capabilities::*, infra::hn::types::Item};
+use crate::{
+    adapters::AppCapabilities,
+    capabilities::*,
+    domain::{
+        backfill::IdRange,
+        fetch::FetchOutcome,
+        version::{field_changes, ItemVersion},
+        ItemRecord,
+    },
+    infra::hn::{
+        block_on,
+        types::{Item, Updates},
+    },
+};
 
 impl FetchUpdates for AppCapabilities {
-    fn fetch_updates(&self) -> Result<Vec<u32>> {
-        Ok(self.client.get_updates()?.items)
+    fn fetch_updates(&self) -> Result<Updates> {
+        Ok(block_on(self.client.get_updates())?)
+    }
+}
+
+impl FetchMaxItemId for AppCapabilities {
+    fn fetch_max_item_id(&self) -> Result<u32> {
+        Ok(block_on(self.client.get_max_item_id())?)
     }
 }
 
 impl FetchItems for AppCapabilities {
     fn fetch_items(&self, ids: Vec<u32>) -> Result<Vec<Item>> {
-        Ok(self.client.get_items(ids)?)
+        Ok(self
+            .fetch_item_outcomes(ids)?
+            .into_iter()
+            .filter_map(FetchOutcome::into_item)
+            .collect())
     }
 }
 
 impl FetchItem for AppCapabilities {
     fn fetch_item(&self, id: u32) -> Result<Option<Item>> {
-        Ok(self.client.get_item(id)?)
+        match self.fetch_item_outcomes(vec![id])?.pop() {
+            Some(FetchOutcome::Failed { message, .. }) => {
+                Err(anyhow!("Couldn't fetch item {}: {}", id, message))
+            }
+            outcome => Ok(outcome.and_then(FetchOutcome::into_item)),
+        }
     }
 }
 
 impl StoreItems for AppCapabilities {
-    fn store_items(&self, items: Vec<Item>) -> Result<()> {
+    fn store_items(&self, items: Vec<Item>) -> Result<Vec<u32>> {
         let mut conn = self.db.get()?;
         let tx = conn.transaction()?;
 
         let ts = Utc::now();
+        let mut changed = vec![];
         for item in items {
             let original = serde_json::to_string(&item)?;
-            let _ = tx
-            .execute(
+            let previous: Option<String> = tx
+                .query_row(
+                    "SELECT original FROM item WHERE id = ?1",
+                    [item.id()],
+                    |row| row.get(0),
+                )
+                .optional()?;
+
+            // Nothing to record when the payload hasn't changed
+            if previous.as_deref() == Some(original.as_str()) {
+                continue;
+            }
+
+            let changes = match &previous {
+                Some(previous) => field_changes(
+                    &serde_json::from_str(previous)?,
+                    &serde_json::to_value(&item)?,
+                ),
+                None => vec![],
+            };
+            // The same id twice in one batch keeps the last
+            tx.execute(
+                "DELETE FROM item_version WHERE id = ?1 AND ts = ?2",
+                params![item.id(), ts],
+            )?;
+            tx.execute(
                 r#"
-                    INSERT INTO item (id, original, descendants, username, score, title, url, body, ts)
-                    VALUES 
-                    (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+                    INSERT INTO item_version (id, ts, original, changes)
+                    VALUES
+                    (?1, ?2, ?3, ?4)
                 "#,
-                params![
-                    item.id(),
-                    original,
-                    item.descendants(),
-                    item.username(),
-                    item.score(),
-                    item.title(),
-                    item.url(),
-                    item.body(),
-                    ts.clone(),
-                ]
+                params![item.id(), ts, original, serde_json::to_string(&changes)?],
             )?;
+
+            // Keep only the latest version in item. DuckDB has no upsert, so
+            // rows seen before are updated in place and the key on id turns
+            // a racing insert of the same item into an error, not a copy
+            let fields = params![
+                item.id(),
+                original,
+                item.descendants(),
+                item.author(),
+                item.score(),
+                item.title(),
+                item.url(),
+                item.body(),
+                ts,
+                item.is_deleted(),
+                item.is_dead(),
+                posted(&item),
+            ];
+            if previous.is_some() {
+                tx.execute(
+                    r#"
+                    UPDATE item
+                    SET original = ?2, descendants = ?3, username = ?4, score = ?5, title = ?6,
+                        url = ?7, body = ?8, ts = ?9, deleted = ?10, dead = ?11, time = ?12
+                    WHERE id = ?1
+                "#,
+                    fields,
+                )?;
+            } else {
+                tx.execute(
+                    r#"
+                    INSERT INTO item (id, original, descendants, username, score, title, url, body, ts, deleted, dead, time)
+                    VALUES
+                    (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+                "#,
+                    fields,
+                )?;
+            }
+            changed.push(item.id());
         }
         tx.commit()?;
 
-        Ok(())
+        if !changed.is_empty() {
+            self.search_index_stale.store(true, Ordering::SeqCst);
+        }
+
+        Ok(changed)
     }
 }
 
-impl StoreItem for AppCapabilities {
-    fn store_item(&self, item: Item) -> Result<()> {
-        let conn = self.db.get()?;
+impl StoreItemsBulk for AppCapabilities {
+    fn store_items_bulk(&self, items: Vec<Item>) -> Result<()> {
+        if items.is_empty() {
+            return Ok(());
+        }
 
-        let ts = Utc::now();
-        let original = serde_json::to_string(&item)?;
-        let _ = conn
-            .execute(
-                r#"
-                    INSERT INTO item (id, original, descendants, username, score, title, url, body, ts)
-                    VALUES 
-                    (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
-                "#,
-                params![
+        // Items seen before need a version diff, so they take the upsert
+        let stored = self.load_stored_item_ids(items.iter().map(Item::id).collect())?;
+        let (known, new): (Vec<_>, Vec<_>) = items
+            .into_iter()
+            .partition(|item| stored.contains(&item.id()));
+
+        // Appending an id twice would break the key, so the last one wins
+        let mut seen = HashSet::new();
+        let mut new = new
+            .into_iter()
+            .rev()
+            .filter(|item| seen.insert(item.id()))
+            .collect::<Vec<_>>();
+        new.reverse();
+
+        if !new.is_empty() {
+            let mut conn = self.db.get()?;
+            let tx = conn.transaction()?;
+            let ts = Utc::now();
+
+            // Appenders write straight into the table, so both go in one
+            // transaction and a batch lands whole or not at all
+            let mut item_appender = tx.appender("item")?;
+            let mut version_appender = tx.appender("item_version")?;
+            for item in new {
+                let original = serde_json::to_string(&item)?;
+                version_appender.append_row(params![item.id(), ts, original, "[]"])?;
+                item_appender.append_row(params![
                     item.id(),
                     original,
                     item.descendants(),
-                    item.username(),
+                    item.author(),
                     item.score(),
                     item.title(),
                     item.url(),
                     item.body(),
-                    ts.clone(),
-                ]
-            )?;
+                    ts,
+                    item.is_deleted(),
+                    item.is_dead(),
+                    posted(&item),
+                ])?;
+            }
+
+            // Flushing here rather than on drop, as a failed flush aborts the
+            // transaction and the commit then reports it
+            item_appender.flush();
+            version_appender.flush();
+            drop(item_appender);
+            drop(version_appender);
+            tx.commit()?;
+
+            self.search_index_stale.store(true, Ordering::SeqCst);
+        }
+
+        self.store_items(known)?;
 
         Ok(())
     }
 }
 
-impl LoadItems for AppCapabilities {
-    fn load_items(&self, ids: Vec<u32>) -> Result<Vec<Item>> {
+impl LoadStoredItemIds for AppCapabilities {
+    fn load_stored_item_ids(&self, ids: Vec<u32>) -> Result<HashSet<u32>> {
+        if ids.is_empty() {
+            return Ok(HashSet::new());
+        }
+
         let conn = self.db.get()?;
 
-        let mut stmt = conn.prepare("SELECT original FROM item WHERE id = ?1")?;
+        // Integers, safe to inline like `load_items`
+        let in_list = ids
+            .iter()
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut stmt = conn.prepare(&format!("SELECT id FROM item WHERE id IN ({})", in_list))?;
+
+        let ids = stmt
+            .query_map([], |row| row.get(0))?
+            .filter_map(Result::ok)
+            .collect();
+
+        Ok(ids)
+    }
+}
 
-        let mut results = vec![];
-        for id in ids {
-            if let Some(item) = stmt
-                .query_row([id], |row| {
-                    let blob: String = row.get(0)?;
-                    let item: Item = serde_json::from_str(&blob).unwrap();
-                    Ok(item)
+impl StoreItem for AppCapabilities {
+    fn store_item(&self, item: Item) -> Result<()> {
+        self.store_items(vec![item])?;
+
+        Ok(())
+    }
+}
+
+impl LoadItemVersions for AppCapabilities {
+    fn load_item_versions(&self, id: u32) -> Result<Vec<ItemVersion>> {
+        let conn = self.db.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, ts, original, changes FROM item_version WHERE id = ?1 ORDER BY ts",
+        )?;
+
+        let results = stmt
+            .query_map([id], |row| {
+                let ts: DateTime<Utc> = row.get(1)?;
+                let changes: String = row.get(3)?;
+
+                Ok(ItemVersion {
+                    id: row.get(0)?,
+                    ts,
+                    original: row.get(2)?,
+                    changes: serde_json::from_str(&changes)
+                        .map_err(|_| duckdb::Error::InvalidQuery)?,
                 })
-                .optional()?
-            {
-                results.push(item);
+            })?
+            .filter_map(Result::ok)
+            .collect();
+
+        Ok(results)
+    }
+}
+
+// Ids up to ?1 that were accounted for one way or another: stored,
+// quarantined, in the failure ledger or null on HN
+const KNOWN_IDS: &str = r#"
+    SELECT
+        id
+    FROM (
+        SELECT id FROM item
+        UNION SELECT id FROM raw_item
+        UNION SELECT id FROM fetch_failure
+        UNION SELECT id FROM missing_item
+    )
+    WHERE
+        id <= ?1
+"#;
+
+impl LoadItemGaps for AppCapabilities {
+    fn load_item_gaps(&self, through: u32) -> Result<Vec<IdRange>> {
+        let conn = self.db.get()?;
+        let (min, max): (Option<u32>, Option<u32>) = conn.query_row(
+            &format!("SELECT min(id), max(id) FROM ({})", KNOWN_IDS),
+            [through],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+
+        let (min, max) = match (min, max) {
+            (Some(min), Some(max)) => (min, max),
+            _ if through > 0 => {
+                return Ok(vec![IdRange {
+                    start: 1,
+                    end: through,
+                }])
             }
+            _ => return Ok(vec![]),
+        };
+
+        // Between known ids
+        let mut stmt = conn.prepare(&format!(
+            r#"
+            SELECT
+                id + 1, next_id - 1
+            FROM (
+                SELECT
+                    id, lead(id) OVER (ORDER BY id) AS next_id
+                FROM
+                    ({})
+            )
+            WHERE
+                next_id > id + 1
+            ORDER BY
+                id
+            "#,
+            KNOWN_IDS
+        ))?;
+        let between = stmt
+            .query_map([through], |row| {
+                Ok(IdRange {
+                    start: row.get(0)?,
+                    end: row.get(1)?,
+                })
+            })?
+            .filter_map(Result::ok);
+
+        // And either side of them
+        let mut gaps = vec![];
+        if min > 1 {
+            gaps.push(IdRange {
+                start: 1,
+                end: min - 1,
+            });
+        }
+        gaps.extend(between);
+        if max < through {
+            gaps.push(IdRange {
+                start: max + 1,
+                end: through,
+            });
         }
 
-        Ok(results)
+        Ok(gaps)
+    }
+}
+
+impl LoadItems for AppCapabilities {
+    fn load_items(&self, ids: Vec<u32>) -> Result<Vec<Item>> {
+        if ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let conn = self.db.get()?;
+
+        // Ids are integers so they are safe to inline, and this keeps the
+        // whole batch to a single round-trip
+        let in_list = ids
+            .iter()
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut stmt = conn.prepare(&format!(
+            "SELECT original FROM item WHERE id IN ({})",
+            in_list
+        ))?;
+
+        let by_id = stmt
+            .query_map([], |row| {
+                let blob: String = row.get(0)?;
+                let item: Item = serde_json::from_str(&blob).unwrap();
+                Ok(item)
+            })?
+            .filter_map(Result::ok)
+            .map(|item| (item.id(), item))
+            .collect::<HashMap<_, _>>();
+
+        // Return in the order requested
+        Ok(ids.iter().filter_map(|id| by_id.get(id).cloned()).collect())
     }
 }
 
@@ -125,6 +404,46 @@ impl LoadItem for AppCapabilities {
     }
 }
 
+impl LoadItemRecord for AppCapabilities {
+    fn load_item_record(&self, id: u32) -> Result<Option<ItemRecord>> {
+        let conn = self.db.get()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT
+                id, original, descendants, username, score, title, url, body, ts,
+                coalesce(deleted, FALSE), coalesce(dead, FALSE)
+            FROM
+                item
+            WHERE
+                id = ?1
+            "#,
+        )?;
+
+        Ok(stmt
+            .query_row([id], |row| {
+                Ok(ItemRecord {
+                    id: row.get(0)?,
+                    original: row.get(1)?,
+                    descendants: row.get(2)?,
+                    username: row.get(3)?,
+                    score: row.get(4)?,
+                    title: row.get(5)?,
+                    url: row.get(6)?,
+                    body: row.get(7)?,
+                    ts: row.get(8)?,
+                    deleted: row.get(9)?,
+                    dead: row.get(10)?,
+                })
+            })
+            .optional()?)
+    }
+}
+
+// When the item was posted, which date filters go by
+fn posted(item: &Item) -> Option<DateTime<Utc>> {
+    item.ts().map(|time| Utc.timestamp(*time as i64, 0))
+}
+
 #[cfg(test)]
 pub mod test {
     use super::*;
@@ -135,9 +454,27 @@ pub mod test {
         let app = crate::adapters::test::setup();
         let items = sample_items();
         let ids = items.iter().map(|i| i.id()).collect::<Vec<_>>();
-        let _ = app.store_items(items.clone()).unwrap();
+        let changed = app.store_items(items.clone()).unwrap();
+        assert_eq!(changed, ids);
+
         let got = app.load_items(ids).unwrap();
-        let want = items;
+        let want = items.clone();
+        assert_eq!(got, want);
+
+        // Storing the same payloads again changes nothing
+        let changed = app.store_items(items).unwrap();
+        assert!(changed.is_empty());
+    }
+
+    #[test]
+    fn load_items_in_requested_order() {
+        let app = crate::adapters::test::setup();
+        let items = sample_items();
+        let _ = app.store_items(items.clone()).unwrap();
+        let got = app
+            .load_items(vec![items[2].id(), 404, items[0].id()])
+            .unwrap();
+        let want = vec![items[2].clone(), items[0].clone()];
 
         assert_eq!(got, want);
     }
@@ -153,4 +490,142 @@ pub mod test {
 
         assert_eq!(got, want);
     }
+
+    #[test]
+    fn load_item_record() {
+        let app = crate::adapters::test::setup();
+        let items = sample_items();
+        let item = &items[0];
+        let _ = app.store_item(item.clone()).unwrap();
+        let got = app.load_item_record(item.id()).unwrap().unwrap();
+
+        assert_eq!(got.id, item.id());
+        assert_eq!(got.original, serde_json::to_string(item).unwrap());
+        assert_eq!(got.title.as_deref(), item.title());
+        assert_eq!(got.score, item.score());
+    }
+
+    #[test]
+    fn store_item_versions() {
+        let app = crate::adapters::test::setup();
+        let items = sample_items();
+        let item = items[0].clone();
+        let mut edited: serde_json::Value = serde_json::to_value(&item).unwrap();
+        edited["title"] = "Dropbox".into();
+        let edited: Item = serde_json::from_value(edited).unwrap();
+
+        let _ = app.store_item(item.clone()).unwrap();
+        // Storing the same payload again doesn't add a version
+        let _ = app.store_item(item.clone()).unwrap();
+        let _ = app.store_item(edited.clone()).unwrap();
+
+        let got = app.load_item(item.id()).unwrap();
+        let want = Some(edited);
+        assert_eq!(got, want);
+
+        let got = app.load_item_versions(item.id()).unwrap();
+        assert_eq!(got.len(), 2);
+        assert!(got[0].changes.is_empty());
+        assert_eq!(got[1].changes.len(), 1);
+        assert_eq!(got[1].changes[0].field, "title");
+        assert_eq!(got[1].changes[0].to, "Dropbox");
+    }
+
+    #[test]
+    fn store_items_keeps_one_row_per_id() {
+        let app = crate::adapters::test::setup();
+        let items = sample_items();
+        let _ = app.store_items(items.clone()).unwrap();
+        let _ = app.store_items(items.clone()).unwrap();
+
+        let conn = app.db.get().unwrap();
+        let got: u32 = conn
+            .query_row("SELECT count(*) FROM item", [], |row| row.get(0))
+            .unwrap();
+        let want = items.len() as u32;
+
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn store_tombstone() {
+        let app = crate::adapters::test::setup();
+        let item: Item = serde_json::from_str(
+            r#"{"deleted": true, "id": 2921984, "parent": 2921506, "time": 1314211130, "type": "comment"}"#,
+        )
+        .unwrap();
+        let _ = app.store_item(item.clone()).unwrap();
+        let got = app.load_item_record(item.id()).unwrap().unwrap();
+
+        assert!(got.deleted);
+        assert!(!got.dead);
+        assert_eq!(got.username, None);
+    }
+
+    #[test]
+    fn load_item_gaps() {
+        let app = crate::adapters::test::setup();
+        let items = sample_items();
+        let mut ids = items.iter().map(|i| i.id()).collect::<Vec<_>>();
+        ids.sort_unstable();
+        let _ = app.store_items(items).unwrap();
+
+        let got = app.load_item_gaps(ids[ids.len() - 1] + 10).unwrap();
+
+        assert_eq!(
+            got.first(),
+            Some(&IdRange {
+                start: 1,
+                end: ids[0] - 1
+            })
+        );
+        assert_eq!(
+            got.last(),
+            Some(&IdRange {
+                start: ids[ids.len() - 1] + 1,
+                end: ids[ids.len() - 1] + 10
+            })
+        );
+        let missing = got.iter().map(|gap| gap.end - gap.start + 1).sum::<u32>();
+        assert_eq!(missing, ids[ids.len() - 1] + 10 - ids.len() as u32);
+    }
+
+    #[test]
+    fn load_item_gaps_empty() {
+        let app = crate::adapters::test::setup();
+
+        assert_eq!(
+            app.load_item_gaps(5).unwrap(),
+            vec![IdRange { start: 1, end: 5 }]
+        );
+        assert_eq!(app.load_item_gaps(0).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn store_items_bulk() {
+        let app = crate::adapters::test::setup();
+        let items = sample_items();
+        let mut edited: serde_json::Value = serde_json::to_value(&items[0]).unwrap();
+        edited["title"] = "Dropbox".into();
+        let edited: Item = serde_json::from_value(edited).unwrap();
+
+        let _ = app.store_items(vec![items[0].clone()]).unwrap();
+        let mut batch = items[1..].to_vec();
+        batch.push(edited.clone());
+        let _ = app.store_items_bulk(batch).unwrap();
+
+        let mut want = items.clone();
+        want[0] = edited;
+        let got = app
+            .load_items(want.iter().map(|i| i.id()).collect())
+            .unwrap();
+        assert_eq!(got, want);
+
+        // New items get a first version, the known one a diff
+        let got = app.load_item_versions(items[1].id()).unwrap();
+        assert_eq!(got.len(), 1);
+        let got = app.load_item_versions(items[0].id()).unwrap();
+        assert_eq!(got.len(), 2);
+        assert_eq!(got[1].changes[0].field, "title");
+    }
 }
@@ -1,13 +1,15 @@
-use std::str::FromStr;
+use std::{collections::HashMap, str::FromStr};
 
 use anyhow::Result;
 use chrono::{DateTime, Utc};
-use duckdb::{params, DropBehavior, OptionalExt};
+use duckdb::{params, DropBehavior};
 
 use crate::{
     adapters::AppCapabilities,
-    capabilities::{LoadItemRanks, LoadLatestItemRank, StoreItemRanks},
-    domain::{ItemRank, ListCategory},
+    capabilities::{
+        LoadItemRanks, LoadLatestItemRanks, LoadListExits, StoreItemRanks, StoreListExits,
+    },
+    domain::{ItemRank, ListCategory, ListExit},
 };
 
 impl StoreItemRanks for AppCapabilities {
@@ -54,27 +56,45 @@ impl LoadItemRanks for AppCapabilities {
     }
 }
 
-impl LoadLatestItemRank for AppCapabilities {
-    fn load_latest_item_rank(&self, id: u32, category: ListCategory) -> Result<Option<ItemRank>> {
+impl LoadLatestItemRanks for AppCapabilities {
+    fn load_latest_item_ranks(
+        &self,
+        ids: Vec<u32>,
+        category: ListCategory,
+    ) -> Result<HashMap<u32, ItemRank>> {
+        if ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
         let conn = self.db.get()?;
-        let mut stmt = conn.prepare(
+        let in_list = ids
+            .iter()
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut stmt = conn.prepare(&format!(
             r#"
             SELECT
-                id, rank, category, ts 
-            FROM 
-                item_rank 
-            WHERE 
-                id = ?1 
-            AND 
-                category = ?2
-            ORDER BY 
-                ts DESC
-            LIMIT 1
+                id, rank, category, ts
+            FROM (
+                SELECT
+                    id, rank, category, ts,
+                    row_number() OVER (PARTITION BY id ORDER BY ts DESC) AS n
+                FROM
+                    item_rank
+                WHERE
+                    category = ?1
+                AND
+                    id IN ({})
+            ) ranks
+            WHERE
+                n = 1
             "#,
-        )?;
+            in_list
+        ))?;
 
         let results = stmt
-            .query_row(params![id, category.to_string()], |row| {
+            .query_map(params![category.to_string()], |row| {
                 let cat: String = row.get(2)?;
                 let ts: DateTime<Utc> = row.get(3)?;
 
@@ -85,8 +105,53 @@ impl LoadLatestItemRank for AppCapabilities {
                         .map_err(|_| duckdb::Error::InvalidQuery)?,
                     ts,
                 })
-            })
-            .optional()?;
+            })?
+            .filter_map(Result::ok)
+            .map(|rank| (rank.id, rank))
+            .collect();
+
+        Ok(results)
+    }
+}
+
+impl StoreListExits for AppCapabilities {
+    fn store_list_exits(&self, list_exits: Vec<ListExit>) -> Result<()> {
+        let mut conn = self.db.get()?;
+        let mut tx = conn.transaction()?;
+        tx.set_drop_behavior(DropBehavior::Commit);
+
+        for exit in list_exits.into_iter() {
+            tx.execute(
+                "INSERT INTO item_list_exit (id, category, ts) VALUES (?1, ?2, ?3)",
+                params![exit.id, exit.category.to_string(), exit.ts],
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl LoadListExits for AppCapabilities {
+    fn load_list_exits(&self, id: u32, category: ListCategory) -> Result<Vec<ListExit>> {
+        let conn = self.db.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, category, ts FROM item_list_exit WHERE id = ?1 AND category = ?2 ORDER BY ts",
+        )?;
+
+        let results = stmt
+            .query_map(params![id, category.to_string()], |row| {
+                let cat: String = row.get(1)?;
+                let ts: DateTime<Utc> = row.get(2)?;
+
+                Ok(ListExit {
+                    id: row.get(0)?,
+                    category: ListCategory::from_str(&cat)
+                        .map_err(|_| duckdb::Error::InvalidQuery)?,
+                    ts,
+                })
+            })?
+            .filter_map(Result::ok)
+            .collect();
 
         Ok(results)
     }
@@ -113,4 +178,62 @@ pub mod test {
 
         assert_eq!(got, want);
     }
+
+    #[test]
+    fn load_latest_item_ranks() {
+        let app = crate::adapters::test::setup();
+        let rank = |id, rank, day, category| ItemRank {
+            id,
+            rank,
+            ts: Utc.ymd(2020, 1, day).and_hms(0, 0, 0),
+            category,
+        };
+        app.store_item_ranks(vec![
+            rank(1, 3, 1, ListCategory::Top),
+            rank(1, 2, 2, ListCategory::Top),
+            rank(1, 9, 3, ListCategory::New),
+            rank(2, 5, 1, ListCategory::Top),
+            rank(3, 1, 1, ListCategory::Top),
+        ])
+        .unwrap();
+
+        let got = app
+            .load_latest_item_ranks(vec![1, 2, 4], ListCategory::Top)
+            .unwrap();
+        let want = vec![
+            (1, rank(1, 2, 2, ListCategory::Top)),
+            (2, rank(2, 5, 1, ListCategory::Top)),
+        ]
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn store_list_exits() {
+        let app = crate::adapters::test::setup();
+        let exits = vec![
+            ListExit {
+                id: 1,
+                category: ListCategory::Top,
+                ts: Utc.ymd(2020, 1, 2).and_hms(0, 0, 0),
+            },
+            ListExit {
+                id: 1,
+                category: ListCategory::Top,
+                ts: Utc.ymd(2020, 1, 1).and_hms(0, 0, 0),
+            },
+            ListExit {
+                id: 1,
+                category: ListCategory::New,
+                ts: Utc.ymd(2020, 1, 1).and_hms(0, 0, 0),
+            },
+        ];
+        app.store_list_exits(exits.clone()).unwrap();
+        let got = app.load_list_exits(1, ListCategory::Top).unwrap();
+        let want = vec![exits[1].clone(), exits[0].clone()];
+
+        assert_eq!(got, want);
+    }
 }
@@ -0,0 +1,172 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use duckdb::{params, DropBehavior, OptionalExt};
+
+use crate::{
+    adapters::AppCapabilities,
+    capabilities::{LoadItemScoreAt, LoadItemScores, LoadLatestItemScore, StoreItemScores},
+    domain::ItemScore,
+};
+
+impl StoreItemScores for AppCapabilities {
+    fn store_item_scores(&self, item_scores: Vec<ItemScore>) -> Result<()> {
+        let mut conn = self.db.get()?;
+        let mut tx = conn.transaction()?;
+        tx.set_drop_behavior(DropBehavior::Commit);
+
+        for item_score in item_scores.into_iter() {
+            tx.execute(
+                "INSERT INTO item_score (id, score, descendants, ts) VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    item_score.id,
+                    item_score.score,
+                    item_score.descendants,
+                    item_score.ts
+                ],
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl LoadItemScores for AppCapabilities {
+    fn load_item_scores(&self, id: u32) -> Result<Vec<ItemScore>> {
+        let conn = self.db.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, score, descendants, ts FROM item_score WHERE id = ?1 ORDER BY ts",
+        )?;
+
+        let results = stmt
+            .query_map(params![id], |row| {
+                let ts: DateTime<Utc> = row.get(3)?;
+
+                Ok(ItemScore {
+                    id: row.get(0)?,
+                    score: row.get(1)?,
+                    descendants: row.get(2)?,
+                    ts,
+                })
+            })?
+            .filter_map(Result::ok)
+            .collect();
+
+        Ok(results)
+    }
+}
+
+impl LoadLatestItemScore for AppCapabilities {
+    fn load_latest_item_score(&self, id: u32) -> Result<Option<ItemScore>> {
+        let conn = self.db.get()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT
+                id, score, descendants, ts
+            FROM
+                item_score
+            WHERE
+                id = ?1
+            ORDER BY
+                ts DESC
+            LIMIT 1
+            "#,
+        )?;
+
+        let results = stmt
+            .query_row(params![id], |row| {
+                let ts: DateTime<Utc> = row.get(3)?;
+
+                Ok(ItemScore {
+                    id: row.get(0)?,
+                    score: row.get(1)?,
+                    descendants: row.get(2)?,
+                    ts,
+                })
+            })
+            .optional()?;
+
+        Ok(results)
+    }
+}
+
+impl LoadItemScoreAt for AppCapabilities {
+    fn load_item_score_at(&self, id: u32, ts: DateTime<Utc>) -> Result<Option<ItemScore>> {
+        let conn = self.db.get()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT
+                id, score, descendants, ts
+            FROM
+                item_score
+            WHERE
+                id = ?1
+            AND
+                ts <= ?2
+            ORDER BY
+                ts DESC
+            LIMIT 1
+            "#,
+        )?;
+
+        let results = stmt
+            .query_row(params![id, ts], |row| {
+                let ts: DateTime<Utc> = row.get(3)?;
+
+                Ok(ItemScore {
+                    id: row.get(0)?,
+                    score: row.get(1)?,
+                    descendants: row.get(2)?,
+                    ts,
+                })
+            })
+            .optional()?;
+
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn store_item_scores() {
+        let app = crate::adapters::test::setup();
+        let scores = vec![
+            ItemScore {
+                id: 1,
+                score: 10,
+                descendants: Some(2),
+                ts: Utc.ymd(2020, 1, 1).and_hms_milli(0, 0, 1, 0),
+            },
+            ItemScore {
+                id: 1,
+                score: 12,
+                descendants: Some(3),
+                ts: Utc.ymd(2020, 1, 1).and_hms_milli(0, 5, 1, 0),
+            },
+        ];
+        let _ = app.store_item_scores(scores.clone()).unwrap();
+
+        let got = app.load_item_scores(1).unwrap();
+        let want = scores.clone();
+        assert_eq!(got, want);
+
+        let got = app.load_latest_item_score(1).unwrap();
+        let want = Some(scores[1].clone());
+        assert_eq!(got, want);
+
+        let got = app
+            .load_item_score_at(1, Utc.ymd(2020, 1, 1).and_hms(0, 3, 0))
+            .unwrap();
+        let want = Some(scores[0].clone());
+        assert_eq!(got, want);
+
+        let got = app
+            .load_item_score_at(1, Utc.ymd(2019, 1, 1).and_hms(0, 0, 0))
+            .unwrap();
+        let want = None;
+        assert_eq!(got, want);
+    }
+}
@@ -1,42 +1,85 @@
 use anyhow::Result;
+use std::collections::BTreeMap;
 
 use crate::{
     adapters::AppCapabilities,
-    capabilities::{FetchList, LoadList, ReplaceList, StoreList},
-    domain::ListCategory,
+    capabilities::{
+        FetchList, LoadConfigValue, LoadItems, LoadList, LoadListAt, ReplaceList, StoreList,
+    },
+    domain::{
+        rising::{rising_ids, RisingConfig},
+        snapshot::{apply_delta, snapshot_delta, SnapshotDelta, KEYFRAME_INTERVAL},
+        ItemScore, ListCategory,
+    },
+    infra::hn::block_on,
 };
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use duckdb::{params, OptionalExt};
 
 impl FetchList for AppCapabilities {
     fn fetch_list(&self, category: ListCategory) -> Result<Vec<u32>> {
-        Ok(self.client.get_stories_list(category)?)
+        Ok(block_on(self.client.get_stories_list(category))?)
     }
 }
 
 impl ReplaceList for AppCapabilities {
-    fn replace_list(&self, category: ListCategory, ids: &[u32]) -> Result<()> {
-        let conn = self.db.get()?;
-        let _ = conn.execute(
+    fn replace_list(&self, category: ListCategory, ids: &[u32], ts: &DateTime<Utc>) -> Result<()> {
+        let mut conn = self.db.get()?;
+        let tx = conn.transaction()?;
+
+        // Keep the history, skipping fetches where nothing moved
+        let previous = list_at(&tx, &category, ts)?;
+        if previous.as_deref() != Some(ids) {
+            let since_keyframe: u32 = tx.query_row(
+                r#"
+                SELECT
+                    count(*)
+                FROM
+                    list_snapshot
+                WHERE
+                    category = ?1
+                AND
+                    ts <= ?2
+                AND
+                    ts >= (SELECT max(ts) FROM list_snapshot WHERE category = ?1 AND keyframe AND ts <= ?2)
+                "#,
+                params![category.to_string(), ts],
+                |row| row.get(0),
+            )?;
+
+            // A keyframe is a delta from nothing, the full list
+            let keyframe = previous.is_none() || since_keyframe >= KEYFRAME_INTERVAL;
+            let delta = match previous {
+                Some(previous) if !keyframe => snapshot_delta(&previous, ids),
+                _ => snapshot_delta(&[], ids),
+            };
+            store_snapshot(
+                &tx,
+                &category.to_string(),
+                ts,
+                ids.len() as u32,
+                keyframe,
+                &delta,
+            )?;
+        }
+
+        // And the current list for quick reads
+        tx.execute(
             "DELETE FROM item_list WHERE category = ?1",
             params![category.to_string()],
         )?;
-
-        // LOL
-        let _ = conn.execute(
+        tx.execute(
             r#"
             INSERT INTO
                 item_list (category, ids, ts)
             VALUES
                 (?1, ?2, ?3)
             "#,
-            params![
-                category.to_string(),
-                serde_json::to_string(&ids)?,
-                Utc::now()
-            ],
+            params![category.to_string(), serde_json::to_string(&ids)?, ts],
         )?;
 
+        tx.commit()?;
+
         Ok(())
     }
 }
@@ -62,8 +105,58 @@ impl StoreList for AppCapabilities {
     }
 }
 
+impl AppCapabilities {
+    fn load_rising_list(&self) -> Result<Vec<u32>> {
+        let config: RisingConfig = self
+            .load_config_value_as("rising_config")?
+            .unwrap_or_default();
+        let now = Utc::now();
+
+        // Everything scored within the window, plus the history before it
+        let conn = self.db.get()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT
+                id, score, descendants, ts
+            FROM
+                item_score
+            WHERE
+                id IN (SELECT DISTINCT id FROM item_score WHERE ts >= ?1)
+            "#,
+        )?;
+        let snapshots = stmt
+            .query_map(params![config.window_start(&now)], |row| {
+                let ts: DateTime<Utc> = row.get(3)?;
+
+                Ok(ItemScore {
+                    id: row.get(0)?,
+                    score: row.get(1)?,
+                    descendants: row.get(2)?,
+                    ts,
+                })
+            })?
+            .filter_map(Result::ok)
+            .collect::<Vec<_>>();
+
+        let mut ids = snapshots.iter().map(|s| s.id).collect::<Vec<_>>();
+        ids.sort_unstable();
+        ids.dedup();
+        let created = self
+            .load_items(ids)?
+            .iter()
+            .filter_map(|item| Some((item.id(), *item.ts()?)))
+            .collect();
+
+        Ok(rising_ids(&snapshots, &created, &now, &config))
+    }
+}
+
 impl LoadList for AppCapabilities {
     fn load_list(&self, category: ListCategory) -> Result<Vec<u32>> {
+        if category == ListCategory::Rising {
+            return self.load_rising_list();
+        }
+
         let conn = self.db.get()?;
         let mut stmt = conn.prepare("SELECT ids FROM item_list WHERE category=?1")?;
         let ids_str: Option<String> = stmt
@@ -77,6 +170,132 @@ impl LoadList for AppCapabilities {
     }
 }
 
+impl LoadListAt for AppCapabilities {
+    fn load_list_at(&self, category: ListCategory, ts: DateTime<Utc>) -> Result<Vec<u32>> {
+        let conn = self.db.get()?;
+
+        Ok(list_at(&conn, &category, &ts)?.unwrap_or_default())
+    }
+}
+
+/// Rebuild the list as of `ts` from the latest keyframe at or before it and
+/// the deltas since.
+fn list_at(
+    conn: &duckdb::Connection,
+    category: &ListCategory,
+    ts: &DateTime<Utc>,
+) -> Result<Option<Vec<u32>>> {
+    let keyframe: Option<DateTime<Utc>> = conn
+        .query_row(
+            r#"
+            SELECT
+                ts
+            FROM
+                list_snapshot
+            WHERE
+                category = ?1
+            AND
+                keyframe
+            AND
+                ts <= ?2
+            ORDER BY
+                ts DESC
+            LIMIT 1
+            "#,
+            params![category.to_string(), ts],
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    let keyframe = match keyframe {
+        Some(keyframe) => keyframe,
+        None => return Ok(None),
+    };
+
+    // The keyframe's own entries are the first delta, from an empty list
+    let mut deltas: BTreeMap<DateTime<Utc>, SnapshotDelta> = BTreeMap::new();
+    let mut stmt = conn.prepare(
+        r#"
+        SELECT
+            ts, rank, id
+        FROM
+            list_snapshot_entry
+        WHERE
+            category = ?1
+        AND
+            ts >= ?2
+        AND
+            ts <= ?3
+        "#,
+    )?;
+    let entries = stmt.query_map(params![category.to_string(), keyframe, ts], |row| {
+        let ts: DateTime<Utc> = row.get(0)?;
+        Ok((ts, row.get(1)?, row.get(2)?))
+    })?;
+    for entry in entries {
+        let (ts, rank, id) = entry?;
+        deltas.entry(ts).or_default().ranked.push((rank, id));
+    }
+
+    let mut stmt = conn.prepare(
+        r#"
+        SELECT
+            ts, id
+        FROM
+            list_snapshot_exit
+        WHERE
+            category = ?1
+        AND
+            ts > ?2
+        AND
+            ts <= ?3
+        "#,
+    )?;
+    let exits = stmt.query_map(params![category.to_string(), keyframe, ts], |row| {
+        let ts: DateTime<Utc> = row.get(0)?;
+        Ok((ts, row.get(1)?))
+    })?;
+    for exit in exits {
+        let (ts, id) = exit?;
+        deltas.entry(ts).or_default().exited.push(id);
+    }
+
+    let ids = deltas
+        .values()
+        .fold(vec![], |list, delta| apply_delta(&list, delta));
+
+    Ok(Some(ids))
+}
+
+/// Record a snapshot of a list, either a keyframe or a delta.
+fn store_snapshot(
+    conn: &duckdb::Connection,
+    category: &str,
+    ts: &DateTime<Utc>,
+    size: u32,
+    keyframe: bool,
+    delta: &SnapshotDelta,
+) -> Result<()> {
+    conn.execute(
+        "INSERT INTO list_snapshot (category, ts, size, keyframe) VALUES (?1, ?2, ?3, ?4)",
+        params![category, ts, size, keyframe],
+    )?;
+    for (rank, id) in &delta.ranked {
+        conn.execute(
+            "INSERT INTO list_snapshot_entry (category, ts, rank, id) VALUES (?1, ?2, ?3, ?4)",
+            params![category, ts, rank, id],
+        )?;
+    }
+    for id in &delta.exited {
+        conn.execute(
+            "INSERT INTO list_snapshot_exit (category, ts, id) VALUES (?1, ?2, ?3)",
+            params![category, ts, id],
+        )?;
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 pub mod test {
     use super::*;
@@ -100,10 +319,123 @@ pub mod test {
 
         assert_eq!(got, want);
 
-        let _ = app.replace_list(ListCategory::Top, &[2, 3, 4]).unwrap();
+        let _ = app
+            .replace_list(ListCategory::Top, &[2, 3, 4], &Utc::now())
+            .unwrap();
         let got = app.load_list(ListCategory::Top).unwrap();
         let want = vec![2, 3, 4];
 
         assert_eq!(got, want);
     }
+
+    #[test]
+    fn list_snapshots() {
+        use chrono::TimeZone;
+
+        let app = crate::adapters::test::setup();
+        let at = |hour| Utc.ymd(2020, 1, 1).and_hms(hour, 0, 0);
+        let _ = app
+            .replace_list(ListCategory::Top, &[1, 2, 3], &at(1))
+            .unwrap();
+        let _ = app.replace_list(ListCategory::Top, &[2, 1], &at(2)).unwrap();
+        let _ = app
+            .replace_list(ListCategory::Top, &[2, 1, 4], &at(3))
+            .unwrap();
+        let _ = app
+            .replace_list(ListCategory::Top, &[2, 1, 4], &at(4))
+            .unwrap();
+
+        assert_eq!(app.load_list_at(ListCategory::Top, at(0)).unwrap(), vec![]);
+        assert_eq!(
+            app.load_list_at(ListCategory::Top, at(1)).unwrap(),
+            vec![1, 2, 3]
+        );
+        assert_eq!(app.load_list_at(ListCategory::Top, at(2)).unwrap(), vec![2, 1]);
+        assert_eq!(
+            app.load_list_at(ListCategory::Top, at(5)).unwrap(),
+            vec![2, 1, 4]
+        );
+        assert_eq!(app.load_list_at(ListCategory::New, at(5)).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn list_snapshot_keyframes() {
+        use chrono::{Duration, TimeZone};
+
+        let app = crate::adapters::test::setup();
+        let start = Utc.ymd(2020, 1, 1).and_hms(0, 0, 0);
+        let at = |i: u32| start + Duration::minutes(i as i64);
+
+        // A new story on top every time, pushing the last one off
+        let list = |i: u32| (i..i + 30).rev().collect::<Vec<_>>();
+        let count = KEYFRAME_INTERVAL + 5;
+        for i in 0..count {
+            let _ = app
+                .replace_list(ListCategory::Top, &list(i), &at(i))
+                .unwrap();
+        }
+
+        for i in 0..count {
+            assert_eq!(app.load_list_at(ListCategory::Top, at(i)).unwrap(), list(i));
+        }
+
+        let conn = app.db.get().unwrap();
+        let (keyframes, entries): (u32, u32) = conn
+            .query_row(
+                r#"
+                SELECT
+                    (SELECT count(*) FROM list_snapshot WHERE keyframe),
+                    (SELECT count(*) FROM list_snapshot_entry)
+                "#,
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(keyframes, 2);
+        // Two full lists, and one entry for each delta
+        assert_eq!(entries, 2 * 30 + count - 2);
+    }
+
+    #[test]
+    fn rising_list() {
+        use crate::{
+            capabilities::{StoreConfigValue, StoreItemScores},
+            domain::ItemScore,
+        };
+        use chrono::Duration;
+
+        let app = crate::adapters::test::setup();
+        let now = Utc::now();
+        let _ = app
+            .store_config_value(
+                "rising_config",
+                RisingConfig {
+                    gravity: 0.0,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let snapshot = |id, score, hours_ago| ItemScore {
+            id,
+            score,
+            descendants: None,
+            ts: now - Duration::hours(hours_ago),
+        };
+        let _ = app
+            .store_item_scores(vec![
+                snapshot(1, 10, 10),
+                snapshot(1, 40, 1),
+                snapshot(2, 10, 2),
+                snapshot(2, 50, 1),
+                // Nothing new within the window
+                snapshot(3, 10, 12),
+                snapshot(3, 500, 10),
+            ])
+            .unwrap();
+
+        let got = app.load_list(ListCategory::Rising).unwrap();
+        let want = vec![2, 1];
+
+        assert_eq!(got, want);
+    }
 }
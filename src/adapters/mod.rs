@@ -1,18 +1,41 @@
 mod config;
+mod crawl;
+mod decode;
+mod events;
+mod fetch_failure;
 mod item;
 mod item_rank;
+mod item_score;
 mod list;
+mod search;
+mod user;
 
-use crate::infra::{db::Duck, hn::HnClient};
+use std::sync::{atomic::AtomicBool, Arc, RwLock};
 
+use crate::infra::{db::Duck, events::EventBus, hn::HnClient, queue::WorkQueue};
+
+#[derive(Clone)]
 pub struct AppCapabilities {
     db: Duck,
     client: HnClient,
+    events: EventBus,
+    search_index_stale: Arc<AtomicBool>,
+    /// Whether the full-text extension is loaded, locked while the index
+    /// is rebuilt.
+    search_index: Arc<RwLock<bool>>,
+    crawls: WorkQueue<String>,
 }
 
 impl AppCapabilities {
     pub fn new(db: Duck, client: HnClient) -> Self {
-        Self { db, client }
+        Self {
+            db,
+            client,
+            events: EventBus::default(),
+            search_index_stale: Arc::new(AtomicBool::new(true)),
+            search_index: Arc::new(RwLock::new(false)),
+            crawls: WorkQueue::default(),
+        }
     }
 }
 
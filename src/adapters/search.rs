@@ -0,0 +1,246 @@
+use anyhow::{anyhow, Result};
+use duckdb::{params, ToSql};
+use std::sync::atomic::Ordering;
+
+use crate::{
+    adapters::AppCapabilities,
+    capabilities::{QueryItems, RefreshSearchIndex, SearchItems},
+    domain::{query::Query, SearchHit},
+    infra::{db::query::compile, hn::types::Item},
+};
+
+// DuckDB's full-text index is a snapshot of the table, so writes to `item`
+// only flag it and cron rebuilds it out of band
+impl RefreshSearchIndex for AppCapabilities {
+    fn refresh_search_index(&self) -> Result<bool> {
+        // Searches hold the read side, so none of them see a half-built index
+        let mut loaded = self
+            .search_index
+            .write()
+            .map_err(|_| anyhow!("Search index lock poisoned"))?;
+
+        // Writes landing during the rebuild flag it again for the next one
+        if !self.search_index_stale.swap(false, Ordering::SeqCst) {
+            return Ok(false);
+        }
+
+        let conn = self.db.get()?;
+        let result = load_extension(&conn, &mut loaded, true).and_then(|_| {
+            Ok(conn.execute_batch(
+                "PRAGMA create_fts_index('item', 'id', 'title', 'body', overwrite=1);",
+            )?)
+        });
+
+        if result.is_err() {
+            self.search_index_stale.store(true, Ordering::SeqCst);
+        }
+        result?;
+
+        Ok(true)
+    }
+}
+
+impl SearchItems for AppCapabilities {
+    fn search_items(&self, text: &str, limit: u32) -> Result<Vec<SearchHit>> {
+        let conn = self.db.get()?;
+
+        let loaded = *self
+            .search_index
+            .read()
+            .map_err(|_| anyhow!("Search index lock poisoned"))?;
+        if !loaded {
+            let mut loaded = self
+                .search_index
+                .write()
+                .map_err(|_| anyhow!("Search index lock poisoned"))?;
+            load_extension(&conn, &mut loaded, false)?;
+        }
+
+        let _lock = self
+            .search_index
+            .read()
+            .map_err(|_| anyhow!("Search index lock poisoned"))?;
+
+        let conn = self.db.get()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT
+                id, relevance
+            FROM (
+                SELECT id, fts_main_item.match_bm25(id, ?1) AS relevance
+                FROM item
+            ) matches
+            WHERE
+                relevance IS NOT NULL
+            ORDER BY
+                relevance DESC
+            LIMIT ?2
+            "#,
+        )?;
+
+        let results = stmt
+            .query_map(params![text, limit], |row| {
+                Ok(SearchHit {
+                    id: row.get(0)?,
+                    relevance: row.get(1)?,
+                })
+            })?
+            .filter_map(Result::ok)
+            .collect();
+
+        Ok(results)
+    }
+}
+
+impl QueryItems for AppCapabilities {
+    fn query_items(&self, query: &Query, limit: u32) -> Result<Vec<Item>> {
+        let (clause, params) = compile(query);
+        let params = params.iter().map(|p| p.as_ref()).collect::<Vec<&dyn ToSql>>();
+
+        let conn = self.db.get()?;
+        let mut stmt = conn.prepare(&format!(
+            r#"
+            SELECT
+                original
+            FROM
+                item
+            WHERE
+                {}
+            ORDER BY
+                ts DESC
+            LIMIT {}
+            "#,
+            clause, limit
+        ))?;
+
+        let results = stmt
+            .query_map(&params[..], |row| {
+                let blob: String = row.get(0)?;
+                let item: Item = serde_json::from_str(&blob).unwrap();
+                Ok(item)
+            })?
+            .filter_map(Result::ok)
+            .collect();
+
+        Ok(results)
+    }
+}
+
+// The extension is only installed, which downloads it the first time, when
+// the index is built. Searching just loads it, and without it search is
+// unavailable rather than the app failing to start
+fn load_extension(conn: &duckdb::Connection, loaded: &mut bool, install: bool) -> Result<()> {
+    if *loaded {
+        return Ok(());
+    }
+
+    let sql = if install {
+        "INSTALL fts; LOAD fts;"
+    } else {
+        "LOAD fts;"
+    };
+    conn.execute_batch(sql)
+        .map_err(|e| anyhow!("Search is unavailable, couldn't load fts: {}", e))?;
+    *loaded = true;
+
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::{
+        capabilities::StoreItems, domain::query::parse, infra::hn::types::tests::sample_items,
+    };
+
+    // Downloads the fts extension the first time it's run
+    #[test]
+    #[ignore]
+    fn search_titles_and_bodies() {
+        let app = crate::adapters::test::setup();
+        let items = sample_items();
+        let _ = app.store_items(items[..1].to_vec()).unwrap();
+        assert!(app.refresh_search_index().unwrap());
+
+        let got = app
+            .search_items("dropbox", 10)
+            .unwrap()
+            .into_iter()
+            .map(|hit| hit.id)
+            .collect::<Vec<_>>();
+        assert_eq!(got, vec![8863]);
+
+        // Nothing to do until something is written
+        assert!(!app.refresh_search_index().unwrap());
+
+        // Picks up items stored after the last rebuild
+        let _ = app.store_items(items[1..].to_vec()).unwrap();
+        assert!(app.refresh_search_index().unwrap());
+        let got = app.search_items("valleywag", 10).unwrap();
+        assert_eq!(got.len(), 1);
+        assert_eq!(got[0].id, 160705);
+    }
+
+    #[test]
+    fn query_with_filters() {
+        let app = crate::adapters::test::setup();
+        let _ = app.store_items(sample_items()).unwrap();
+
+        let ids = |query: &str| {
+            app.query_items(&parse(query).unwrap(), 10)
+                .unwrap()
+                .into_iter()
+                .map(|item| item.id())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(ids("author:pg type:poll"), vec![126809]);
+        assert_eq!(ids("score>100 -type:pollopt"), vec![8863]);
+        assert_eq!(ids("site:getdropbox.com after:2000-01-01"), vec![8863]);
+        assert_eq!(ids("\"arc effect\" before:2000-01-01"), Vec::<u32>::new());
+        assert_eq!(ids("\"arc effect\""), vec![121003]);
+    }
+
+    #[test]
+    fn query_by_post_time() {
+        let app = crate::adapters::test::setup();
+        let _ = app.store_items(sample_items()).unwrap();
+
+        // Posted in 2008, fetched just now
+        let ids = |query: &str| {
+            app.query_items(&parse(query).unwrap(), 10)
+                .unwrap()
+                .into_iter()
+                .map(|item| item.id())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(ids("type:poll before:2010-01-01"), vec![126809]);
+        assert_eq!(ids("type:poll after:2008-03-01"), vec![126809]);
+        assert_eq!(ids("type:poll after:2010-01-01"), Vec::<u32>::new());
+    }
+
+    #[test]
+    fn query_tombstones() {
+        let app = crate::adapters::test::setup();
+        let deleted: Item = serde_json::from_str(
+            r#"{"deleted": true, "id": 2921984, "parent": 2921506, "time": 1314211130, "type": "comment"}"#,
+        )
+        .unwrap();
+        let _ = app.store_items(sample_items()).unwrap();
+        let _ = app.store_items(vec![deleted]).unwrap();
+
+        let got = app
+            .query_items(&parse("is:deleted").unwrap(), 10)
+            .unwrap()
+            .into_iter()
+            .map(|item| item.id())
+            .collect::<Vec<_>>();
+
+        assert_eq!(got, vec![2921984]);
+        assert!(app
+            .query_items(&parse("is:dead").unwrap(), 10)
+            .unwrap()
+            .is_empty());
+    }
+}
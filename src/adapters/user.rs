@@ -0,0 +1,322 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use duckdb::{params, OptionalExt, ToSql};
+use std::collections::HashSet;
+
+use crate::{
+    adapters::AppCapabilities,
+    capabilities::{
+        FetchUser, FetchUsers, LoadKarmaHistory, LoadStoredUsernames, LoadUser, LoadUserActivity,
+        LoadUserItems, StoreUser, StoreUsers,
+    },
+    domain::{KarmaSnapshot, UserActivity},
+    infra::hn::{
+        block_on,
+        types::{Item, User},
+    },
+};
+
+impl FetchUsers for AppCapabilities {
+    fn fetch_users(&self, usernames: Vec<String>) -> Result<Vec<(String, Result<Option<User>>)>> {
+        Ok(block_on(self.client.get_users(usernames))?
+            .into_iter()
+            .map(|(username, result)| (username, result.map_err(Into::into)))
+            .collect())
+    }
+}
+
+impl FetchUser for AppCapabilities {
+    fn fetch_user(&self, username: &str) -> Result<Option<User>> {
+        match self.fetch_users(vec![username.to_string()])?.pop() {
+            Some((_, result)) => result,
+            None => Ok(None),
+        }
+    }
+}
+
+impl StoreUsers for AppCapabilities {
+    fn store_users(&self, users: Vec<User>) -> Result<()> {
+        let mut conn = self.db.get()?;
+        let tx = conn.transaction()?;
+
+        let ts = Utc::now();
+        for user in users {
+            let previous: Option<u32> = tx
+                .query_row(
+                    "SELECT karma FROM hn_user WHERE id = ?1",
+                    [&user.id],
+                    |row| row.get(0),
+                )
+                .optional()?;
+
+            // Snapshot karma only when it moves
+            if previous != Some(user.karma) {
+                tx.execute(
+                    "DELETE FROM user_karma WHERE id = ?1 AND ts = ?2",
+                    params![user.id, ts],
+                )?;
+                tx.execute(
+                    "INSERT INTO user_karma (id, ts, karma) VALUES (?1, ?2, ?3)",
+                    params![user.id, ts, user.karma],
+                )?;
+            }
+
+            tx.execute("DELETE FROM hn_user WHERE id = ?1", [&user.id])?;
+            tx.execute(
+                r#"
+                    INSERT INTO hn_user (id, created, karma, delay, about, submitted)
+                    VALUES
+                    (?1, ?2, ?3, ?4, ?5, ?6)
+                "#,
+                params![
+                    user.id,
+                    user.created as i64,
+                    user.karma,
+                    user.delay,
+                    user.about,
+                    serde_json::to_string(&user.submitted)?
+                ],
+            )?;
+        }
+        tx.commit()?;
+
+        Ok(())
+    }
+}
+
+impl StoreUser for AppCapabilities {
+    fn store_user(&self, user: User) -> Result<()> {
+        self.store_users(vec![user])
+    }
+}
+
+impl LoadUser for AppCapabilities {
+    fn load_user(&self, username: &str) -> Result<Option<User>> {
+        let conn = self.db.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, created, karma, delay, about, submitted FROM hn_user WHERE id = ?1",
+        )?;
+
+        Ok(stmt
+            .query_row([username], |row| {
+                let created: i64 = row.get(1)?;
+                let submitted: String = row.get(5)?;
+
+                Ok(User {
+                    id: row.get(0)?,
+                    created: created as u64,
+                    karma: row.get(2)?,
+                    delay: row.get(3)?,
+                    about: row.get(4)?,
+                    submitted: serde_json::from_str(&submitted)
+                        .map_err(|_| duckdb::Error::InvalidQuery)?,
+                })
+            })
+            .optional()?)
+    }
+}
+
+impl LoadStoredUsernames for AppCapabilities {
+    fn load_stored_usernames(&self, usernames: Vec<String>) -> Result<HashSet<String>> {
+        if usernames.is_empty() {
+            return Ok(HashSet::new());
+        }
+
+        let conn = self.db.get()?;
+
+        // Unlike ids these can't be inlined
+        let placeholders = vec!["?"; usernames.len()].join(", ");
+        let params = usernames
+            .iter()
+            .map(|username| username as &dyn ToSql)
+            .collect::<Vec<_>>();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id FROM hn_user WHERE id IN ({})",
+            placeholders
+        ))?;
+
+        let usernames = stmt
+            .query_map(params.as_slice(), |row| row.get(0))?
+            .filter_map(Result::ok)
+            .collect();
+
+        Ok(usernames)
+    }
+}
+
+impl LoadUserActivity for AppCapabilities {
+    fn load_user_activity(&self, username: &str) -> Result<UserActivity> {
+        let conn = self.db.get()?;
+
+        // The type tag is always the first key of the stored original
+        let (stored, stories, comments, points): (i64, Option<i64>, Option<i64>, Option<i64>) =
+            conn.query_row(
+                r#"
+                SELECT
+                    count(*),
+                    CAST(sum(CASE WHEN original LIKE ?2 THEN 1 ELSE 0 END) AS BIGINT),
+                    CAST(sum(CASE WHEN original LIKE ?3 THEN 1 ELSE 0 END) AS BIGINT),
+                    CAST(sum(score) AS BIGINT)
+                FROM
+                    item
+                WHERE
+                    username = ?1
+                "#,
+                params![username, r#"{"type":"story"%"#, r#"{"type":"comment"%"#],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )?;
+
+        Ok(UserActivity {
+            stored: stored as usize,
+            stories: stories.unwrap_or(0) as usize,
+            comments: comments.unwrap_or(0) as usize,
+            points: points.unwrap_or(0) as u32,
+        })
+    }
+}
+
+impl LoadUserItems for AppCapabilities {
+    fn load_user_items(&self, username: &str, limit: u32, offset: u32) -> Result<Vec<Item>> {
+        let conn = self.db.get()?;
+        let mut stmt = conn.prepare(
+            r#"
+            SELECT
+                original
+            FROM
+                item
+            WHERE
+                username = ?1
+            ORDER BY
+                id DESC
+            LIMIT ?2
+            OFFSET ?3
+            "#,
+        )?;
+
+        let results = stmt
+            .query_map(params![username, limit, offset], |row| {
+                let original: String = row.get(0)?;
+                serde_json::from_str(&original).map_err(|_| duckdb::Error::InvalidQuery)
+            })?
+            .filter_map(Result::ok)
+            .collect();
+
+        Ok(results)
+    }
+}
+
+impl LoadKarmaHistory for AppCapabilities {
+    fn load_karma_history(&self, username: &str) -> Result<Vec<KarmaSnapshot>> {
+        let conn = self.db.get()?;
+        let mut stmt =
+            conn.prepare("SELECT id, ts, karma FROM user_karma WHERE id = ?1 ORDER BY ts")?;
+
+        let results = stmt
+            .query_map([username], |row| {
+                let ts: DateTime<Utc> = row.get(1)?;
+
+                Ok(KarmaSnapshot {
+                    id: row.get(0)?,
+                    ts,
+                    karma: row.get(2)?,
+                })
+            })?
+            .filter_map(Result::ok)
+            .collect();
+
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::{
+        capabilities::StoreItems,
+        infra::hn::types::tests::{sample_items, sample_user},
+    };
+
+    #[test]
+    fn store_user() {
+        let app = crate::adapters::test::setup();
+        let user = sample_user();
+        let _ = app.store_user(user.clone()).unwrap();
+        let got = app.load_user("pg").unwrap();
+        let want = Some(user);
+
+        assert_eq!(got, want);
+        assert_eq!(app.load_user("nobody").unwrap(), None);
+    }
+
+    #[test]
+    fn load_stored_usernames() {
+        let app = crate::adapters::test::setup();
+        let _ = app.store_user(sample_user()).unwrap();
+
+        let got = app
+            .load_stored_usernames(vec!["pg".into(), "nobody".into()])
+            .unwrap();
+        let want = vec!["pg".to_string()].into_iter().collect();
+
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn user_activity_and_items() {
+        let app = crate::adapters::test::setup();
+        let _ = app.store_items(sample_items()).unwrap();
+
+        // A poll and a poll option
+        let got = app.load_user_activity("pg").unwrap();
+        let want = UserActivity {
+            stored: 2,
+            stories: 0,
+            comments: 0,
+            points: 47 + 335,
+        };
+        assert_eq!(got, want);
+
+        assert_eq!(app.load_user_activity("norvig").unwrap().comments, 1);
+        assert_eq!(app.load_user_activity("tel").unwrap().stories, 1);
+        assert_eq!(
+            app.load_user_activity("nobody").unwrap(),
+            UserActivity::default()
+        );
+
+        let got = app
+            .load_user_items("pg", 10, 0)
+            .unwrap()
+            .iter()
+            .map(Item::id)
+            .collect::<Vec<_>>();
+        assert_eq!(got, vec![160705, 126809]);
+
+        let got = app.load_user_items("pg", 10, 1).unwrap();
+        assert_eq!(got, vec![sample_items()[4].clone()]);
+    }
+
+    #[test]
+    fn karma_history() {
+        let app = crate::adapters::test::setup();
+        let user = sample_user();
+        let _ = app.store_user(user.clone()).unwrap();
+        // Same karma, no new snapshot
+        let _ = app.store_user(user.clone()).unwrap();
+        let _ = app
+            .store_user(User {
+                karma: user.karma + 10,
+                ..user.clone()
+            })
+            .unwrap();
+
+        let got = app
+            .load_karma_history("pg")
+            .unwrap()
+            .iter()
+            .map(|snapshot| snapshot.karma)
+            .collect::<Vec<_>>();
+        let want = vec![user.karma, user.karma + 10];
+
+        assert_eq!(got, want);
+    }
+}
@@ -0,0 +1,193 @@
+use rocket::{
+    http::{ContentType, Status},
+    response::{content, Stream},
+    State,
+};
+use serde_json::json;
+use std::{
+    io::{self, Read},
+    sync::mpsc::RecvTimeoutError,
+    time::Duration,
+};
+
+use crate::{
+    adapters::AppCapabilities, api::rest::internal_error, capabilities::SubscribeEvents,
+    domain::Event, infra::events::Subscription,
+};
+
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// Format an event for the `text/event-stream` wire format.
+pub fn to_sse(event: &Event) -> String {
+    let (name, data) = match event {
+        Event::RankChanged {
+            id,
+            category,
+            from,
+            to,
+            ts,
+        } => (
+            "rank_changed",
+            json!({
+                "id": id,
+                "category": category.to_string(),
+                "from": from,
+                "to": to,
+                "ts": ts.to_rfc3339(),
+            }),
+        ),
+        Event::EnteredList {
+            id,
+            category,
+            rank,
+            ts,
+        } => (
+            "entered_list",
+            json!({
+                "id": id,
+                "category": category.to_string(),
+                "rank": rank,
+                "ts": ts.to_rfc3339(),
+            }),
+        ),
+        Event::ItemUpdated {
+            id,
+            score,
+            descendants,
+            ts,
+        } => (
+            "item_updated",
+            json!({
+                "id": id,
+                "score": score,
+                "descendants": descendants,
+                "ts": ts.to_rfc3339(),
+            }),
+        ),
+    };
+
+    format!("event: {}\ndata: {}\n\n", name, data)
+}
+
+/// Blocking reader over an event subscription.
+///
+/// Rocket's `sse` feature flushes the response whenever the body returns
+/// `WouldBlock`, so one is returned after each event is written out.
+pub struct EventStream {
+    subscription: Subscription,
+    pending: Vec<u8>,
+    needs_flush: bool,
+}
+
+impl EventStream {
+    pub fn new(subscription: Subscription) -> Self {
+        Self {
+            subscription,
+            pending: vec![],
+            needs_flush: false,
+        }
+    }
+}
+
+impl Read for EventStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pending.is_empty() {
+            if self.needs_flush {
+                self.needs_flush = false;
+                return Err(io::ErrorKind::WouldBlock.into());
+            }
+
+            // Comments keep the connection alive and let us notice when the
+            // client has gone away
+            self.pending = match self.subscription.receiver.recv_timeout(KEEP_ALIVE) {
+                Ok(event) => to_sse(&event).into_bytes(),
+                Err(RecvTimeoutError::Timeout) => b": keep-alive\n\n".to_vec(),
+                Err(RecvTimeoutError::Disconnected) => return Ok(0),
+            };
+        }
+
+        let n = buf.len().min(self.pending.len());
+        buf[..n].copy_from_slice(&self.pending[..n]);
+        self.pending.drain(..n);
+        self.needs_flush = self.pending.is_empty();
+
+        Ok(n)
+    }
+}
+
+// ROUTES
+/// Every open stream holds a worker, so past the subscriber cap new ones are
+/// turned away with a 503.
+#[get("/events")]
+pub fn get_events(
+    app: State<AppCapabilities>,
+) -> Result<content::Content<Stream<EventStream>>, Status> {
+    let subscription = app
+        .subscribe_events()
+        .map_err(internal_error)?
+        .ok_or(Status::ServiceUnavailable)?;
+
+    Ok(content::Content(
+        ContentType::new("text", "event-stream"),
+        Stream::from(EventStream::new(subscription)),
+    ))
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::{domain::ListCategory, infra::events::EventBus};
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn format_rank_changed() {
+        let event = Event::RankChanged {
+            id: 1,
+            category: ListCategory::Top,
+            from: 3,
+            to: 2,
+            ts: Utc.ymd(2020, 1, 1).and_hms(0, 0, 0),
+        };
+
+        let got = to_sse(&event);
+        let (head, data) = got.split_at(got.find("data: ").unwrap());
+        let data: serde_json::Value = serde_json::from_str(&data["data: ".len()..]).unwrap();
+
+        assert_eq!(head, "event: rank_changed\n");
+        assert!(got.ends_with("\n\n"));
+        assert_eq!(
+            data,
+            json!({
+                "id": 1,
+                "category": "top",
+                "from": 3,
+                "to": 2,
+                "ts": "2020-01-01T00:00:00+00:00",
+            })
+        );
+    }
+
+    #[test]
+    fn stream_flushes_after_each_event() {
+        let bus = EventBus::default();
+        let mut stream = EventStream::new(bus.subscribe().unwrap().unwrap());
+        let event = Event::ItemUpdated {
+            id: 1,
+            score: Some(2),
+            descendants: None,
+            ts: Utc.ymd(2020, 1, 1).and_hms(0, 0, 0),
+        };
+        bus.publish(event.clone()).unwrap();
+        drop(bus);
+
+        let mut buf = [0; 1024];
+        let n = stream.read(&mut buf).unwrap();
+        assert_eq!(&buf[..n], to_sse(&event).as_bytes());
+
+        let err = stream.read(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+
+        // Closed once the publisher goes away
+        assert_eq!(stream.read(&mut buf).unwrap(), 0);
+    }
+}
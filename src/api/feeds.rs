@@ -0,0 +1,308 @@
+use chrono::{DateTime, TimeZone, Utc};
+use rocket::{
+    http::{ContentType, Status},
+    response::content,
+    State,
+};
+
+use crate::{
+    adapters::AppCapabilities,
+    api::rest::{internal_error, parse_category, Items},
+    capabilities::*,
+    domain::ListCategory,
+    infra::hn::types::Item,
+};
+
+const HN_URL: &str = "https://news.ycombinator.com";
+
+/// Which list items make it into a feed.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct FeedFilter {
+    pub min_score: Option<u32>,
+    pub exclude_domains: Vec<String>,
+}
+
+impl FeedFilter {
+    pub fn matches(&self, item: &Item) -> bool {
+        if item.title().is_none() {
+            return false;
+        }
+
+        if let Some(min_score) = self.min_score {
+            if item.score().unwrap_or(0) < min_score {
+                return false;
+            }
+        }
+
+        match item.url().and_then(domain) {
+            Some(host) => !self
+                .exclude_domains
+                .iter()
+                .any(|excluded| host == excluded || host.ends_with(&format!(".{}", excluded))),
+            None => true,
+        }
+    }
+}
+
+/// Return the host of a url, without any `www.` prefix.
+pub fn domain(url: &str) -> Option<&str> {
+    let rest = url.split("://").nth(1)?;
+    let host = rest.split(|c| c == '/' || c == '?' || c == '#').next()?;
+    let host = host.split(':').next()?;
+    let host = host.strip_prefix("www.").unwrap_or(host);
+
+    if host.is_empty() {
+        None
+    } else {
+        Some(host)
+    }
+}
+
+pub fn discussion_link(id: u32) -> String {
+    format!("{}/item?id={}", HN_URL, id)
+}
+
+/// Link to the story itself, or to the discussion for Ask HN and other text
+/// posts.
+pub fn item_link(item: &Item) -> String {
+    match item.url() {
+        Some(url) if !url.is_empty() => url.to_string(),
+        _ => discussion_link(item.id()),
+    }
+}
+
+fn item_time(item: &Item) -> DateTime<Utc> {
+    item.ts()
+        .map(|ts| Utc.timestamp(*ts as i64, 0))
+        .unwrap_or_else(Utc::now)
+}
+
+fn summary(item: &Item) -> String {
+    format!(
+        "{} points by {} | <a href=\"{}\">Comments</a>",
+        item.score().unwrap_or(0),
+        item.author().unwrap_or("unknown"),
+        discussion_link(item.id())
+    )
+}
+
+fn escape(input: &str) -> String {
+    input
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn feed_title(category: &ListCategory) -> String {
+    format!("Hacker News: {}", category.to_string())
+}
+
+pub fn render_rss(category: &ListCategory, items: &[Item]) -> String {
+    let entries = items
+        .iter()
+        .map(|item| {
+            format!(
+                r#"
+    <item>
+      <title>{}</title>
+      <link>{}</link>
+      <guid isPermaLink="true">{}</guid>
+      <comments>{}</comments>
+      <dc:creator>{}</dc:creator>
+      <pubDate>{}</pubDate>
+      <description>{}</description>
+    </item>"#,
+                escape(item.title().unwrap_or_default()),
+                escape(&item_link(item)),
+                escape(&discussion_link(item.id())),
+                escape(&discussion_link(item.id())),
+                escape(item.author().unwrap_or_default()),
+                item_time(item).to_rfc2822(),
+                escape(&summary(item)),
+            )
+        })
+        .collect::<String>();
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0" xmlns:dc="http://purl.org/dc/elements/1.1/">
+  <channel>
+    <title>{}</title>
+    <link>{}</link>
+    <description>{}</description>
+    <lastBuildDate>{}</lastBuildDate>{}
+  </channel>
+</rss>
+"#,
+        escape(&feed_title(category)),
+        HN_URL,
+        escape(&feed_title(category)),
+        Utc::now().to_rfc2822(),
+        entries
+    )
+}
+
+pub fn render_atom(category: &ListCategory, items: &[Item]) -> String {
+    let updated = items.iter().map(item_time).max().unwrap_or_else(Utc::now);
+    let entries = items
+        .iter()
+        .map(|item| {
+            format!(
+                r#"
+  <entry>
+    <id>{}</id>
+    <title>{}</title>
+    <link href="{}"/>
+    <link rel="replies" href="{}"/>
+    <author><name>{}</name></author>
+    <updated>{}</updated>
+    <summary type="html">{}</summary>
+  </entry>"#,
+                escape(&discussion_link(item.id())),
+                escape(item.title().unwrap_or_default()),
+                escape(&item_link(item)),
+                escape(&discussion_link(item.id())),
+                escape(item.author().unwrap_or("unknown")),
+                item_time(item).to_rfc3339(),
+                escape(&summary(item)),
+            )
+        })
+        .collect::<String>();
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <id>{}/{}</id>
+  <title>{}</title>
+  <link href="{}"/>
+  <updated>{}</updated>{}
+</feed>
+"#,
+        HN_URL,
+        category.to_string(),
+        escape(&feed_title(category)),
+        HN_URL,
+        updated.to_rfc3339(),
+        entries
+    )
+}
+
+// ROUTES
+#[get("/feeds/<file>?<min_score>&<exclude_domain>")]
+pub fn get_feed(
+    app: State<AppCapabilities>,
+    items: Items,
+    file: String,
+    min_score: Option<u32>,
+    exclude_domain: Option<String>,
+) -> Result<content::Content<String>, Status> {
+    let (category, format) = file.rsplit_once('.').ok_or(Status::NotFound)?;
+    let category = parse_category(category)?;
+    let filter = FeedFilter {
+        min_score,
+        exclude_domains: exclude_domain
+            .map(|domains| domains.split(',').map(|d| d.trim().to_string()).collect())
+            .unwrap_or_default(),
+    };
+
+    let ids = app.load_list(category.clone()).map_err(internal_error)?;
+    let items = items
+        .load_items(ids)
+        .map_err(internal_error)?
+        .into_iter()
+        .filter(|item| filter.matches(item))
+        .collect::<Vec<_>>();
+
+    match format {
+        "rss" => Ok(content::Content(
+            ContentType::new("application", "rss+xml"),
+            render_rss(&category, &items),
+        )),
+        "atom" => Ok(content::Content(
+            ContentType::new("application", "atom+xml"),
+            render_atom(&category, &items),
+        )),
+        _ => Err(Status::NotFound),
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::infra::hn::types::tests::sample_items;
+
+    #[test]
+    fn domain_of_url() {
+        assert_eq!(
+            domain("http://www.getdropbox.com/u/2/screencast.html"),
+            Some("getdropbox.com")
+        );
+        assert_eq!(domain("https://github.com:443?x=1"), Some("github.com"));
+        assert_eq!(domain(""), None);
+    }
+
+    #[test]
+    fn link_falls_back_to_discussion() {
+        let items = sample_items();
+
+        assert_eq!(
+            item_link(&items[0]),
+            "http://www.getdropbox.com/u/2/screencast.html"
+        );
+        assert_eq!(
+            item_link(&items[1]),
+            "https://news.ycombinator.com/item?id=121003"
+        );
+    }
+
+    #[test]
+    fn filter_by_min_score() {
+        let items = sample_items();
+        let filter = FeedFilter {
+            min_score: Some(100),
+            ..Default::default()
+        };
+
+        let got = items
+            .iter()
+            .filter(|item| filter.matches(item))
+            .map(|item| item.id())
+            .collect::<Vec<_>>();
+
+        assert_eq!(got, vec![8863]);
+    }
+
+    #[test]
+    fn filter_by_domain() {
+        let items = sample_items();
+        let filter = FeedFilter {
+            exclude_domains: vec!["getdropbox.com".into()],
+            ..Default::default()
+        };
+
+        assert!(!filter.matches(&items[0]));
+        assert!(filter.matches(&items[1]));
+    }
+
+    #[test]
+    fn render_rss_escapes_content() {
+        let items = sample_items();
+        let got = render_rss(&ListCategory::Top, &items[..1]);
+
+        assert!(got.contains("<title>My YC app: Dropbox - Throw away your USB drive</title>"));
+        assert!(got.contains("<dc:creator>dhouston</dc:creator>"));
+        assert!(got.contains("104 points by dhouston | &lt;a href="));
+    }
+
+    #[test]
+    fn render_atom_entries() {
+        let items = sample_items();
+        let got = render_atom(&ListCategory::Top, &items[..2]);
+
+        assert!(got.contains("<id>https://news.ycombinator.com/item?id=8863</id>"));
+        assert!(got.contains("<link href=\"https://news.ycombinator.com/item?id=121003\"/>"));
+        assert!(got.contains("<updated>2008-02-22T02:33:40+00:00</updated>"));
+    }
+}
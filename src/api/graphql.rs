@@ -0,0 +1,798 @@
+use anyhow::Result;
+use chrono::{DateTime, TimeZone, Utc};
+use juniper::{
+    graphql_object, graphql_value, http::graphiql::graphiql_source, http::GraphQLRequest,
+    EmptyMutation, EmptySubscription, FieldError, FieldResult, GraphQLUnion, RootNode,
+};
+use rocket::{http::Status, response::content, State};
+
+use crate::{
+    adapters::AppCapabilities,
+    api::loader::ItemLoader,
+    capabilities::*,
+    domain::{
+        dwell::ListInterval,
+        list_diff::{ListDiff, RankChange},
+        query,
+        version::{FieldChange, ItemVersion},
+        ItemRank, ItemScore, ListCategory, SearchHit,
+    },
+    infra::hn::types::{Comment, Item, Job, Poll, Pollopt, Story},
+    use_cases::{
+        load_comment_tree::{self, ThreadComment},
+        load_list_at::{self, ListEntry},
+        load_list_diff, load_list_intervals,
+        search_items::{self, SearchQuery, SearchResult},
+    },
+};
+
+pub struct Context {
+    pub app: AppCapabilities,
+    items: ItemLoader,
+}
+
+impl Context {
+    pub fn new(app: AppCapabilities) -> Self {
+        Self {
+            app,
+            items: ItemLoader::new(load_comment_tree::DEFAULT_LIMIT as usize),
+        }
+    }
+
+    // Fetching on a read lets any client drive requests to HN, so it's opt-in
+    fn fetch_missing_allowed(&self) -> Result<bool> {
+        Ok(self
+            .app
+            .load_config_value_as("graphql_fetch_missing")?
+            .unwrap_or(false))
+    }
+}
+
+impl juniper::Context for Context {}
+
+// Resolvers load items through the context so they share one cache per request
+impl LoadItems for Context {
+    fn load_items(&self, ids: Vec<u32>) -> Result<Vec<Item>> {
+        self.items.load_items(&self.app, ids)
+    }
+}
+
+impl LoadItem for Context {
+    fn load_item(&self, id: u32) -> Result<Option<Item>> {
+        self.items.load_item(&self.app, id)
+    }
+}
+
+impl FetchItems for Context {
+    fn fetch_items(&self, ids: Vec<u32>) -> Result<Vec<Item>> {
+        self.app.fetch_items(ids)
+    }
+}
+
+impl SearchItems for Context {
+    fn search_items(&self, text: &str, limit: u32) -> Result<Vec<SearchHit>> {
+        self.app.search_items(text, limit)
+    }
+}
+
+impl StoreItems for Context {
+    fn store_items(&self, items: Vec<Item>) -> Result<Vec<u32>> {
+        self.items.prime(&items)?;
+        self.app.store_items(items)
+    }
+}
+
+pub type Schema = RootNode<'static, Query, EmptyMutation<Context>, EmptySubscription<Context>>;
+
+pub fn schema() -> Schema {
+    Schema::new(Query, EmptyMutation::new(), EmptySubscription::new())
+}
+
+// ROUTES
+#[get("/graphiql")]
+pub fn graphiql() -> content::Html<String> {
+    content::Html(graphiql_source("/graphql", None))
+}
+
+#[post("/graphql", data = "<body>")]
+pub fn post_graphql(
+    app: State<AppCapabilities>,
+    schema: State<Schema>,
+    body: String,
+) -> Result<content::Json<String>, Status> {
+    let request: GraphQLRequest = serde_json::from_str(&body).map_err(|_| Status::BadRequest)?;
+    let context = Context::new(app.inner().clone());
+    let response = request.execute_sync(schema.inner(), &context);
+    let json = serde_json::to_string(&response).map_err(|_| Status::InternalServerError)?;
+
+    Ok(content::Json(json))
+}
+
+// QUERY
+pub struct Query;
+
+#[graphql_object(context = Context)]
+impl Query {
+    /// A single stored item.
+    fn item(context: &Context, id: i32) -> FieldResult<Option<GraphQLItem>> {
+        Ok(context.load_item(id as u32)?.map(Into::into))
+    }
+
+    /// The stored items of a list, in rank order. With `at`, the list as it
+    /// was at that moment.
+    fn list(
+        context: &Context,
+        category: ListCategory,
+        at: Option<DateTime<Utc>>,
+    ) -> FieldResult<Vec<GraphQLItem>> {
+        let ids = match at {
+            Some(at) => context.app.load_list_at(category, at)?,
+            None => context.app.load_list(category)?,
+        };
+        let items = context.load_items(ids)?;
+
+        Ok(items.into_iter().map(Into::into).collect())
+    }
+
+    /// A list as it was at a past moment, with each item's rank and score
+    /// at the time.
+    fn list_at(
+        context: &Context,
+        category: ListCategory,
+        at: DateTime<Utc>,
+    ) -> FieldResult<Vec<ListEntry>> {
+        Ok(load_list_at::run(&context.app, category, at)?)
+    }
+
+    /// What changed in a list between two moments, `to` defaulting to now.
+    fn list_diff(
+        context: &Context,
+        category: ListCategory,
+        from: DateTime<Utc>,
+        to: Option<DateTime<Utc>>,
+    ) -> FieldResult<ListDiff> {
+        let to = to.unwrap_or_else(Utc::now);
+        Ok(load_list_diff::run(&context.app, category, from, to)?)
+    }
+
+    /// Every recorded rank change of an item in a list.
+    fn rank_history(
+        context: &Context,
+        id: i32,
+        category: ListCategory,
+    ) -> FieldResult<Vec<ItemRank>> {
+        Ok(context.app.load_item_ranks(id as u32, category)?)
+    }
+
+    /// Every stay an item has had on a list, oldest first.
+    fn list_intervals(
+        context: &Context,
+        id: i32,
+        category: ListCategory,
+    ) -> FieldResult<Vec<ListInterval>> {
+        Ok(load_list_intervals::run(&context.app, id as u32, category)?)
+    }
+
+    /// Every distinct version of an item we have fetched, oldest first.
+    fn item_versions(context: &Context, id: i32) -> FieldResult<Vec<ItemVersion>> {
+        Ok(context.app.load_item_versions(id as u32)?)
+    }
+
+    /// Every recorded change in an item's score and comment count.
+    fn score_history(context: &Context, id: i32) -> FieldResult<Vec<ItemScore>> {
+        Ok(context.app.load_item_scores(id as u32)?)
+    }
+
+    /// Items matching a full-text query, by BM25 relevance with optional
+    /// boosts for points and recency.
+    fn search(
+        context: &Context,
+        query: String,
+        limit: Option<i32>,
+        score_boost: Option<f64>,
+        recency_boost: Option<f64>,
+    ) -> FieldResult<Vec<SearchResult>> {
+        let query = SearchQuery {
+            limit: limit.map_or(search_items::DEFAULT_LIMIT, |l| l.max(0) as u32),
+            score_boost: score_boost.unwrap_or(0.0),
+            recency_boost: recency_boost.unwrap_or(0.0),
+            ..SearchQuery::new(&query)
+        };
+
+        Ok(search_items::run(context, &query, &Utc::now())?)
+    }
+
+    /// Items matching a structured query such as
+    /// `author:pg score>100 type:story site:github.com before:2020-01-01 "rust"`.
+    fn items(
+        context: &Context,
+        query: String,
+        limit: Option<i32>,
+    ) -> FieldResult<Vec<GraphQLItem>> {
+        let query = query::parse(&query).map_err(|e| {
+            let position = e.position as i32;
+            FieldError::new(e.message, graphql_value!({ "position": position }))
+        })?;
+        let limit = limit
+            .map_or(search_items::DEFAULT_LIMIT, |l| l.max(0) as u32)
+            .min(search_items::MAX_LIMIT);
+        let items = context.app.query_items(&query, limit)?;
+        context.items.prime(&items)?;
+
+        Ok(items.into_iter().map(Into::into).collect())
+    }
+}
+
+// TYPES
+#[derive(GraphQLUnion)]
+#[graphql(name = "Item", context = Context)]
+pub enum GraphQLItem {
+    Story(Story),
+    Comment(Comment),
+    Job(Job),
+    Poll(Poll),
+    Pollopt(Pollopt),
+}
+
+impl From<Item> for GraphQLItem {
+    fn from(item: Item) -> Self {
+        match item {
+            Item::Story(story) => Self::Story(story),
+            Item::Comment(comment) => Self::Comment(comment),
+            Item::Job(job) => Self::Job(job),
+            Item::Poll(poll) => Self::Poll(poll),
+            Item::Pollopt(pollopt) => Self::Pollopt(pollopt),
+        }
+    }
+}
+
+fn to_ids(ids: &Option<Vec<u32>>) -> Vec<i32> {
+    ids.iter().flatten().map(|id| *id as i32).collect()
+}
+
+fn to_datetime(time: u64) -> DateTime<Utc> {
+    Utc.timestamp(time as i64, 0)
+}
+
+// Story, Comment and Poll all have kids, so they share one `comments` field
+// resolving the thread below them
+macro_rules! with_comments {
+    (impl $type:ty { $($fields:tt)* }) => {
+        #[graphql_object(context = Context)]
+        impl $type {
+            $($fields)*
+
+            /// The comment thread below this item, nested in display order.
+            /// `fetchMissing` is refused unless the `graphql_fetch_missing`
+            /// config value is set.
+            fn comments(
+                &self,
+                context: &Context,
+                depth: Option<i32>,
+                limit: Option<i32>,
+                fetch_missing: Option<bool>,
+            ) -> FieldResult<Vec<ThreadComment>> {
+                let fetch_missing = fetch_missing.unwrap_or(false);
+                if fetch_missing && !context.fetch_missing_allowed()? {
+                    return Err(FieldError::new(
+                        "fetchMissing is disabled on this server",
+                        graphql_value!(None),
+                    ));
+                }
+
+                let kids = self.kids.clone().unwrap_or_default();
+                let depth = depth.map_or(load_comment_tree::DEFAULT_DEPTH, |d| d.max(0) as u32);
+                let limit = limit.map_or(load_comment_tree::DEFAULT_LIMIT, |l| l.max(0) as u32);
+
+                Ok(load_comment_tree::run(
+                    context,
+                    &kids,
+                    depth,
+                    limit,
+                    fetch_missing,
+                )?)
+            }
+        }
+    };
+}
+
+with_comments! {
+    impl Story {
+        fn id(&self) -> i32 {
+            self.id as i32
+        }
+
+        fn descendants(&self) -> Option<i32> {
+            self.descendants.map(|d| d as i32)
+        }
+
+        fn by(&self) -> Option<&str> {
+            self.by.as_deref()
+        }
+
+        fn kids(&self) -> Vec<i32> {
+            to_ids(&self.kids)
+        }
+
+        fn score(&self) -> Option<i32> {
+            self.score.map(|s| s as i32)
+        }
+
+        fn title(&self) -> Option<&str> {
+            self.title.as_deref()
+        }
+
+        fn url(&self) -> Option<&str> {
+            self.url.as_deref()
+        }
+
+        fn text(&self) -> Option<&str> {
+            self.text.as_deref()
+        }
+
+        fn time(&self) -> DateTime<Utc> {
+            to_datetime(self.time)
+        }
+
+        fn deleted(&self) -> bool {
+            self.deleted
+        }
+
+        fn dead(&self) -> bool {
+            self.dead
+        }
+    }
+}
+
+with_comments! {
+    impl Comment {
+        fn id(&self) -> i32 {
+            self.id as i32
+        }
+
+        fn by(&self) -> Option<&str> {
+            self.by.as_deref()
+        }
+
+        fn kids(&self) -> Vec<i32> {
+            to_ids(&self.kids)
+        }
+
+        fn parent(&self) -> i32 {
+            self.parent as i32
+        }
+
+        fn text(&self) -> Option<&str> {
+            self.text.as_deref()
+        }
+
+        fn time(&self) -> DateTime<Utc> {
+            to_datetime(self.time)
+        }
+
+        fn deleted(&self) -> bool {
+            self.deleted
+        }
+
+        fn dead(&self) -> bool {
+            self.dead
+        }
+    }
+}
+
+#[graphql_object(context = Context)]
+impl Job {
+    fn id(&self) -> i32 {
+        self.id as i32
+    }
+
+    fn score(&self) -> Option<i32> {
+        self.score.map(|s| s as i32)
+    }
+
+    fn text(&self) -> Option<&str> {
+        self.text.as_deref()
+    }
+
+    fn time(&self) -> DateTime<Utc> {
+        to_datetime(self.time)
+    }
+
+    fn deleted(&self) -> bool {
+        self.deleted
+    }
+
+    fn dead(&self) -> bool {
+        self.dead
+    }
+
+    fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    fn url(&self) -> Option<&str> {
+        self.url.as_deref()
+    }
+}
+
+with_comments! {
+    impl Poll {
+        fn id(&self) -> i32 {
+            self.id as i32
+        }
+
+        fn by(&self) -> Option<&str> {
+            self.by.as_deref()
+        }
+
+        fn descendants(&self) -> Option<i32> {
+            self.descendants.map(|d| d as i32)
+        }
+
+        fn kids(&self) -> Vec<i32> {
+            to_ids(&self.kids)
+        }
+
+        fn parts(&self) -> Vec<i32> {
+            to_ids(&self.parts)
+        }
+
+        fn score(&self) -> Option<i32> {
+            self.score.map(|s| s as i32)
+        }
+
+        fn title(&self) -> Option<&str> {
+            self.title.as_deref()
+        }
+
+        fn text(&self) -> Option<&str> {
+            self.text.as_deref()
+        }
+
+        fn time(&self) -> DateTime<Utc> {
+            to_datetime(self.time)
+        }
+
+        fn deleted(&self) -> bool {
+            self.deleted
+        }
+
+        fn dead(&self) -> bool {
+            self.dead
+        }
+    }
+}
+
+#[graphql_object(context = Context)]
+impl Pollopt {
+    fn id(&self) -> i32 {
+        self.id as i32
+    }
+
+    fn by(&self) -> Option<&str> {
+        self.by.as_deref()
+    }
+
+    fn poll(&self) -> i32 {
+        self.poll as i32
+    }
+
+    fn score(&self) -> Option<i32> {
+        self.score.map(|s| s as i32)
+    }
+
+    fn text(&self) -> Option<&str> {
+        self.text.as_deref()
+    }
+
+    fn time(&self) -> DateTime<Utc> {
+        to_datetime(self.time)
+    }
+
+    fn deleted(&self) -> bool {
+        self.deleted
+    }
+
+    fn dead(&self) -> bool {
+        self.dead
+    }
+}
+
+#[graphql_object(context = Context)]
+impl ThreadComment {
+    fn comment(&self) -> &Comment {
+        &self.comment
+    }
+
+    fn replies(&self) -> &[ThreadComment] {
+        &self.replies
+    }
+}
+
+#[graphql_object(context = Context)]
+impl SearchResult {
+    fn relevance(&self) -> f64 {
+        self.relevance
+    }
+
+    fn item(&self) -> GraphQLItem {
+        self.item.clone().into()
+    }
+}
+
+#[graphql_object(context = Context)]
+impl ItemRank {
+    fn id(&self) -> i32 {
+        self.id as i32
+    }
+
+    fn rank(&self) -> i32 {
+        self.rank as i32
+    }
+
+    fn category(&self) -> ListCategory {
+        self.category.clone()
+    }
+
+    fn ts(&self) -> DateTime<Utc> {
+        self.ts
+    }
+}
+
+#[graphql_object(context = Context)]
+impl ItemScore {
+    fn id(&self) -> i32 {
+        self.id as i32
+    }
+
+    fn score(&self) -> i32 {
+        self.score as i32
+    }
+
+    fn descendants(&self) -> Option<i32> {
+        self.descendants.map(|d| d as i32)
+    }
+
+    fn ts(&self) -> DateTime<Utc> {
+        self.ts
+    }
+}
+
+#[graphql_object(context = Context)]
+impl ListInterval {
+    fn id(&self) -> i32 {
+        self.id as i32
+    }
+
+    fn category(&self) -> ListCategory {
+        self.category.clone()
+    }
+
+    fn entered(&self) -> DateTime<Utc> {
+        self.entered
+    }
+
+    /// Null while the item is still on the list.
+    fn exited(&self) -> Option<DateTime<Utc>> {
+        self.exited
+    }
+
+    /// Time on the list so far, in seconds.
+    fn seconds(&self) -> f64 {
+        self.duration(&Utc::now()).num_seconds() as f64
+    }
+}
+
+#[graphql_object(context = Context)]
+impl ListEntry {
+    fn rank(&self) -> i32 {
+        self.rank as i32
+    }
+
+    fn item(&self) -> GraphQLItem {
+        self.item.clone().into()
+    }
+
+    /// Null when no score was recorded by then.
+    fn score(&self) -> Option<ItemScore> {
+        self.score.clone()
+    }
+}
+
+#[graphql_object(context = Context)]
+impl ListDiff {
+    fn entered(&self) -> Vec<RankChange> {
+        self.entered.clone()
+    }
+
+    fn exited(&self) -> Vec<RankChange> {
+        self.exited.clone()
+    }
+
+    fn moved_up(&self) -> Vec<RankChange> {
+        self.moved_up.clone()
+    }
+
+    fn moved_down(&self) -> Vec<RankChange> {
+        self.moved_down.clone()
+    }
+}
+
+#[graphql_object(context = Context)]
+impl RankChange {
+    fn id(&self) -> i32 {
+        self.id as i32
+    }
+
+    /// Null for items that entered.
+    fn from(&self) -> Option<i32> {
+        self.from.map(|rank| rank as i32)
+    }
+
+    /// Null for items that exited.
+    fn to(&self) -> Option<i32> {
+        self.to.map(|rank| rank as i32)
+    }
+
+    /// Positions gained, negative when the item moved down.
+    #[graphql(name = "delta")]
+    fn positions(&self) -> Option<i32> {
+        RankChange::delta(self).map(|delta| delta as i32)
+    }
+}
+
+#[graphql_object(context = Context)]
+impl ItemVersion {
+    fn id(&self) -> i32 {
+        self.id as i32
+    }
+
+    fn ts(&self) -> DateTime<Utc> {
+        self.ts
+    }
+
+    /// The payload as fetched from HN, as JSON.
+    fn original(&self) -> &str {
+        &self.original
+    }
+
+    /// Empty for the first version.
+    fn changes(&self) -> Vec<FieldChange> {
+        self.changes.clone()
+    }
+}
+
+#[graphql_object(context = Context)]
+impl FieldChange {
+    fn field(&self) -> &str {
+        &self.field
+    }
+
+    /// The old value as JSON, `null` if the field was missing.
+    fn from(&self) -> String {
+        self.from.to_string()
+    }
+
+    /// The new value as JSON, `null` if the field was removed.
+    fn to(&self) -> String {
+        self.to.to_string()
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::infra::hn::types::tests::sample_items;
+    use juniper::Variables;
+
+    fn setup() -> Context {
+        let app = crate::adapters::test::setup();
+        let items = sample_items();
+        let ids = items.iter().map(|i| i.id()).collect::<Vec<_>>();
+        app.store_items(items).unwrap();
+        app.replace_list(
+            ListCategory::Top,
+            &[ids[1], ids[0]],
+            &Utc.ymd(2020, 1, 1).and_hms(0, 0, 0),
+        )
+        .unwrap();
+        app.replace_list(
+            ListCategory::Top,
+            &ids[..2],
+            &Utc.ymd(2020, 1, 2).and_hms(0, 0, 0),
+        )
+        .unwrap();
+        Context::new(app)
+    }
+
+    #[test]
+    fn query_list() {
+        let context = setup();
+        let query = r#"
+            {
+                list(category: TOP) {
+                    ... on Story { id title }
+                }
+            }
+        "#;
+
+        let (got, errors) =
+            juniper::execute_sync(query, None, &schema(), &Variables::new(), &context).unwrap();
+        let want = graphql_value!({
+            "list": [
+                {"id": 8863, "title": "My YC app: Dropbox - Throw away your USB drive"},
+                {"id": 121003, "title": "Ask HN: The Arc Effect"}
+            ]
+        });
+
+        assert!(errors.is_empty());
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn query_list_at() {
+        let context = setup();
+        let query = r#"
+            {
+                listAt(category: TOP, at: "2020-01-01T09:00:00Z") {
+                    rank
+                    item { ... on Story { id } }
+                }
+            }
+        "#;
+
+        let (got, errors) =
+            juniper::execute_sync(query, None, &schema(), &Variables::new(), &context).unwrap();
+        let want = graphql_value!({
+            "listAt": [
+                {"rank": 1, "item": {"id": 121003}},
+                {"rank": 2, "item": {"id": 8863}}
+            ]
+        });
+
+        assert!(errors.is_empty());
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn query_item() {
+        let context = setup();
+        let query = r#"
+            {
+                item(id: 2921983) {
+                    ... on Comment { by parent }
+                }
+            }
+        "#;
+
+        let (got, errors) =
+            juniper::execute_sync(query, None, &schema(), &Variables::new(), &context).unwrap();
+        let want = graphql_value!({
+            "item": {"by": "norvig", "parent": 2921506}
+        });
+
+        assert!(errors.is_empty());
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn query_comments_fetch_missing_disabled() {
+        let context = setup();
+        let query = r#"
+            {
+                item(id: 8863) {
+                    ... on Story { comments(fetchMissing: true) { comment { id } } }
+                }
+            }
+        "#;
+
+        let (_, errors) =
+            juniper::execute_sync(query, None, &schema(), &Variables::new(), &context).unwrap();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].error().message(),
+            "fetchMissing is disabled on this server"
+        );
+    }
+}
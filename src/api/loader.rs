@@ -0,0 +1,193 @@
+use anyhow::{anyhow, Result};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Mutex,
+};
+
+use crate::{capabilities::LoadItems, infra::hn::types::Item};
+
+/// Per-request item loader. Every lookup goes to the database at most once,
+/// and all ids not seen yet are loaded together in a single `load_items`.
+///
+/// Resolvers run one after another, so siblings can't be collected before
+/// they ask. Instead the first `prefetch` kids of every loaded item are
+/// queued and ride along with the next lookup, so a whole level of a tree
+/// costs one query no matter how many parents it has.
+#[derive(Default)]
+pub struct ItemLoader {
+    prefetch: usize,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    cache: HashMap<u32, Option<Item>>,
+    pending: HashSet<u32>,
+}
+
+impl State {
+    fn insert(&mut self, item: Item, prefetch: usize) {
+        for kid in item.kids().iter().take(prefetch) {
+            if !self.cache.contains_key(kid) {
+                self.pending.insert(*kid);
+            }
+        }
+        self.pending.remove(&item.id());
+        self.cache.insert(item.id(), Some(item));
+    }
+}
+
+impl ItemLoader {
+    pub fn new(prefetch: usize) -> Self {
+        Self {
+            prefetch,
+            ..Default::default()
+        }
+    }
+
+    pub fn load_items(&self, app: &impl LoadItems, ids: Vec<u32>) -> Result<Vec<Item>> {
+        let mut state = self
+            .state
+            .lock()
+            .map_err(|_| anyhow!("Item cache poisoned"))?;
+
+        let mut missing = ids
+            .iter()
+            .filter(|id| !state.cache.contains_key(id))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if !missing.is_empty() {
+            missing.extend(state.pending.drain());
+            missing.sort_unstable();
+            missing.dedup();
+
+            let loaded = app.load_items(missing.clone())?;
+
+            // Remember misses too so they aren't queried again
+            for id in missing {
+                state.cache.insert(id, None);
+            }
+            for item in loaded {
+                state.insert(item, self.prefetch);
+            }
+        }
+
+        Ok(ids
+            .iter()
+            .filter_map(|id| state.cache.get(id).cloned().flatten())
+            .collect())
+    }
+
+    pub fn load_item(&self, app: &impl LoadItems, id: u32) -> Result<Option<Item>> {
+        Ok(self.load_items(app, vec![id])?.pop())
+    }
+
+    pub fn prime(&self, items: &[Item]) -> Result<()> {
+        let mut state = self
+            .state
+            .lock()
+            .map_err(|_| anyhow!("Item cache poisoned"))?;
+
+        for item in items {
+            state.insert(item.clone(), self.prefetch);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        capabilities::MockLoadItems,
+        infra::hn::types::{tests::sample_items, Comment},
+    };
+
+    #[test]
+    fn loads_each_id_once() {
+        let mut mock = MockLoadItems::new();
+        let items = sample_items();
+        let stored = items.clone();
+
+        mock.expect_load_items()
+            .times(2)
+            .returning(move |ids| {
+                Ok(stored
+                    .iter()
+                    .filter(|item| ids.contains(&item.id()))
+                    .cloned()
+                    .collect())
+            });
+
+        let loader = ItemLoader::default();
+        let _ = loader
+            .load_items(&mock, vec![items[0].id(), items[1].id(), 404])
+            .unwrap();
+
+        // Only the unseen id goes to the database
+        let got = loader
+            .load_items(&mock, vec![items[1].id(), items[2].id(), items[0].id()])
+            .unwrap();
+        let want = vec![items[1].clone(), items[2].clone(), items[0].clone()];
+
+        assert_eq!(got, want);
+
+        // Everything is cached now, including misses
+        let got = loader.load_item(&mock, 404).unwrap();
+        assert_eq!(got, None);
+    }
+
+    fn comment(id: u32, kids: Vec<u32>) -> Item {
+        Item::Comment(Comment {
+            id,
+            by: Some("norvig".into()),
+            kids: Some(kids),
+            parent: 0,
+            text: Some(format!("comment {}", id)),
+            time: 1314211127,
+            deleted: false,
+            dead: false,
+        })
+    }
+
+    #[test]
+    fn loads_a_level_of_siblings_at_once() {
+        let mut mock = MockLoadItems::new();
+        let stored = vec![
+            comment(1, vec![3, 4, 5]),
+            comment(2, vec![6]),
+            comment(3, vec![]),
+            comment(4, vec![]),
+            comment(5, vec![]),
+            comment(6, vec![]),
+        ];
+
+        let mut seq = mockall::Sequence::new();
+        for want in vec![vec![1, 2], vec![3, 4, 6]] {
+            let stored = stored.clone();
+            mock.expect_load_items()
+                .withf(move |ids| *ids == want)
+                .times(1)
+                .in_sequence(&mut seq)
+                .returning(move |ids| {
+                    Ok(stored
+                        .iter()
+                        .filter(|item| ids.contains(&item.id()))
+                        .cloned()
+                        .collect())
+                });
+        }
+
+        let loader = ItemLoader::new(2);
+        loader.load_items(&mock, vec![1, 2]).unwrap();
+
+        // Asking for the kids of 1 loads the kids of 2 along with them
+        let got = loader.load_items(&mock, vec![3, 4]).unwrap();
+        assert_eq!(got, vec![stored[2].clone(), stored[3].clone()]);
+
+        let got = loader.load_items(&mock, vec![6]).unwrap();
+        assert_eq!(got, vec![stored[5].clone()]);
+    }
+}
@@ -0,0 +1,35 @@
+pub mod events;
+pub mod feeds;
+pub mod graphql;
+pub mod loader;
+pub mod rest;
+
+use crate::adapters::AppCapabilities;
+
+pub fn rocket(app: AppCapabilities) -> rocket::Rocket {
+    rocket::ignite()
+        .manage(app)
+        .manage(graphql::schema())
+        .mount(
+            "/",
+            routes![
+                events::get_events,
+                feeds::get_feed,
+                graphql::graphiql,
+                graphql::post_graphql,
+                rest::get_decode_failures,
+                rest::get_list,
+                rest::get_list_diff,
+                rest::get_item,
+                rest::get_item_ranks,
+                rest::get_item_intervals,
+                rest::get_item_scores,
+                rest::get_item_versions,
+                rest::get_query,
+                rest::get_search,
+                rest::get_user,
+                rest::get_user_timeline,
+                rest::post_user_crawl
+            ],
+        )
+}
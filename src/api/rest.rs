@@ -0,0 +1,960 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use rocket::{
+    http::Status,
+    request::{self, FromRequest, Request},
+    response::{content, status},
+    Outcome, State,
+};
+use serde::Serialize;
+use serde_json::json;
+use std::str::FromStr;
+use strum::IntoEnumIterator;
+
+use crate::{
+    adapters::AppCapabilities,
+    api::loader::ItemLoader,
+    capabilities::*,
+    domain::{
+        crawl::UserCrawl,
+        decode::{FieldIssue, RawItem},
+        dwell::ListInterval,
+        list_diff::{ListDiff, RankChange},
+        query,
+        version::{FieldChange, ItemVersion},
+        ItemRank, ItemRecord, ItemScore, KarmaSnapshot, ListCategory, SearchHit,
+    },
+    infra::hn::types::{Item, User},
+    use_cases::{
+        load_list_at::{self, ListEntry},
+        load_list_diff, load_list_intervals,
+        load_user_timeline::{self, Timeline, TimelineEntry},
+        search_items::{self, SearchQuery, SearchResult},
+    },
+};
+
+pub type ApiResult = Result<content::Json<String>, Status>;
+
+pub fn to_json(value: &impl Serialize) -> ApiResult {
+    serde_json::to_string(value)
+        .map(content::Json)
+        .map_err(|_| Status::InternalServerError)
+}
+
+pub fn parse_category(input: &str) -> Result<ListCategory, Status> {
+    ListCategory::from_str(input).map_err(|_| Status::BadRequest)
+}
+
+pub fn parse_timestamp(input: &str) -> Result<DateTime<Utc>, Status> {
+    DateTime::parse_from_rfc3339(input)
+        .map(|ts| ts.with_timezone(&Utc))
+        .map_err(|_| Status::BadRequest)
+}
+
+pub fn internal_error<E>(_: E) -> Status {
+    Status::InternalServerError
+}
+
+/// The app with item lookups going through an `ItemLoader` kept for the
+/// request, the same way GraphQL resolvers load them.
+pub struct Items<'a> {
+    app: &'a AppCapabilities,
+    loader: &'a ItemLoader,
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for Items<'a> {
+    type Error = ();
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, ()> {
+        let app = request.guard::<State<AppCapabilities>>()?.inner();
+        let loader = request.local_cache(ItemLoader::default);
+
+        Outcome::Success(Items { app, loader })
+    }
+}
+
+impl LoadItems for Items<'_> {
+    fn load_items(&self, ids: Vec<u32>) -> Result<Vec<Item>> {
+        self.loader.load_items(self.app, ids)
+    }
+}
+
+impl LoadListAt for Items<'_> {
+    fn load_list_at(&self, category: ListCategory, ts: DateTime<Utc>) -> Result<Vec<u32>> {
+        self.app.load_list_at(category, ts)
+    }
+}
+
+impl LoadItemScoreAt for Items<'_> {
+    fn load_item_score_at(&self, id: u32, ts: DateTime<Utc>) -> Result<Option<ItemScore>> {
+        self.app.load_item_score_at(id, ts)
+    }
+}
+
+impl SearchItems for Items<'_> {
+    fn search_items(&self, text: &str, limit: u32) -> Result<Vec<SearchHit>> {
+        self.app.search_items(text, limit)
+    }
+}
+
+// RESPONSES
+#[derive(Serialize)]
+pub struct ItemRecordResponse {
+    id: u32,
+    original: serde_json::Value,
+    descendants: Option<u32>,
+    username: Option<String>,
+    score: Option<u32>,
+    title: Option<String>,
+    url: Option<String>,
+    body: Option<String>,
+    ts: Option<String>,
+    deleted: bool,
+    dead: bool,
+}
+
+impl From<ItemRecord> for ItemRecordResponse {
+    fn from(record: ItemRecord) -> Self {
+        Self {
+            id: record.id,
+            original: serde_json::from_str(&record.original).unwrap_or_default(),
+            descendants: record.descendants,
+            username: record.username,
+            score: record.score,
+            title: record.title,
+            url: record.url,
+            body: record.body,
+            ts: record.ts.map(|ts| ts.to_rfc3339()),
+            deleted: record.deleted,
+            dead: record.dead,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ItemRankResponse {
+    id: u32,
+    rank: u32,
+    category: String,
+    ts: String,
+}
+
+impl From<ItemRank> for ItemRankResponse {
+    fn from(item_rank: ItemRank) -> Self {
+        Self {
+            id: item_rank.id,
+            rank: item_rank.rank,
+            category: item_rank.category.to_string(),
+            ts: item_rank.ts.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ItemScoreResponse {
+    id: u32,
+    score: u32,
+    descendants: Option<u32>,
+    ts: String,
+}
+
+impl From<ItemScore> for ItemScoreResponse {
+    fn from(item_score: ItemScore) -> Self {
+        Self {
+            id: item_score.id,
+            score: item_score.score,
+            descendants: item_score.descendants,
+            ts: item_score.ts.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ListIntervalResponse {
+    id: u32,
+    category: String,
+    entered: String,
+    exited: Option<String>,
+    seconds: i64,
+}
+
+impl From<ListInterval> for ListIntervalResponse {
+    fn from(interval: ListInterval) -> Self {
+        Self {
+            id: interval.id,
+            category: interval.category.to_string(),
+            entered: interval.entered.to_rfc3339(),
+            exited: interval.exited.map(|ts| ts.to_rfc3339()),
+            seconds: interval.duration(&Utc::now()).num_seconds(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ItemVersionResponse {
+    id: u32,
+    ts: String,
+    original: serde_json::Value,
+    changes: Vec<FieldChange>,
+}
+
+impl From<ItemVersion> for ItemVersionResponse {
+    fn from(version: ItemVersion) -> Self {
+        Self {
+            id: version.id,
+            ts: version.ts.to_rfc3339(),
+            original: serde_json::from_str(&version.original).unwrap_or_default(),
+            changes: version.changes,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct RawItemResponse {
+    id: u32,
+    ts: String,
+    raw: serde_json::Value,
+    issues: Vec<FieldIssue>,
+    decoded: bool,
+}
+
+impl From<RawItem> for RawItemResponse {
+    fn from(raw_item: RawItem) -> Self {
+        Self {
+            id: raw_item.id,
+            ts: raw_item.ts.to_rfc3339(),
+            raw: serde_json::from_str(&raw_item.raw).unwrap_or_default(),
+            issues: raw_item.issues,
+            decoded: raw_item.decoded,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ListEntryResponse {
+    rank: u32,
+    item: Item,
+    score: Option<ItemScoreResponse>,
+}
+
+impl From<ListEntry> for ListEntryResponse {
+    fn from(entry: ListEntry) -> Self {
+        Self {
+            rank: entry.rank,
+            item: entry.item,
+            score: entry.score.map(ItemScoreResponse::from),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct RankChangeResponse {
+    id: u32,
+    from: Option<u32>,
+    to: Option<u32>,
+    delta: Option<i64>,
+}
+
+impl From<RankChange> for RankChangeResponse {
+    fn from(change: RankChange) -> Self {
+        Self {
+            delta: change.delta(),
+            id: change.id,
+            from: change.from,
+            to: change.to,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ListDiffResponse {
+    entered: Vec<RankChangeResponse>,
+    exited: Vec<RankChangeResponse>,
+    moved_up: Vec<RankChangeResponse>,
+    moved_down: Vec<RankChangeResponse>,
+}
+
+impl From<ListDiff> for ListDiffResponse {
+    fn from(diff: ListDiff) -> Self {
+        let convert = |changes: Vec<RankChange>| {
+            changes
+                .into_iter()
+                .map(RankChangeResponse::from)
+                .collect::<Vec<_>>()
+        };
+
+        Self {
+            entered: convert(diff.entered),
+            exited: convert(diff.exited),
+            moved_up: convert(diff.moved_up),
+            moved_down: convert(diff.moved_down),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct SearchResultResponse {
+    relevance: f64,
+    item: Item,
+}
+
+impl From<SearchResult> for SearchResultResponse {
+    fn from(result: SearchResult) -> Self {
+        Self {
+            relevance: result.relevance,
+            item: result.item,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct KarmaSnapshotResponse {
+    karma: u32,
+    ts: String,
+}
+
+impl From<KarmaSnapshot> for KarmaSnapshotResponse {
+    fn from(snapshot: KarmaSnapshot) -> Self {
+        Self {
+            karma: snapshot.karma,
+            ts: snapshot.ts.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct TimelineEntryResponse {
+    item: Item,
+    score: Option<ItemScoreResponse>,
+}
+
+impl From<TimelineEntry> for TimelineEntryResponse {
+    fn from(entry: TimelineEntry) -> Self {
+        Self {
+            item: entry.item,
+            score: entry.score.map(ItemScoreResponse::from),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct UserCrawlResponse {
+    status: String,
+    submitted: usize,
+    stored: usize,
+    unavailable: usize,
+    failed: usize,
+    error: Option<String>,
+    updated: String,
+}
+
+impl From<UserCrawl> for UserCrawlResponse {
+    fn from(crawl: UserCrawl) -> Self {
+        Self {
+            status: crawl.status.to_string(),
+            submitted: crawl.progress.submitted,
+            stored: crawl.progress.stored,
+            unavailable: crawl.progress.unavailable,
+            failed: crawl.progress.failed,
+            error: crawl.error,
+            updated: crawl.updated.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct TimelineResponse {
+    user: User,
+    stories: usize,
+    comments: usize,
+    points: u32,
+    missing: usize,
+    crawl: Option<UserCrawlResponse>,
+    entries: Vec<TimelineEntryResponse>,
+}
+
+impl From<Timeline> for TimelineResponse {
+    fn from(timeline: Timeline) -> Self {
+        Self {
+            user: timeline.user,
+            stories: timeline.stories,
+            comments: timeline.comments,
+            points: timeline.points,
+            missing: timeline.missing,
+            crawl: timeline.crawl.map(UserCrawlResponse::from),
+            entries: timeline
+                .entries
+                .into_iter()
+                .map(TimelineEntryResponse::from)
+                .collect(),
+        }
+    }
+}
+
+// ROUTES
+/// The current list, or with `at` the list as it was then, with ranks and
+/// the scores items had at the time.
+#[get("/lists/<category>?<at>")]
+pub fn get_list(
+    app: State<AppCapabilities>,
+    items: Items,
+    category: String,
+    at: Option<String>,
+) -> ApiResult {
+    let category = parse_category(&category)?;
+
+    if let Some(at) = at {
+        let entries = load_list_at::run(&items, category, parse_timestamp(&at)?)
+            .map_err(internal_error)?
+            .into_iter()
+            .map(ListEntryResponse::from)
+            .collect::<Vec<_>>();
+
+        return to_json(&entries);
+    }
+
+    let ids = app.load_list(category).map_err(internal_error)?;
+    let items = items.load_items(ids).map_err(internal_error)?;
+
+    to_json(&items)
+}
+
+/// What changed in a list between two moments, `to` defaulting to now.
+#[get("/lists/<category>/diff?<from>&<to>")]
+pub fn get_list_diff(
+    app: State<AppCapabilities>,
+    category: String,
+    from: String,
+    to: Option<String>,
+) -> ApiResult {
+    let category = parse_category(&category)?;
+    let from = parse_timestamp(&from)?;
+    let to = match to {
+        Some(to) => parse_timestamp(&to)?,
+        None => Utc::now(),
+    };
+    let diff = load_list_diff::run(app.inner(), category, from, to).map_err(internal_error)?;
+
+    to_json(&ListDiffResponse::from(diff))
+}
+
+#[get("/items/<id>")]
+pub fn get_item(app: State<AppCapabilities>, id: u32) -> ApiResult {
+    let record = app
+        .load_item_record(id)
+        .map_err(internal_error)?
+        .ok_or(Status::NotFound)?;
+
+    to_json(&ItemRecordResponse::from(record))
+}
+
+#[get("/items/<id>/ranks?<category>")]
+pub fn get_item_ranks(
+    app: State<AppCapabilities>,
+    id: u32,
+    category: Option<String>,
+) -> ApiResult {
+    let categories = match category {
+        Some(category) => vec![parse_category(&category)?],
+        None => ListCategory::iter().filter(|c| !c.is_derived()).collect(),
+    };
+
+    if app.load_item_record(id).map_err(internal_error)?.is_none() {
+        return Err(Status::NotFound);
+    }
+
+    let mut ranks = vec![];
+    for category in categories {
+        ranks.extend(
+            app.load_item_ranks(id, category)
+                .map_err(internal_error)?
+                .into_iter()
+                .map(ItemRankResponse::from),
+        );
+    }
+
+    to_json(&ranks)
+}
+
+#[get("/items/<id>/intervals?<category>")]
+pub fn get_item_intervals(
+    app: State<AppCapabilities>,
+    id: u32,
+    category: Option<String>,
+) -> ApiResult {
+    let categories = match category {
+        Some(category) => vec![parse_category(&category)?],
+        None => ListCategory::iter().filter(|c| !c.is_derived()).collect(),
+    };
+
+    if app.load_item_record(id).map_err(internal_error)?.is_none() {
+        return Err(Status::NotFound);
+    }
+
+    let mut intervals = vec![];
+    for category in categories {
+        intervals.extend(
+            load_list_intervals::run(app.inner(), id, category)
+                .map_err(internal_error)?
+                .into_iter()
+                .map(ListIntervalResponse::from),
+        );
+    }
+
+    to_json(&intervals)
+}
+
+#[get("/items/<id>/versions")]
+pub fn get_item_versions(app: State<AppCapabilities>, id: u32) -> ApiResult {
+    let versions = app
+        .load_item_versions(id)
+        .map_err(internal_error)?
+        .into_iter()
+        .map(ItemVersionResponse::from)
+        .collect::<Vec<_>>();
+
+    if versions.is_empty() {
+        return Err(Status::NotFound);
+    }
+
+    to_json(&versions)
+}
+
+#[get("/items/<id>/scores")]
+pub fn get_item_scores(app: State<AppCapabilities>, id: u32) -> ApiResult {
+    if app.load_item_record(id).map_err(internal_error)?.is_none() {
+        return Err(Status::NotFound);
+    }
+
+    let scores = app
+        .load_item_scores(id)
+        .map_err(internal_error)?
+        .into_iter()
+        .map(ItemScoreResponse::from)
+        .collect::<Vec<_>>();
+
+    to_json(&scores)
+}
+
+/// The stored profile along with how its karma has moved.
+#[get("/users/<id>")]
+pub fn get_user(app: State<AppCapabilities>, id: String) -> ApiResult {
+    let user = app
+        .load_user(&id)
+        .map_err(internal_error)?
+        .ok_or(Status::NotFound)?;
+    let karma = app
+        .load_karma_history(&id)
+        .map_err(internal_error)?
+        .into_iter()
+        .map(KarmaSnapshotResponse::from)
+        .collect::<Vec<_>>();
+
+    to_json(&json!({ "user": user, "karma": karma }))
+}
+
+/// A user's submissions from the local store, newest first, a page at a time.
+#[get("/users/<id>/timeline?<limit>&<offset>")]
+pub fn get_user_timeline(
+    app: State<AppCapabilities>,
+    id: String,
+    limit: Option<u32>,
+    offset: Option<u32>,
+) -> ApiResult {
+    let timeline =
+        load_user_timeline::run(app.inner(), &id, limit.unwrap_or(100), offset.unwrap_or(0))
+            .map_err(internal_error)?
+            .ok_or(Status::NotFound)?;
+
+    to_json(&TimelineResponse::from(timeline))
+}
+
+/// Queue a crawl of a user's submissions, unless one is queued or running
+/// already. The timeline's `crawl` shows how far along it is.
+#[post("/users/<id>/crawl")]
+pub fn post_user_crawl(
+    app: State<AppCapabilities>,
+    id: String,
+) -> Result<status::Accepted<content::Json<String>>, Status> {
+    let queued = app.queue_user_crawl(&id).map_err(internal_error)?;
+
+    Ok(status::Accepted(Some(content::Json(
+        json!({ "crawling": id, "queued": queued }).to_string(),
+    ))))
+}
+
+/// Issues found decoding every quarantined payload, most frequent first,
+/// along with the last `limit` payloads themselves.
+#[get("/decode-failures?<limit>")]
+pub fn get_decode_failures(app: State<AppCapabilities>, limit: Option<u32>) -> ApiResult {
+    let issues = app.load_decode_report().map_err(internal_error)?;
+    let raw_items = app
+        .load_raw_items(limit.unwrap_or(500))
+        .map_err(internal_error)?;
+    let recent = raw_items
+        .into_iter()
+        .map(RawItemResponse::from)
+        .collect::<Vec<_>>();
+
+    to_json(&json!({ "issues": issues, "recent": recent }))
+}
+
+#[get("/search?<q>&<limit>&<score_boost>&<recency_boost>")]
+pub fn get_search(
+    items: Items,
+    q: String,
+    limit: Option<u32>,
+    score_boost: Option<f64>,
+    recency_boost: Option<f64>,
+) -> ApiResult {
+    let query = SearchQuery {
+        limit: limit.unwrap_or(search_items::DEFAULT_LIMIT),
+        score_boost: score_boost.unwrap_or(0.0),
+        recency_boost: recency_boost.unwrap_or(0.0),
+        ..SearchQuery::new(&q)
+    };
+    let results = search_items::run(&items, &query, &Utc::now()).map_err(internal_error)?;
+
+    to_json(
+        &results
+            .into_iter()
+            .map(SearchResultResponse::from)
+            .collect::<Vec<_>>(),
+    )
+}
+
+#[get("/query?<q>&<limit>")]
+pub fn get_query(
+    app: State<AppCapabilities>,
+    q: String,
+    limit: Option<u32>,
+) -> Result<content::Json<String>, status::Custom<content::Json<String>>> {
+    let query = query::parse(&q).map_err(|e| {
+        status::Custom(
+            Status::BadRequest,
+            content::Json(json!({ "message": e.message, "position": e.position }).to_string()),
+        )
+    })?;
+    let limit = limit
+        .unwrap_or(search_items::DEFAULT_LIMIT)
+        .min(search_items::MAX_LIMIT);
+
+    app.query_items(&query, limit)
+        .map_err(internal_error)
+        .and_then(|items| to_json(&items))
+        .map_err(|status| status::Custom(status, content::Json("{}".to_string())))
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::{
+        domain::ListExit,
+        infra::hn::types::tests::{sample_items, sample_user},
+    };
+    use chrono::TimeZone;
+    use rocket::local::Client;
+
+    fn setup() -> Client {
+        let app = crate::adapters::test::setup();
+        let items = sample_items();
+        let ids = items.iter().map(|i| i.id()).collect::<Vec<_>>();
+        app.store_items(items).unwrap();
+        app.replace_list(
+            ListCategory::Top,
+            &[ids[1], ids[0]],
+            &Utc.ymd(2020, 1, 1).and_hms(0, 0, 0),
+        )
+        .unwrap();
+        app.replace_list(
+            ListCategory::Top,
+            &ids[..2],
+            &Utc.ymd(2020, 1, 2).and_hms(0, 0, 0),
+        )
+        .unwrap();
+        app.store_item_ranks(vec![ItemRank {
+            id: ids[0],
+            rank: 1,
+            category: ListCategory::Top,
+            ts: Utc.ymd(2020, 1, 1).and_hms(0, 0, 0),
+        }])
+        .unwrap();
+        app.store_list_exits(vec![ListExit {
+            id: ids[0],
+            category: ListCategory::Top,
+            ts: Utc.ymd(2020, 1, 1).and_hms(2, 0, 0),
+        }])
+        .unwrap();
+        app.store_raw_items(vec![RawItem {
+            id: 2,
+            ts: Utc.ymd(2020, 1, 1).and_hms(0, 0, 0),
+            raw: r#"{"id": 2, "type": "comment"}"#.to_string(),
+            issues: vec![FieldIssue {
+                field: "time".to_string(),
+                problem: "missing".to_string(),
+            }],
+            decoded: false,
+        }])
+        .unwrap();
+        app.store_item_scores(vec![
+            ItemScore {
+                id: ids[0],
+                score: 104,
+                descendants: Some(71),
+                ts: Utc.ymd(2020, 1, 1).and_hms(0, 0, 0),
+            },
+            ItemScore {
+                id: ids[5],
+                score: 335,
+                descendants: None,
+                ts: Utc.ymd(2020, 1, 1).and_hms(0, 0, 0),
+            },
+        ])
+        .unwrap();
+
+        app.store_user(sample_user()).unwrap();
+
+        Client::new(crate::api::rocket(app)).unwrap()
+    }
+
+    #[test]
+    fn list() {
+        let client = setup();
+        let mut response = client.get("/lists/top").dispatch();
+        let got: serde_json::Value =
+            serde_json::from_str(&response.body_string().unwrap()).unwrap();
+
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(got[0]["id"], 8863);
+        assert_eq!(got[1]["id"], 121003);
+    }
+
+    #[test]
+    fn list_at() {
+        let client = setup();
+        let mut response = client
+            .get("/lists/top?at=2020-01-01T09:00:00Z")
+            .dispatch();
+        let got: serde_json::Value =
+            serde_json::from_str(&response.body_string().unwrap()).unwrap();
+
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(got[0]["rank"], 1);
+        assert_eq!(got[0]["item"]["id"], 121003);
+        assert_eq!(got[1]["item"]["id"], 8863);
+        assert_eq!(got[1]["score"]["score"], 104);
+    }
+
+    #[test]
+    fn list_at_before_history() {
+        let client = setup();
+        let mut response = client
+            .get("/lists/top?at=2019-01-01T00:00:00Z")
+            .dispatch();
+
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.body_string().unwrap(), "[]");
+    }
+
+    #[test]
+    fn list_at_bad_timestamp() {
+        let client = setup();
+        let response = client.get("/lists/top?at=yesterday").dispatch();
+
+        assert_eq!(response.status(), Status::BadRequest);
+    }
+
+    #[test]
+    fn list_diff() {
+        let client = setup();
+        let mut response = client
+            .get("/lists/top/diff?from=2020-01-01T09:00:00Z&to=2020-01-02T09:00:00Z")
+            .dispatch();
+        let got: serde_json::Value =
+            serde_json::from_str(&response.body_string().unwrap()).unwrap();
+
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(got["moved_up"][0]["id"], 8863);
+        assert_eq!(got["moved_up"][0]["delta"], 1);
+        assert_eq!(got["moved_down"][0]["id"], 121003);
+        assert_eq!(got["entered"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn list_with_bad_category() {
+        let client = setup();
+        let response = client.get("/lists/nope").dispatch();
+
+        assert_eq!(response.status(), Status::BadRequest);
+    }
+
+    #[test]
+    fn item() {
+        let client = setup();
+        let mut response = client.get("/items/8863").dispatch();
+        let got: serde_json::Value =
+            serde_json::from_str(&response.body_string().unwrap()).unwrap();
+
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(got["original"]["by"], "dhouston");
+        assert_eq!(got["score"], 104);
+    }
+
+    #[test]
+    fn item_not_found() {
+        let client = setup();
+        let response = client.get("/items/404").dispatch();
+
+        assert_eq!(response.status(), Status::NotFound);
+    }
+
+    #[test]
+    fn item_ranks() {
+        let client = setup();
+        let mut response = client.get("/items/8863/ranks?category=top").dispatch();
+        let got: serde_json::Value =
+            serde_json::from_str(&response.body_string().unwrap()).unwrap();
+
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(got[0]["rank"], 1);
+        assert_eq!(got[0]["category"], "top");
+    }
+
+    #[test]
+    fn item_intervals() {
+        let client = setup();
+        let mut response = client.get("/items/8863/intervals?category=top").dispatch();
+        let got: serde_json::Value =
+            serde_json::from_str(&response.body_string().unwrap()).unwrap();
+
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(got[0]["entered"], "2020-01-01T00:00:00+00:00");
+        assert_eq!(got[0]["exited"], "2020-01-01T02:00:00+00:00");
+        assert_eq!(got[0]["seconds"], 7200);
+    }
+
+    #[test]
+    fn item_versions() {
+        let client = setup();
+        let mut response = client.get("/items/8863/versions").dispatch();
+        let got: serde_json::Value =
+            serde_json::from_str(&response.body_string().unwrap()).unwrap();
+
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(got[0]["original"]["by"], "dhouston");
+        assert_eq!(got[0]["changes"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn item_scores() {
+        let client = setup();
+        let mut response = client.get("/items/8863/scores").dispatch();
+        let got: serde_json::Value =
+            serde_json::from_str(&response.body_string().unwrap()).unwrap();
+
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(got[0]["score"], 104);
+        assert_eq!(got[0]["descendants"], 71);
+    }
+
+    #[test]
+    fn user() {
+        let client = setup();
+        let mut response = client.get("/users/pg").dispatch();
+        let got: serde_json::Value =
+            serde_json::from_str(&response.body_string().unwrap()).unwrap();
+
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(got["user"]["karma"], 155111);
+        assert_eq!(got["karma"][0]["karma"], 155111);
+
+        let response = client.get("/users/nobody").dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+    }
+
+    #[test]
+    fn user_timeline() {
+        let client = setup();
+        let mut response = client.get("/users/pg/timeline").dispatch();
+        let got: serde_json::Value =
+            serde_json::from_str(&response.body_string().unwrap()).unwrap();
+
+        // A poll and one of its options
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(got["points"], 382);
+        assert_eq!(got["missing"], 0);
+        assert_eq!(got["crawl"], serde_json::Value::Null);
+        assert_eq!(got["entries"][0]["item"]["id"], 160705);
+        assert_eq!(got["entries"][0]["score"]["score"], 335);
+        assert_eq!(got["entries"][1]["score"], serde_json::Value::Null);
+
+        let mut response = client
+            .get("/users/pg/timeline?limit=1&offset=1")
+            .dispatch();
+        let got: serde_json::Value =
+            serde_json::from_str(&response.body_string().unwrap()).unwrap();
+        assert_eq!(got["entries"].as_array().unwrap().len(), 1);
+        assert_eq!(got["entries"][0]["item"]["id"], 126809);
+
+        let response = client.get("/users/nobody/timeline").dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+    }
+
+    #[test]
+    fn user_crawl() {
+        let client = setup();
+        let mut response = client.post("/users/pg/crawl").dispatch();
+        let got: serde_json::Value =
+            serde_json::from_str(&response.body_string().unwrap()).unwrap();
+
+        assert_eq!(response.status(), Status::Accepted);
+        assert_eq!(got["queued"], true);
+
+        // Nothing works the queue here, so it's still waiting
+        let mut response = client.post("/users/pg/crawl").dispatch();
+        let got: serde_json::Value =
+            serde_json::from_str(&response.body_string().unwrap()).unwrap();
+        assert_eq!(got["queued"], false);
+
+        let mut response = client.get("/users/pg/timeline").dispatch();
+        let got: serde_json::Value =
+            serde_json::from_str(&response.body_string().unwrap()).unwrap();
+        assert_eq!(got["crawl"]["status"], "queued");
+    }
+
+    #[test]
+    fn decode_failures() {
+        let client = setup();
+        let mut response = client.get("/decode-failures").dispatch();
+        let got: serde_json::Value =
+            serde_json::from_str(&response.body_string().unwrap()).unwrap();
+
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(got["issues"][0]["field"], "time");
+        assert_eq!(got["issues"][0]["lost"], 1);
+        assert_eq!(got["recent"][0]["raw"]["type"], "comment");
+    }
+
+    #[test]
+    fn query() {
+        let client = setup();
+        let mut response = client.get("/query?q=author:pg%20type:poll").dispatch();
+        let got: serde_json::Value =
+            serde_json::from_str(&response.body_string().unwrap()).unwrap();
+
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(got[0]["id"], 126809);
+    }
+
+    #[test]
+    fn query_with_parse_error() {
+        let client = setup();
+        let mut response = client.get("/query?q=rust%20color:red").dispatch();
+        let got: serde_json::Value =
+            serde_json::from_str(&response.body_string().unwrap()).unwrap();
+
+        assert_eq!(response.status(), Status::BadRequest);
+        assert_eq!(got["message"], "Unknown field 'color'");
+        assert_eq!(got["position"], 5);
+    }
+}
@@ -1,9 +1,23 @@
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use serde::{de::DeserializeOwned, ser::Serialize};
+use std::collections::{HashMap, HashSet};
 
 use crate::{
-    domain::{ItemRank, ListCategory},
-    infra::hn::types::Item,
+    domain::{
+        backfill::IdRange,
+        crawl::UserCrawl,
+        decode::{IssueCount, RawItem},
+        fetch::{FetchFailure, FetchOutcome},
+        query::Query,
+        version::ItemVersion,
+        Event, ItemRank, ItemRecord, ItemScore, KarmaSnapshot, ListCategory, ListExit, SearchHit,
+        UserActivity,
+    },
+    infra::{
+        events::Subscription,
+        hn::types::{Item, Updates, User},
+    },
 };
 
 // LISTS
@@ -14,7 +28,7 @@ pub trait StoreList {
 
 #[mockall::automock]
 pub trait ReplaceList {
-    fn replace_list(&self, category: ListCategory, ids: &[u32]) -> Result<()>;
+    fn replace_list(&self, category: ListCategory, ids: &[u32], ts: &DateTime<Utc>) -> Result<()>;
 }
 
 #[mockall::automock]
@@ -22,6 +36,11 @@ pub trait LoadList {
     fn load_list(&self, category: ListCategory) -> Result<Vec<u32>>;
 }
 
+#[mockall::automock]
+pub trait LoadListAt {
+    fn load_list_at(&self, category: ListCategory, ts: DateTime<Utc>) -> Result<Vec<u32>>;
+}
+
 #[mockall::automock]
 pub trait FetchList {
     fn fetch_list(&self, category: ListCategory) -> Result<Vec<u32>>;
@@ -38,9 +57,38 @@ pub trait LoadItem {
     fn load_item(&self, ids: u32) -> Result<Option<Item>>;
 }
 
+#[mockall::automock]
+pub trait LoadItemRecord {
+    fn load_item_record(&self, id: u32) -> Result<Option<ItemRecord>>;
+}
+
+#[mockall::automock]
+pub trait LoadStoredItemIds {
+    /// Which of `ids` are in the item table.
+    fn load_stored_item_ids(&self, ids: Vec<u32>) -> Result<HashSet<u32>>;
+}
+
+#[mockall::automock]
+pub trait LoadItemGaps {
+    /// Ranges of ids up to `through` that were never accounted for: not
+    /// stored, quarantined, failed or known to be null.
+    fn load_item_gaps(&self, through: u32) -> Result<Vec<IdRange>>;
+}
+
+#[mockall::automock]
+pub trait LoadItemVersions {
+    fn load_item_versions(&self, id: u32) -> Result<Vec<ItemVersion>>;
+}
+
 #[mockall::automock]
 pub trait StoreItems {
-    fn store_items(&self, items: Vec<Item>) -> Result<()>;
+    /// Returns the ids of the items whose stored payload changed.
+    fn store_items(&self, items: Vec<Item>) -> Result<Vec<u32>>;
+}
+
+#[mockall::automock]
+pub trait StoreItemsBulk {
+    fn store_items_bulk(&self, items: Vec<Item>) -> Result<()>;
 }
 
 #[mockall::automock]
@@ -58,9 +106,60 @@ pub trait FetchItems {
     fn fetch_items(&self, ids: Vec<u32>) -> Result<Vec<Item>>;
 }
 
+#[mockall::automock]
+pub trait FetchItemOutcomes {
+    fn fetch_item_outcomes(&self, ids: Vec<u32>) -> Result<Vec<FetchOutcome>>;
+}
+
+#[mockall::automock]
+pub trait FetchMaxItemId {
+    fn fetch_max_item_id(&self) -> Result<u32>;
+}
+
 #[mockall::automock]
 pub trait FetchUpdates {
-    fn fetch_updates(&self) -> Result<Vec<u32>>;
+    fn fetch_updates(&self) -> Result<Updates>;
+}
+
+// FETCH FAILURES
+#[mockall::automock]
+pub trait LoadDueFetchFailures {
+    fn load_due_fetch_failures(&self, now: DateTime<Utc>, limit: u32) -> Result<Vec<FetchFailure>>;
+}
+
+// DECODING
+#[mockall::automock]
+pub trait StoreRawItems {
+    fn store_raw_items(&self, raw_items: Vec<RawItem>) -> Result<()>;
+}
+
+#[mockall::automock]
+pub trait LoadRawItems {
+    fn load_raw_items(&self, limit: u32) -> Result<Vec<RawItem>>;
+}
+
+#[mockall::automock]
+pub trait LoadDecodeReport {
+    /// Every issue seen across all quarantined payloads, most frequent first.
+    fn load_decode_report(&self) -> Result<Vec<IssueCount>>;
+}
+
+// SEARCH
+#[mockall::automock]
+pub trait SearchItems {
+    fn search_items(&self, text: &str, limit: u32) -> Result<Vec<SearchHit>>;
+}
+
+#[mockall::automock]
+pub trait RefreshSearchIndex {
+    /// Rebuild the index if anything was written since the last rebuild,
+    /// returning whether it did.
+    fn refresh_search_index(&self) -> Result<bool>;
+}
+
+#[mockall::automock]
+pub trait QueryItems {
+    fn query_items(&self, query: &Query, limit: u32) -> Result<Vec<Item>>;
 }
 
 // ITEM RANKS
@@ -75,8 +174,135 @@ pub trait LoadItemRanks {
 }
 
 #[mockall::automock]
-pub trait LoadLatestItemRank {
-    fn load_latest_item_rank(&self, id: u32, category: ListCategory) -> Result<Option<ItemRank>>;
+pub trait LoadLatestItemRanks {
+    /// The most recent rank in a list of each of `ids` that has one.
+    fn load_latest_item_ranks(
+        &self,
+        ids: Vec<u32>,
+        category: ListCategory,
+    ) -> Result<HashMap<u32, ItemRank>>;
+}
+
+// LIST EXITS
+#[mockall::automock]
+pub trait StoreListExits {
+    fn store_list_exits(&self, list_exits: Vec<ListExit>) -> Result<()>;
+}
+
+#[mockall::automock]
+pub trait LoadListExits {
+    fn load_list_exits(&self, id: u32, category: ListCategory) -> Result<Vec<ListExit>>;
+}
+
+// ITEM SCORES
+#[mockall::automock]
+pub trait StoreItemScores {
+    fn store_item_scores(&self, item_scores: Vec<ItemScore>) -> Result<()>;
+}
+
+#[mockall::automock]
+pub trait LoadItemScores {
+    fn load_item_scores(&self, id: u32) -> Result<Vec<ItemScore>>;
+}
+
+#[mockall::automock]
+pub trait LoadLatestItemScore {
+    fn load_latest_item_score(&self, id: u32) -> Result<Option<ItemScore>>;
+}
+
+#[mockall::automock]
+pub trait LoadItemScoreAt {
+    fn load_item_score_at(&self, id: u32, ts: DateTime<Utc>) -> Result<Option<ItemScore>>;
+}
+
+// USERS
+#[mockall::automock]
+pub trait FetchUsers {
+    /// Each of `usernames` paired with what HN returned for it.
+    fn fetch_users(&self, usernames: Vec<String>) -> Result<Vec<(String, Result<Option<User>>)>>;
+}
+
+#[mockall::automock]
+pub trait FetchUser {
+    fn fetch_user(&self, username: &str) -> Result<Option<User>>;
+}
+
+#[mockall::automock]
+pub trait StoreUsers {
+    fn store_users(&self, users: Vec<User>) -> Result<()>;
+}
+
+#[mockall::automock]
+pub trait StoreUser {
+    fn store_user(&self, user: User) -> Result<()>;
+}
+
+#[mockall::automock]
+pub trait LoadUser {
+    fn load_user(&self, username: &str) -> Result<Option<User>>;
+}
+
+#[mockall::automock]
+pub trait LoadStoredUsernames {
+    /// Which of `usernames` are in the user table.
+    fn load_stored_usernames(&self, usernames: Vec<String>) -> Result<HashSet<String>>;
+}
+
+#[mockall::automock]
+pub trait LoadKarmaHistory {
+    fn load_karma_history(&self, username: &str) -> Result<Vec<KarmaSnapshot>>;
+}
+
+#[mockall::automock]
+pub trait LoadUserActivity {
+    fn load_user_activity(&self, username: &str) -> Result<UserActivity>;
+}
+
+#[mockall::automock]
+pub trait LoadUserItems {
+    /// The user's stored submissions, newest first.
+    fn load_user_items(&self, username: &str, limit: u32, offset: u32) -> Result<Vec<Item>>;
+}
+
+// CRAWLS
+#[mockall::automock]
+pub trait StoreUserCrawl {
+    fn store_user_crawl(&self, crawl: UserCrawl) -> Result<()>;
+}
+
+#[mockall::automock]
+pub trait LoadUserCrawl {
+    fn load_user_crawl(&self, username: &str) -> Result<Option<UserCrawl>>;
+}
+
+#[mockall::automock]
+pub trait QueueUserCrawl {
+    /// Queue a crawl unless one is queued or running for the user already,
+    /// returning whether it was queued.
+    fn queue_user_crawl(&self, username: &str) -> Result<bool>;
+}
+
+#[mockall::automock]
+pub trait LoadUnfinishedUserCrawls {
+    /// Users whose crawl was queued or running, in the order they were
+    /// last updated.
+    fn load_unfinished_user_crawls(&self) -> Result<Vec<String>>;
+}
+
+pub trait WorkUserCrawls {
+    /// Hand queued usernames to `crawl` one at a time, forever.
+    fn work_user_crawls(&self, crawl: impl FnMut(&str)) -> Result<()>;
+}
+
+// EVENTS
+#[mockall::automock]
+pub trait PublishEvents {
+    fn publish_events(&self, events: Vec<Event>) -> Result<()>;
+}
+
+pub trait SubscribeEvents {
+    /// `None` when there are already as many subscribers as are allowed.
+    fn subscribe_events(&self) -> Result<Option<Subscription>>;
 }
 
 // CONFIG
@@ -86,3 +312,10 @@ pub trait StoreConfigValue {
 pub trait LoadConfigValue {
     fn load_config_value_as<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>>;
 }
+pub trait LoadConfigValues {
+    /// Every value with a key starting with `prefix`.
+    fn load_config_values_as<T: DeserializeOwned>(&self, prefix: &str) -> Result<Vec<T>>;
+}
+pub trait DeleteConfigValue {
+    fn delete_config_value(&self, key: &str) -> Result<()>;
+}
@@ -0,0 +1,295 @@
+//! Which ids are left to backfill, newest first.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Ids from `start` to `end`, inclusive.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct IdRange {
+    pub start: u32,
+    pub end: u32,
+}
+
+/// Config keys of queued ranges start with this, each range checkpoints
+/// under its own key.
+pub const RANGE_KEY_PREFIX: &str = "backfill_range:";
+
+/// A range being walked from the top down. Everything above `next` is done.
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct BackfillRange {
+    pub start: u32,
+    pub end: u32,
+    pub next: u32,
+}
+
+impl BackfillRange {
+    pub fn new(range: &IdRange) -> Self {
+        Self {
+            start: range.start,
+            end: range.end,
+            next: range.end,
+        }
+    }
+
+    pub fn key(&self) -> String {
+        format!("{}{}-{}", RANGE_KEY_PREFIX, self.start, self.end)
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.next < self.start
+    }
+
+    /// The next `count` ids to fetch, highest first.
+    pub fn batch(&self, count: u32) -> Vec<u32> {
+        if self.is_done() || count == 0 {
+            return vec![];
+        }
+        let low = self.next.saturating_sub(count - 1).max(self.start);
+
+        (low..=self.next).rev().collect()
+    }
+
+    /// Move past a batch once it's been fetched. Failures are in the ledger
+    /// for `retry_failed_fetches` by then, and an id that somehow got no
+    /// outcome at all is left for the gap scan, so nothing holds the range.
+    pub fn advance(&mut self, batch: &[u32]) {
+        if let Some(low) = batch.iter().min() {
+            self.next = self.next.min(low - 1);
+        }
+    }
+
+    // The ids not fetched yet
+    fn remaining(&self) -> Option<IdRange> {
+        Some(IdRange {
+            start: self.start,
+            end: self.next,
+        })
+        .filter(|_| !self.is_done())
+    }
+}
+
+/// How many ids are left across the queue.
+pub fn remaining(queue: &[BackfillRange]) -> u64 {
+    queue
+        .iter()
+        .filter_map(BackfillRange::remaining)
+        .map(|range| (range.end - range.start) as u64 + 1)
+        .sum()
+}
+
+/// Throughput of a backfill run.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Progress {
+    pub started: DateTime<Utc>,
+    pub done: u64,
+    pub remaining: u64,
+}
+
+impl Progress {
+    pub fn new(started: DateTime<Utc>, remaining: u64) -> Self {
+        Self {
+            started,
+            done: 0,
+            remaining,
+        }
+    }
+
+    pub fn record(&mut self, done: u64, remaining: u64) {
+        self.done += done;
+        self.remaining = remaining;
+    }
+
+    pub fn per_second(&self, now: &DateTime<Utc>) -> f64 {
+        let elapsed = (*now - self.started).num_milliseconds();
+        if elapsed <= 0 {
+            return 0.0;
+        }
+
+        self.done as f64 * 1000.0 / elapsed as f64
+    }
+
+    /// At the rate so far, `None` until anything is done.
+    pub fn eta(&self, now: &DateTime<Utc>) -> Option<Duration> {
+        let per_second = self.per_second(now);
+        if per_second <= 0.0 {
+            return None;
+        }
+
+        Some(Duration::seconds(
+            (self.remaining as f64 / per_second).ceil() as i64,
+        ))
+    }
+}
+
+/// Newest first, the order ranges are worked in.
+pub fn sort_queue(queue: &mut Vec<BackfillRange>) {
+    queue.retain(|range| !range.is_done());
+    queue.sort_by(|a, b| b.next.cmp(&a.next));
+}
+
+/// Queue ranges for backfill, skipping ids already waiting in the queue, and
+/// return the ranges added.
+pub fn schedule(queue: &mut Vec<BackfillRange>, ranges: &[IdRange]) -> Vec<BackfillRange> {
+    let mut added = vec![];
+    for range in ranges {
+        let mut pieces = vec![range.clone()];
+        for queued in queue.iter().filter_map(BackfillRange::remaining) {
+            pieces = pieces
+                .iter()
+                .flat_map(|piece| subtract(piece, &queued))
+                .collect();
+        }
+        added.extend(pieces.iter().map(BackfillRange::new));
+    }
+
+    queue.extend(added.iter().cloned());
+    sort_queue(queue);
+
+    added
+}
+
+// What's left of `range` outside of `other`
+fn subtract(range: &IdRange, other: &IdRange) -> Vec<IdRange> {
+    if other.end < range.start || other.start > range.end {
+        return vec![range.clone()];
+    }
+
+    let mut pieces = vec![];
+    if other.start > range.start {
+        pieces.push(IdRange {
+            start: range.start,
+            end: other.start - 1,
+        });
+    }
+    if other.end < range.end {
+        pieces.push(IdRange {
+            start: other.end + 1,
+            end: range.end,
+        });
+    }
+
+    pieces
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn range(start: u32, end: u32) -> IdRange {
+        IdRange { start, end }
+    }
+
+    #[test]
+    fn batches_walk_down() {
+        let mut backfill = BackfillRange::new(&range(1, 5));
+
+        let batch = backfill.batch(3);
+        assert_eq!(batch, vec![5, 4, 3]);
+        backfill.advance(&batch);
+        assert_eq!(backfill.next, 2);
+
+        let batch = backfill.batch(3);
+        assert_eq!(batch, vec![2, 1]);
+        backfill.advance(&batch);
+        assert!(backfill.is_done());
+        assert_eq!(backfill.batch(3), vec![]);
+    }
+
+    #[test]
+    fn advance_never_moves_back() {
+        let mut backfill = BackfillRange {
+            start: 1,
+            end: 10,
+            next: 4,
+        };
+        backfill.advance(&[]);
+        assert_eq!(backfill.next, 4);
+        backfill.advance(&[8, 7]);
+        assert_eq!(backfill.next, 4);
+    }
+
+    #[test]
+    fn subtract_ranges() {
+        assert_eq!(subtract(&range(1, 10), &range(20, 30)), vec![range(1, 10)]);
+        assert_eq!(
+            subtract(&range(1, 10), &range(4, 6)),
+            vec![range(1, 3), range(7, 10)]
+        );
+        assert_eq!(subtract(&range(1, 10), &range(1, 6)), vec![range(7, 10)]);
+        assert_eq!(subtract(&range(1, 10), &range(0, 12)), vec![]);
+    }
+
+    #[test]
+    fn schedule_newest_first() {
+        let mut queue = vec![];
+        schedule(&mut queue, &[range(1, 100)]);
+        schedule(&mut queue, &[range(101, 120)]);
+
+        assert_eq!(
+            queue,
+            vec![
+                BackfillRange::new(&range(101, 120)),
+                BackfillRange::new(&range(1, 100)),
+            ]
+        );
+    }
+
+    #[test]
+    fn schedule_skips_queued_ids() {
+        let mut queue = vec![BackfillRange {
+            start: 1,
+            end: 100,
+            next: 50,
+        }];
+        // 51 to 100 are done, so only the gap above them is new
+        let added = schedule(&mut queue, &[range(40, 60), range(10, 20)]);
+        assert_eq!(added, vec![BackfillRange::new(&range(51, 60))]);
+
+        assert_eq!(
+            queue,
+            vec![
+                BackfillRange::new(&range(51, 60)),
+                BackfillRange {
+                    start: 1,
+                    end: 100,
+                    next: 50,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn remaining_ids() {
+        let queue = vec![
+            BackfillRange {
+                start: 1,
+                end: 100,
+                next: 50,
+            },
+            BackfillRange::new(&range(200, 209)),
+            BackfillRange {
+                start: 300,
+                end: 400,
+                next: 299,
+            },
+        ];
+
+        assert_eq!(remaining(&queue), 60);
+    }
+
+    #[test]
+    fn progress_rate_and_eta() {
+        use chrono::TimeZone;
+
+        let started = Utc.ymd(2020, 1, 1).and_hms(0, 0, 0);
+        let mut progress = Progress::new(started, 1000);
+        assert_eq!(progress.eta(&started), None);
+
+        progress.record(100, 900);
+        progress.record(100, 800);
+        let now = started + Duration::seconds(20);
+
+        assert_eq!(progress.per_second(&now), 10.0);
+        assert_eq!(progress.eta(&now), Some(Duration::seconds(80)));
+    }
+}
@@ -0,0 +1,94 @@
+//! Crawls of a user's submitted history, and how far along each one is.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use std::{fmt, str::FromStr};
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct CrawlProgress {
+    pub submitted: usize,
+    /// Stored items, including any from before this crawl.
+    pub stored: usize,
+    /// Submitted ids HN returned nothing for, or that didn't decode.
+    pub unavailable: usize,
+    /// Left for the failure ledger to retry.
+    pub failed: usize,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum CrawlStatus {
+    Queued,
+    Running,
+    Done,
+    /// HN has no such user.
+    NoSuchUser,
+    Failed,
+}
+
+impl fmt::Display for CrawlStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Queued => write!(f, "queued"),
+            Self::Running => write!(f, "running"),
+            Self::Done => write!(f, "done"),
+            Self::NoSuchUser => write!(f, "no such user"),
+            Self::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+impl FromStr for CrawlStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        match input {
+            "queued" => Ok(Self::Queued),
+            "running" => Ok(Self::Running),
+            "done" => Ok(Self::Done),
+            "no such user" => Ok(Self::NoSuchUser),
+            "failed" => Ok(Self::Failed),
+            _ => Err(anyhow!("Invalid CrawlStatus")),
+        }
+    }
+}
+
+/// The latest crawl of a user, updated as it goes.
+#[derive(Debug, PartialEq, Clone)]
+pub struct UserCrawl {
+    pub username: String,
+    pub status: CrawlStatus,
+    pub progress: CrawlProgress,
+    /// What stopped a failed crawl.
+    pub error: Option<String>,
+    pub updated: DateTime<Utc>,
+}
+
+impl UserCrawl {
+    pub fn new(username: &str, status: CrawlStatus, now: &DateTime<Utc>) -> Self {
+        Self {
+            username: username.to_string(),
+            status,
+            progress: CrawlProgress::default(),
+            error: None,
+            updated: *now,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn crawl_status_round_trip() {
+        for status in vec![
+            CrawlStatus::Queued,
+            CrawlStatus::Running,
+            CrawlStatus::Done,
+            CrawlStatus::NoSuchUser,
+            CrawlStatus::Failed,
+        ] {
+            assert_eq!(status.to_string().parse::<CrawlStatus>().unwrap(), status);
+        }
+    }
+}
@@ -0,0 +1,38 @@
+//! Items that didn't decode cleanly, kept so schema drift can be fixed.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A field of a payload that didn't match what we expect, e.g. `missing`,
+/// `expected integer, found string` or `unknown field`.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct FieldIssue {
+    pub field: String,
+    pub problem: String,
+}
+
+/// A payload as fetched from HN along with what was wrong with it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct RawItem {
+    pub id: u32,
+    pub ts: DateTime<Utc>,
+    pub raw: String,
+    pub issues: Vec<FieldIssue>,
+    /// Whether the item still decoded and was stored, so the issues are
+    /// drift rather than data loss.
+    pub decoded: bool,
+}
+
+/// How often an issue has been seen, with a few ids to look at.
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct IssueCount {
+    pub field: String,
+    pub problem: String,
+    pub count: usize,
+    /// Items that failed to decode because of this issue.
+    pub lost: usize,
+    pub example_ids: Vec<u32>,
+}
+
+/// How many example ids a report keeps per issue.
+pub const EXAMPLES: usize = 5;
@@ -0,0 +1,139 @@
+//! How long items stay on a list.
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::domain::{ItemRank, ListCategory, ListExit};
+
+/// A continuous stretch of time an item spent on a list. `exited` is `None`
+/// while the item is still on it.
+#[derive(PartialEq, Debug, Clone)]
+pub struct ListInterval {
+    pub id: u32,
+    pub category: ListCategory,
+    pub entered: DateTime<Utc>,
+    pub exited: Option<DateTime<Utc>>,
+}
+
+impl ListInterval {
+    pub fn duration(&self, now: &DateTime<Utc>) -> Duration {
+        self.exited.unwrap_or(*now) - self.entered
+    }
+}
+
+/// Stitch rank changes and exits for one item and category into intervals.
+///
+/// A rank after an exit (or the first rank ever) opens an interval, and the
+/// next exit closes it. Ranks in between are just moves.
+pub fn list_intervals(ranks: &[ItemRank], exits: &[ListExit]) -> Vec<ListInterval> {
+    let mut ranks = ranks.iter().collect::<Vec<_>>();
+    ranks.sort_by_key(|rank| rank.ts);
+    let mut exits = exits.iter().collect::<Vec<_>>();
+    exits.sort_by_key(|exit| exit.ts);
+
+    let mut results = vec![];
+    let mut exits = exits.into_iter().peekable();
+    let mut open: Option<&ItemRank> = None;
+
+    for rank in ranks {
+        // Close the open interval if it ended before this rank
+        if let Some(entered) = open {
+            if let Some(exit) = exits.next_if(|exit| exit.ts <= rank.ts) {
+                results.push(interval(entered, Some(exit.ts)));
+                open = None;
+            }
+        }
+
+        // Exits with no interval open are stale, skip past them
+        while exits.next_if(|exit| exit.ts <= rank.ts).is_some() {}
+
+        if open.is_none() {
+            open = Some(rank);
+        }
+    }
+
+    if let Some(entered) = open {
+        results.push(interval(entered, exits.next().map(|exit| exit.ts)));
+    }
+
+    results
+}
+
+fn interval(entered: &ItemRank, exited: Option<DateTime<Utc>>) -> ListInterval {
+    ListInterval {
+        id: entered.id,
+        category: entered.category.clone(),
+        entered: entered.ts,
+        exited,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(hour: u32) -> DateTime<Utc> {
+        Utc.ymd(2020, 1, 1).and_hms(hour, 0, 0)
+    }
+
+    fn rank(rank: u32, hour: u32) -> ItemRank {
+        ItemRank {
+            id: 1,
+            rank,
+            category: ListCategory::Top,
+            ts: at(hour),
+        }
+    }
+
+    fn exit(hour: u32) -> ListExit {
+        ListExit {
+            id: 1,
+            category: ListCategory::Top,
+            ts: at(hour),
+        }
+    }
+
+    #[test]
+    fn intervals_from_ranks_and_exits() {
+        let ranks = vec![rank(5, 1), rank(3, 2), rank(10, 6), rank(8, 7)];
+        let exits = vec![exit(4)];
+
+        let got = list_intervals(&ranks, &exits);
+        let want = vec![
+            ListInterval {
+                id: 1,
+                category: ListCategory::Top,
+                entered: at(1),
+                exited: Some(at(4)),
+            },
+            ListInterval {
+                id: 1,
+                category: ListCategory::Top,
+                entered: at(6),
+                exited: None,
+            },
+        ];
+
+        assert_eq!(got, want);
+        assert_eq!(got[0].duration(&at(12)), Duration::hours(3));
+        assert_eq!(got[1].duration(&at(12)), Duration::hours(6));
+    }
+
+    #[test]
+    fn closed_interval() {
+        let got = list_intervals(&[rank(1, 1)], &[exit(2)]);
+        let want = vec![ListInterval {
+            id: 1,
+            category: ListCategory::Top,
+            entered: at(1),
+            exited: Some(at(2)),
+        }];
+
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn no_ranks() {
+        assert_eq!(list_intervals(&[], &[exit(2)]), vec![]);
+    }
+}
@@ -0,0 +1,239 @@
+//! What happened to each id we asked HN for, and the ledger of failures.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+use crate::infra::hn::types::Item;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum FailureKind {
+    Timeout,
+    /// A non-success HTTP status.
+    Status(u16),
+    /// The response body wasn't JSON.
+    Body,
+    /// Anything else on the way there, e.g. DNS or a dropped connection.
+    Connection,
+}
+
+impl fmt::Display for FailureKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Timeout => write!(f, "timeout"),
+            Self::Status(status) => write!(f, "status {}", status),
+            Self::Body => write!(f, "body"),
+            Self::Connection => write!(f, "connection"),
+        }
+    }
+}
+
+impl FailureKind {
+    /// Whether trying again can't help, e.g. a 404.
+    pub fn is_permanent(&self) -> bool {
+        match self {
+            // Timeouts and rate limits
+            Self::Status(408) | Self::Status(429) => false,
+            Self::Status(status) => (400..500).contains(status),
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for FailureKind {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        match input {
+            "timeout" => Ok(Self::Timeout),
+            "body" => Ok(Self::Body),
+            "connection" => Ok(Self::Connection),
+            _ => input
+                .strip_prefix("status ")
+                .and_then(|status| status.parse().ok())
+                .map(Self::Status)
+                .ok_or_else(|| anyhow!("Invalid FailureKind")),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum FetchOutcome {
+    Found(Item),
+    /// HN returned null, the id doesn't exist (yet).
+    Missing(u32),
+    /// The payload was kept in `raw_item` but didn't decode.
+    Quarantined(u32),
+    Failed {
+        id: u32,
+        kind: FailureKind,
+        message: String,
+    },
+}
+
+impl FetchOutcome {
+    pub fn id(&self) -> u32 {
+        match self {
+            Self::Found(item) => item.id(),
+            Self::Missing(id) | Self::Quarantined(id) => *id,
+            Self::Failed { id, .. } => *id,
+        }
+    }
+
+    pub fn into_item(self) -> Option<Item> {
+        match self {
+            Self::Found(item) => Some(item),
+            _ => None,
+        }
+    }
+}
+
+/// An id that couldn't be fetched, and when to try again.
+#[derive(Debug, PartialEq, Clone)]
+pub struct FetchFailure {
+    pub id: u32,
+    pub kind: FailureKind,
+    pub message: String,
+    pub attempts: u32,
+    pub first_failed: DateTime<Utc>,
+    pub next_attempt: DateTime<Utc>,
+    /// Out of attempts or failed in a way retrying can't fix, so it's never
+    /// retried again but stays in the ledger.
+    pub abandoned: bool,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    /// The wait after the first failure, doubled after each one since.
+    pub base_seconds: u32,
+    pub max_seconds: u32,
+    /// Failures after which an id is abandoned.
+    pub max_attempts: u32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            base_seconds: 60,
+            max_seconds: 6 * 60 * 60,
+            max_attempts: 10,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn backoff(&self, attempts: u32) -> Duration {
+        let exponent = attempts.saturating_sub(1).min(31);
+        let seconds = (self.base_seconds as u64)
+            .saturating_mul(1 << exponent)
+            .min(self.max_seconds as u64);
+
+        Duration::seconds(seconds as i64)
+    }
+
+    /// Fold another failure into the ledger entry for the id.
+    pub fn record(
+        &self,
+        previous: Option<&FetchFailure>,
+        id: u32,
+        kind: FailureKind,
+        message: String,
+        now: &DateTime<Utc>,
+    ) -> FetchFailure {
+        let attempts = previous.map_or(0, |p| p.attempts) + 1;
+
+        FetchFailure {
+            id,
+            abandoned: attempts >= self.max_attempts || kind.is_permanent(),
+            kind,
+            message,
+            attempts,
+            first_failed: previous.map_or(*now, |p| p.first_failed),
+            next_attempt: *now + self.backoff(attempts),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn backoff_doubles_up_to_max() {
+        let policy = RetryPolicy {
+            base_seconds: 10,
+            max_seconds: 60,
+            max_attempts: 5,
+        };
+
+        assert_eq!(policy.backoff(1), Duration::seconds(10));
+        assert_eq!(policy.backoff(2), Duration::seconds(20));
+        assert_eq!(policy.backoff(3), Duration::seconds(40));
+        assert_eq!(policy.backoff(4), Duration::seconds(60));
+        assert_eq!(policy.backoff(100), Duration::seconds(60));
+    }
+
+    #[test]
+    fn record_failures() {
+        let policy = RetryPolicy::default();
+        let first = Utc.ymd(2020, 1, 1).and_hms(0, 0, 0);
+        let second = Utc.ymd(2020, 1, 1).and_hms(0, 5, 0);
+
+        let failure = policy.record(None, 1, FailureKind::Timeout, "timed out".into(), &first);
+        assert_eq!(failure.attempts, 1);
+        assert_eq!(failure.next_attempt, first + Duration::seconds(60));
+
+        let failure = policy.record(
+            Some(&failure),
+            1,
+            FailureKind::Status(503),
+            "unavailable".into(),
+            &second,
+        );
+        assert_eq!(failure.attempts, 2);
+        assert_eq!(failure.kind, FailureKind::Status(503));
+        assert_eq!(failure.first_failed, first);
+        assert_eq!(failure.next_attempt, second + Duration::seconds(120));
+        assert!(!failure.abandoned);
+    }
+
+    #[test]
+    fn abandon_failures() {
+        let policy = RetryPolicy {
+            max_attempts: 2,
+            ..Default::default()
+        };
+        let now = Utc.ymd(2020, 1, 1).and_hms(0, 0, 0);
+
+        let failure = policy.record(None, 1, FailureKind::Timeout, "timed out".into(), &now);
+        assert!(!failure.abandoned);
+        let failure = policy.record(
+            Some(&failure),
+            1,
+            FailureKind::Timeout,
+            "timed out".into(),
+            &now,
+        );
+        assert!(failure.abandoned);
+
+        // No point retrying an id HN says isn't there
+        let failure = policy.record(None, 2, FailureKind::Status(404), "not found".into(), &now);
+        assert!(failure.abandoned);
+        let failure = policy.record(None, 3, FailureKind::Status(429), "slow down".into(), &now);
+        assert!(!failure.abandoned);
+    }
+
+    #[test]
+    fn failure_kind_round_trip() {
+        for kind in vec![
+            FailureKind::Timeout,
+            FailureKind::Status(503),
+            FailureKind::Body,
+            FailureKind::Connection,
+        ] {
+            assert_eq!(kind.to_string().parse::<FailureKind>().unwrap(), kind);
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! What changed in a list between two snapshots.
+
+/// An item's rank before and after. `from` is `None` for items that entered
+/// and `to` is `None` for items that exited.
+#[derive(Debug, PartialEq, Clone)]
+pub struct RankChange {
+    pub id: u32,
+    pub from: Option<u32>,
+    pub to: Option<u32>,
+}
+
+impl RankChange {
+    /// Positions gained, negative when the item moved down.
+    pub fn delta(&self) -> Option<i64> {
+        Some(self.from? as i64 - self.to? as i64)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ListDiff {
+    /// In rank order of the later list.
+    pub entered: Vec<RankChange>,
+    /// In rank order of the earlier list.
+    pub exited: Vec<RankChange>,
+    /// Biggest climbs first.
+    pub moved_up: Vec<RankChange>,
+    /// Biggest drops first.
+    pub moved_down: Vec<RankChange>,
+}
+
+pub fn list_diff(before: &[u32], after: &[u32]) -> ListDiff {
+    let rank_in = |list: &[u32], id: u32| {
+        list.iter()
+            .position(|other| *other == id)
+            .map(|i| i as u32 + 1)
+    };
+
+    let mut diff = ListDiff::default();
+
+    for (i, id) in after.iter().enumerate() {
+        let change = RankChange {
+            id: *id,
+            from: rank_in(before, *id),
+            to: Some(i as u32 + 1),
+        };
+
+        match change.delta() {
+            None => diff.entered.push(change),
+            Some(delta) if delta > 0 => diff.moved_up.push(change),
+            Some(delta) if delta < 0 => diff.moved_down.push(change),
+            _ => (),
+        }
+    }
+
+    for (i, id) in before.iter().enumerate() {
+        if rank_in(after, *id).is_none() {
+            diff.exited.push(RankChange {
+                id: *id,
+                from: Some(i as u32 + 1),
+                to: None,
+            });
+        }
+    }
+
+    diff.moved_up.sort_by_key(|change| -change.delta().unwrap_or(0));
+    diff.moved_down.sort_by_key(|change| change.delta().unwrap_or(0));
+
+    diff
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn change(id: u32, from: Option<u32>, to: Option<u32>) -> RankChange {
+        RankChange { id, from, to }
+    }
+
+    #[test]
+    fn diff_lists() {
+        let got = list_diff(&[1, 2, 3, 4, 5], &[3, 1, 6, 2, 5]);
+        let want = ListDiff {
+            entered: vec![change(6, None, Some(3))],
+            exited: vec![change(4, Some(4), None)],
+            moved_up: vec![change(3, Some(3), Some(1))],
+            moved_down: vec![change(2, Some(2), Some(4)), change(1, Some(1), Some(2))],
+        };
+
+        assert_eq!(got, want);
+        assert_eq!(got.moved_down[0].delta(), Some(-2));
+    }
+
+    #[test]
+    fn diff_same_list() {
+        assert_eq!(list_diff(&[1, 2], &[1, 2]), ListDiff::default());
+    }
+}
@@ -1,9 +1,22 @@
 use anyhow::{anyhow, Result};
 use chrono::{DateTime, Utc};
+use juniper::GraphQLEnum;
 use std::str::FromStr;
 use strum_macros::EnumIter;
 
-#[derive(EnumIter, Debug, PartialEq, Clone)]
+pub mod backfill;
+pub mod crawl;
+pub mod decode;
+pub mod dwell;
+pub mod fetch;
+pub mod list_diff;
+pub mod query;
+pub mod rising;
+pub mod snapshot;
+pub mod tracking;
+pub mod version;
+
+#[derive(EnumIter, GraphQLEnum, Debug, PartialEq, Clone)]
 pub enum ListCategory {
     Top,
     New,
@@ -11,6 +24,15 @@ pub enum ListCategory {
     Ask,
     Show,
     Job,
+    /// Computed locally from score history rather than fetched from HN.
+    Rising,
+}
+
+impl ListCategory {
+    /// Whether the list is computed here instead of fetched from HN.
+    pub fn is_derived(&self) -> bool {
+        matches!(self, Self::Rising)
+    }
 }
 
 #[derive(PartialEq, Debug, Clone)]
@@ -21,6 +43,86 @@ pub struct ItemRank {
     pub ts: DateTime<Utc>,
 }
 
+/// An item dropping off a list it was previously on.
+#[derive(PartialEq, Debug, Clone)]
+pub struct ListExit {
+    pub id: u32,
+    pub category: ListCategory,
+    pub ts: DateTime<Utc>,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct ItemScore {
+    pub id: u32,
+    pub score: u32,
+    pub descendants: Option<u32>,
+    pub ts: DateTime<Utc>,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct KarmaSnapshot {
+    pub id: String,
+    pub karma: u32,
+    pub ts: DateTime<Utc>,
+}
+
+/// Counts over the items stored for a user.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct UserActivity {
+    pub stored: usize,
+    pub stories: usize,
+    pub comments: usize,
+    /// Points across every stored submission.
+    pub points: u32,
+}
+
+/// Something that happened while syncing with HN, pushed to live subscribers.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Event {
+    RankChanged {
+        id: u32,
+        category: ListCategory,
+        from: u32,
+        to: u32,
+        ts: DateTime<Utc>,
+    },
+    EnteredList {
+        id: u32,
+        category: ListCategory,
+        rank: u32,
+        ts: DateTime<Utc>,
+    },
+    ItemUpdated {
+        id: u32,
+        score: Option<u32>,
+        descendants: Option<u32>,
+        ts: DateTime<Utc>,
+    },
+}
+
+/// A full-text match and its BM25 relevance.
+#[derive(PartialEq, Debug, Clone)]
+pub struct SearchHit {
+    pub id: u32,
+    pub relevance: f64,
+}
+
+/// An item as stored, with the columns parsed out of the original payload.
+#[derive(PartialEq, Debug, Clone)]
+pub struct ItemRecord {
+    pub id: u32,
+    pub original: String,
+    pub descendants: Option<u32>,
+    pub username: Option<String>,
+    pub score: Option<u32>,
+    pub title: Option<String>,
+    pub url: Option<String>,
+    pub body: Option<String>,
+    pub ts: Option<DateTime<Utc>>,
+    pub deleted: bool,
+    pub dead: bool,
+}
+
 impl FromStr for ListCategory {
     type Err = anyhow::Error;
 
@@ -32,6 +134,7 @@ impl FromStr for ListCategory {
             "ask" => Ok(Self::Ask),
             "show" => Ok(Self::Show),
             "job" => Ok(Self::Job),
+            "rising" => Ok(Self::Rising),
             _ => Err(anyhow!("Invalid ListCategory")),
         }
     }
@@ -0,0 +1,335 @@
+//! A small query language for filtering items, e.g.
+//! `author:pg score>100 type:story site:github.com before:2020-01-01 "rust"`.
+//! `is:deleted` and `is:dead` find tombstones.
+//!
+//! Terms are separated by whitespace and must all match. A leading `-`
+//! negates a term, and double quotes group words into a single phrase.
+
+use chrono::NaiveDate;
+use std::fmt;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Comparison {
+    Eq,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ItemType {
+    Story,
+    Comment,
+    Job,
+    Poll,
+    Pollopt,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Filter {
+    /// Free text in the title or body.
+    Text(String),
+    Author(String),
+    Type(ItemType),
+    /// The url's host, including subdomains.
+    Site(String),
+    Score(Comparison, u32),
+    /// The total comment count.
+    Comments(Comparison, u32),
+    /// Stored before the start of the day.
+    Before(NaiveDate),
+    /// Stored on or after the start of the day.
+    After(NaiveDate),
+    /// Deleted by its author.
+    Deleted,
+    /// Killed by flags or moderators.
+    Dead,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Term {
+    pub negated: bool,
+    pub filter: Filter,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Query {
+    pub terms: Vec<Term>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ParseError {
+    /// Byte offset into the query where the problem starts.
+    pub position: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    fn new(position: usize, message: impl Into<String>) -> Self {
+        Self {
+            position,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+pub fn parse(input: &str) -> Result<Query, ParseError> {
+    let terms = tokenize(input)?
+        .into_iter()
+        .map(|(position, token)| parse_term(position, token))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Query { terms })
+}
+
+// Split on whitespace outside of quotes
+fn tokenize(input: &str) -> Result<Vec<(usize, &str)>, ParseError> {
+    let mut tokens = vec![];
+    let mut start = None;
+    let mut quote = None;
+
+    for (i, c) in input.char_indices() {
+        match (c, quote) {
+            ('"', None) => {
+                quote = Some(i);
+                start.get_or_insert(i);
+            }
+            ('"', Some(_)) => quote = None,
+            (c, None) if c.is_whitespace() => {
+                if let Some(s) = start.take() {
+                    tokens.push((s, &input[s..i]));
+                }
+            }
+            _ => {
+                start.get_or_insert(i);
+            }
+        }
+    }
+
+    if let Some(q) = quote {
+        return Err(ParseError::new(q, "Unterminated quote"));
+    }
+    if let Some(s) = start {
+        tokens.push((s, &input[s..]));
+    }
+
+    Ok(tokens)
+}
+
+fn parse_term(position: usize, token: &str) -> Result<Term, ParseError> {
+    let (negated, position, token) = match token.strip_prefix('-') {
+        Some(rest) if !rest.is_empty() => (true, position + 1, rest),
+        _ => (false, position, token),
+    };
+
+    let split = token.find(|c| matches!(c, ':' | '<' | '>' | '=' | '"'));
+    let filter = match split {
+        Some(i) if i > 0 && !token[i..].starts_with('"') => {
+            let field = &token[..i];
+            let (comparison, op_len) = parse_comparison(&token[i..]);
+            let is_colon = token[i..].starts_with(':');
+            let value_position = position + i + op_len;
+            let value = unquote(&token[i + op_len..]);
+
+            if value.is_empty() {
+                return Err(ParseError::new(
+                    value_position,
+                    format!("Expected a value for '{}'", field),
+                ));
+            }
+
+            let text_only = |position| {
+                ParseError::new(position, format!("'{}' only supports ':'", field))
+            };
+
+            match field.to_lowercase().as_str() {
+                "author" if is_colon => Filter::Author(value.to_string()),
+                "site" if is_colon => Filter::Site(value.to_lowercase()),
+                "type" if is_colon => Filter::Type(parse_type(value_position, value)?),
+                "before" if is_colon => Filter::Before(parse_date(value_position, value)?),
+                "after" if is_colon => Filter::After(parse_date(value_position, value)?),
+                "is" if is_colon => parse_flag(value_position, value)?,
+                "author" | "site" | "type" | "before" | "after" | "is" => {
+                    return Err(text_only(position + i))
+                }
+                "score" => Filter::Score(comparison, parse_number(value_position, value)?),
+                "comments" => Filter::Comments(comparison, parse_number(value_position, value)?),
+                _ => {
+                    return Err(ParseError::new(
+                        position,
+                        format!("Unknown field '{}'", field),
+                    ))
+                }
+            }
+        }
+        _ => Filter::Text(unquote(token).to_string()),
+    };
+
+    Ok(Term { negated, filter })
+}
+
+fn parse_comparison(input: &str) -> (Comparison, usize) {
+    if input.starts_with(">=") {
+        (Comparison::Gte, 2)
+    } else if input.starts_with("<=") {
+        (Comparison::Lte, 2)
+    } else if input.starts_with('>') {
+        (Comparison::Gt, 1)
+    } else if input.starts_with('<') {
+        (Comparison::Lt, 1)
+    } else {
+        // ':' and '='
+        (Comparison::Eq, 1)
+    }
+}
+
+fn unquote(input: &str) -> &str {
+    if input.len() >= 2 && input.starts_with('"') && input.ends_with('"') {
+        &input[1..input.len() - 1]
+    } else {
+        input
+    }
+}
+
+fn parse_type(position: usize, input: &str) -> Result<ItemType, ParseError> {
+    match input.to_lowercase().as_str() {
+        "story" => Ok(ItemType::Story),
+        "comment" => Ok(ItemType::Comment),
+        "job" => Ok(ItemType::Job),
+        "poll" => Ok(ItemType::Poll),
+        "pollopt" => Ok(ItemType::Pollopt),
+        _ => Err(ParseError::new(
+            position,
+            format!("Unknown item type '{}'", input),
+        )),
+    }
+}
+
+fn parse_flag(position: usize, input: &str) -> Result<Filter, ParseError> {
+    match input.to_lowercase().as_str() {
+        "deleted" => Ok(Filter::Deleted),
+        "dead" => Ok(Filter::Dead),
+        _ => Err(ParseError::new(
+            position,
+            format!("Expected 'deleted' or 'dead', got '{}'", input),
+        )),
+    }
+}
+
+fn parse_date(position: usize, input: &str) -> Result<NaiveDate, ParseError> {
+    NaiveDate::parse_from_str(input, "%Y-%m-%d").map_err(|_| {
+        ParseError::new(
+            position,
+            format!("Expected a date like 2020-01-01, got '{}'", input),
+        )
+    })
+}
+
+fn parse_number(position: usize, input: &str) -> Result<u32, ParseError> {
+    input.parse().map_err(|_| {
+        ParseError::new(position, format!("Expected a number, got '{}'", input))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn term(filter: Filter) -> Term {
+        Term {
+            negated: false,
+            filter,
+        }
+    }
+
+    #[test]
+    fn parse_full_query() {
+        let got = parse(r#"author:pg score>100 type:story site:github.com before:2020-01-01 "rust""#)
+            .unwrap();
+        let want = Query {
+            terms: vec![
+                term(Filter::Author("pg".into())),
+                term(Filter::Score(Comparison::Gt, 100)),
+                term(Filter::Type(ItemType::Story)),
+                term(Filter::Site("github.com".into())),
+                term(Filter::Before(NaiveDate::from_ymd(2020, 1, 1))),
+                term(Filter::Text("rust".into())),
+            ],
+        };
+
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn parse_phrases_and_negation() {
+        let got = parse(r#"  "hacker news" -site:medium.com comments<=5 author:"pg""#).unwrap();
+        let want = Query {
+            terms: vec![
+                term(Filter::Text("hacker news".into())),
+                Term {
+                    negated: true,
+                    filter: Filter::Site("medium.com".into()),
+                },
+                term(Filter::Comments(Comparison::Lte, 5)),
+                term(Filter::Author("pg".into())),
+            ],
+        };
+
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn parse_flags() {
+        let got = parse("is:deleted -is:dead").unwrap();
+        let want = Query {
+            terms: vec![
+                term(Filter::Deleted),
+                Term {
+                    negated: true,
+                    filter: Filter::Dead,
+                },
+            ],
+        };
+
+        assert_eq!(got, want);
+        assert_eq!(parse("is:flagged").unwrap_err().position, 3);
+    }
+
+    #[test]
+    fn parse_empty() {
+        assert_eq!(parse("   ").unwrap(), Query::default());
+    }
+
+    #[test]
+    fn error_unknown_field() {
+        let got = parse("rust color:red").unwrap_err();
+        let want = ParseError::new(5, "Unknown field 'color'");
+
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn error_unterminated_quote() {
+        let got = parse(r#"rust "hacker news"#).unwrap_err();
+
+        assert_eq!(got.position, 5);
+    }
+
+    #[test]
+    fn error_bad_values() {
+        assert_eq!(parse("score>lots").unwrap_err().position, 6);
+        assert_eq!(parse("x before:yesterday").unwrap_err().position, 9);
+        assert_eq!(parse("type:blog").unwrap_err().position, 5);
+        assert_eq!(parse("author:").unwrap_err().position, 7);
+        assert_eq!(parse("author>pg").unwrap_err().position, 6);
+    }
+}
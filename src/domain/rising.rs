@@ -0,0 +1,157 @@
+//! The "rising" list: items ranked by how fast they are gaining points.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::{cmp::Ordering, collections::HashMap};
+
+use crate::domain::ItemScore;
+
+// Don't let an item seen for a few seconds divide by almost nothing
+const MIN_ELAPSED_HOURS: f64 = 0.25;
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct RisingConfig {
+    /// How far back to measure points gained.
+    pub window_hours: u32,
+    /// How strongly older items are pushed down, as in HN's own ranking.
+    pub gravity: f64,
+    /// How many items the list holds.
+    pub size: usize,
+}
+
+impl Default for RisingConfig {
+    fn default() -> Self {
+        Self {
+            window_hours: 6,
+            gravity: 1.8,
+            size: 30,
+        }
+    }
+}
+
+impl RisingConfig {
+    pub fn window_start(&self, now: &DateTime<Utc>) -> DateTime<Utc> {
+        *now - Duration::hours(self.window_hours as i64)
+    }
+}
+
+/// Rank items by points per hour over the window, divided by
+/// `(age in hours + 2) ^ gravity`.
+///
+/// `snapshots` must include, for every item, the latest snapshot from before
+/// the window, and `created` maps ids to creation time in Unix time.
+pub fn rising_ids(
+    snapshots: &[ItemScore],
+    created: &HashMap<u32, u64>,
+    now: &DateTime<Utc>,
+    config: &RisingConfig,
+) -> Vec<u32> {
+    let window_start = config.window_start(now);
+
+    let mut by_id: HashMap<u32, Vec<&ItemScore>> = HashMap::new();
+    for snapshot in snapshots {
+        by_id.entry(snapshot.id).or_default().push(snapshot);
+    }
+
+    let mut ranked = by_id
+        .into_iter()
+        .filter_map(|(id, mut series)| {
+            series.sort_by_key(|s| s.ts);
+            let latest = series.last()?;
+
+            // Scores are only stored on change, so the last snapshot before
+            // the window is exactly the score when the window opened
+            let (baseline, since) = match series.iter().rev().find(|s| s.ts <= window_start) {
+                Some(before) => (*before, window_start),
+                None => (series[0], series[0].ts),
+            };
+
+            let gained = latest.score.saturating_sub(baseline.score);
+            if gained == 0 {
+                return None;
+            }
+
+            let elapsed = hours_between(&since, now).max(MIN_ELAPSED_HOURS);
+            let age = created
+                .get(&id)
+                .map(|time| (now.timestamp() - *time as i64).max(0) as f64 / 3600.0)
+                .unwrap_or(0.0);
+            let rank = (gained as f64 / elapsed) / (age + 2.0).powf(config.gravity);
+
+            Some((id, rank))
+        })
+        .collect::<Vec<_>>();
+
+    ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+    ranked.truncate(config.size);
+
+    ranked.into_iter().map(|(id, _)| id).collect()
+}
+
+fn hours_between(from: &DateTime<Utc>, to: &DateTime<Utc>) -> f64 {
+    (*to - *from).num_seconds() as f64 / 3600.0
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn snapshot(id: u32, score: u32, ts: DateTime<Utc>) -> ItemScore {
+        ItemScore {
+            id,
+            score,
+            descendants: None,
+            ts,
+        }
+    }
+
+    #[test]
+    fn ranks_by_points_per_hour() {
+        let now = Utc.ymd(2020, 1, 1).and_hms(12, 0, 0);
+        let config = RisingConfig {
+            gravity: 0.0,
+            ..Default::default()
+        };
+        let snapshots = vec![
+            // 1 went from 10 to 70 over the whole 6 hour window: 10/h
+            snapshot(1, 10, Utc.ymd(2020, 1, 1).and_hms(1, 0, 0)),
+            snapshot(1, 70, Utc.ymd(2020, 1, 1).and_hms(11, 0, 0)),
+            // 2 was first seen an hour ago with 5 and now has 25: 20/h
+            snapshot(2, 5, Utc.ymd(2020, 1, 1).and_hms(11, 0, 0)),
+            snapshot(2, 25, Utc.ymd(2020, 1, 1).and_hms(11, 30, 0)),
+            // 3 hasn't moved
+            snapshot(3, 500, Utc.ymd(2020, 1, 1).and_hms(11, 0, 0)),
+        ];
+
+        let got = rising_ids(&snapshots, &HashMap::new(), &now, &config);
+        let want = vec![2, 1];
+
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn gravity_favours_younger_items() {
+        let now = Utc.ymd(2020, 1, 1).and_hms(12, 0, 0);
+        let config = RisingConfig::default();
+        let snapshots = vec![
+            snapshot(1, 10, Utc.ymd(2020, 1, 1).and_hms(1, 0, 0)),
+            snapshot(1, 70, Utc.ymd(2020, 1, 1).and_hms(11, 0, 0)),
+            snapshot(2, 10, Utc.ymd(2020, 1, 1).and_hms(1, 0, 0)),
+            snapshot(2, 70, Utc.ymd(2020, 1, 1).and_hms(11, 0, 0)),
+        ];
+        let created = vec![
+            // A day old
+            (1, Utc.ymd(2019, 12, 31).and_hms(12, 0, 0).timestamp() as u64),
+            // Posted this morning
+            (2, Utc.ymd(2020, 1, 1).and_hms(0, 0, 0).timestamp() as u64),
+        ]
+        .into_iter()
+        .collect();
+
+        let got = rising_ids(&snapshots, &created, &now, &config);
+        let want = vec![2, 1];
+
+        assert_eq!(got, want);
+    }
+}
@@ -0,0 +1,160 @@
+//! Lists are stored as a full keyframe every so often, and in between as
+//! deltas against the previous snapshot of the category.
+
+use std::collections::{HashMap, HashSet};
+
+/// Snapshots from one keyframe to the next, so rebuilding a list never
+/// replays more deltas than this.
+pub const KEYFRAME_INTERVAL: u32 = 100;
+
+/// How a list changed since its previous snapshot, keyed by id.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct SnapshotDelta {
+    /// `(rank, id)` of the ids that entered or moved, by rank. Ranks start
+    /// at 1.
+    pub ranked: Vec<(u32, u32)>,
+    /// Ids that left the list.
+    pub exited: Vec<u32>,
+}
+
+/// The delta from `previous` to `current`. Ids that kept their order
+/// relative to each other are left out, so a story entering near the top
+/// is one entry rather than a new rank for everything below it.
+pub fn snapshot_delta(previous: &[u32], current: &[u32]) -> SnapshotDelta {
+    let stable = stable_ids(previous, current);
+    let in_current = current.iter().collect::<HashSet<_>>();
+
+    SnapshotDelta {
+        ranked: current
+            .iter()
+            .enumerate()
+            .filter(|(_, id)| !stable.contains(id))
+            .map(|(i, id)| (i as u32 + 1, *id))
+            .collect(),
+        exited: previous
+            .iter()
+            .filter(|id| !in_current.contains(id))
+            .cloned()
+            .collect(),
+    }
+}
+
+/// Rebuild a list from the one before it and the delta between them.
+pub fn apply_delta(previous: &[u32], delta: &SnapshotDelta) -> Vec<u32> {
+    let moved = delta
+        .exited
+        .iter()
+        .chain(delta.ranked.iter().map(|(_, id)| id))
+        .collect::<HashSet<_>>();
+    let mut list = previous
+        .iter()
+        .filter(|id| !moved.contains(id))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    // In rank order, everything above each one is already in place
+    let mut ranked = delta.ranked.clone();
+    ranked.sort_unstable();
+    for (rank, id) in ranked {
+        let index = (rank as usize - 1).min(list.len());
+        list.insert(index, id);
+    }
+
+    list
+}
+
+// The largest set of ids in both lists that appear in the same order in
+// each, the longest increasing run of their positions in `current`
+fn stable_ids(previous: &[u32], current: &[u32]) -> HashSet<u32> {
+    let position = current
+        .iter()
+        .enumerate()
+        .map(|(i, id)| (*id, i))
+        .collect::<HashMap<_, _>>();
+    let positions = previous
+        .iter()
+        .filter_map(|id| position.get(id).cloned())
+        .collect::<Vec<_>>();
+
+    // Patience sorting: `tails[k]` ends the best run of length k + 1 so far
+    let mut tails: Vec<usize> = vec![];
+    let mut parent = vec![None; positions.len()];
+    for (i, p) in positions.iter().enumerate() {
+        let k = tails
+            .binary_search_by(|&t| positions[t].cmp(p))
+            .unwrap_or_else(|k| k);
+        if k > 0 {
+            parent[i] = Some(tails[k - 1]);
+        }
+        if k == tails.len() {
+            tails.push(i);
+        } else {
+            tails[k] = i;
+        }
+    }
+
+    let mut stable = HashSet::new();
+    let mut next = tails.last().cloned();
+    while let Some(i) = next {
+        stable.insert(current[positions[i]]);
+        next = parent[i];
+    }
+
+    stable
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn delta_of_entry_at_the_top() {
+        let got = snapshot_delta(&[1, 2, 3, 4], &[5, 1, 2, 3]);
+        let want = SnapshotDelta {
+            ranked: vec![(1, 5)],
+            exited: vec![4],
+        };
+
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn delta_of_swap() {
+        let got = snapshot_delta(&[1, 2, 3, 4], &[1, 3, 2, 4]);
+
+        assert_eq!(got.ranked.len(), 1);
+        assert!(got.exited.is_empty());
+        assert_eq!(apply_delta(&[1, 2, 3, 4], &got), vec![1, 3, 2, 4]);
+    }
+
+    #[test]
+    fn delta_of_first_snapshot() {
+        let got = snapshot_delta(&[], &[5, 6]);
+        let want = SnapshotDelta {
+            ranked: vec![(1, 5), (2, 6)],
+            exited: vec![],
+        };
+
+        assert_eq!(got, want);
+        assert_eq!(apply_delta(&[], &got), vec![5, 6]);
+    }
+
+    #[test]
+    fn deltas_rebuild_each_list() {
+        let lists: Vec<Vec<u32>> = vec![
+            vec![1, 2, 3, 4, 5],
+            vec![6, 1, 2, 3, 4],
+            vec![6, 3, 1, 2],
+            vec![2, 6, 7, 3, 1, 8],
+            vec![],
+            vec![8, 7],
+        ];
+
+        let mut previous = vec![];
+        for list in lists {
+            let delta = snapshot_delta(&previous, &list);
+            assert_eq!(apply_delta(&previous, &delta), list);
+            previous = list;
+        }
+    }
+}
@@ -0,0 +1,88 @@
+//! How much of each HN list gets tracked.
+
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
+
+use crate::domain::ListCategory;
+
+// Lists without an entry, such as ask, show and job, which HN serves 200 ids for
+const DEFAULT_DEPTH: usize = 200;
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrackingConfig {
+    /// How many ranks to record per list, keyed by category name. Lists
+    /// without an entry use the default depth. Entries in a stored config
+    /// override the defaults one key at a time.
+    #[serde(deserialize_with = "over_default_depth")]
+    pub depth: HashMap<String, usize>,
+    /// How many items from the top of each list to fetch and store. Items
+    /// below this only get their rank recorded.
+    pub hydrate: usize,
+}
+
+impl Default for TrackingConfig {
+    fn default() -> Self {
+        Self {
+            depth: vec![
+                (ListCategory::Top.to_string(), 500),
+                (ListCategory::Best.to_string(), 500),
+                (ListCategory::New.to_string(), 200),
+            ]
+            .into_iter()
+            .collect(),
+            hydrate: 30,
+        }
+    }
+}
+
+fn over_default_depth<'de, D>(deserializer: D) -> Result<HashMap<String, usize>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let mut depth = TrackingConfig::default().depth;
+    depth.extend(HashMap::<String, usize>::deserialize(deserializer)?);
+
+    Ok(depth)
+}
+
+impl TrackingConfig {
+    pub fn depth(&self, category: &ListCategory) -> usize {
+        self.depth
+            .get(&category.to_string())
+            .cloned()
+            .unwrap_or(DEFAULT_DEPTH)
+    }
+
+    /// Never more than the list is tracked to.
+    pub fn hydrate(&self, category: &ListCategory) -> usize {
+        self.hydrate.min(self.depth(category))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn depth_per_category() {
+        let config = TrackingConfig::default();
+
+        assert_eq!(config.depth(&ListCategory::Top), 500);
+        assert_eq!(config.depth(&ListCategory::New), 200);
+        assert_eq!(config.depth(&ListCategory::Job), DEFAULT_DEPTH);
+        assert_eq!(config.hydrate(&ListCategory::Top), 30);
+    }
+
+    #[test]
+    fn partial_config() {
+        let config: TrackingConfig =
+            serde_json::from_str(r#"{"depth": {"job": 10}}"#).unwrap();
+
+        assert_eq!(config.depth(&ListCategory::Job), 10);
+        assert_eq!(config.depth(&ListCategory::Top), 500);
+        assert_eq!(config.depth(&ListCategory::Ask), DEFAULT_DEPTH);
+        assert_eq!(config.hydrate(&ListCategory::Job), 10);
+        assert_eq!(config.hydrate(&ListCategory::Top), 30);
+    }
+}
@@ -0,0 +1,92 @@
+//! Every distinct version of an item we have fetched.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// A top-level field of the original payload that changed between versions.
+/// A missing field is `null`.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub from: Value,
+    pub to: Value,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ItemVersion {
+    pub id: u32,
+    /// When this version was fetched.
+    pub ts: DateTime<Utc>,
+    pub original: String,
+    /// Changes from the previous version, empty for the first one.
+    pub changes: Vec<FieldChange>,
+}
+
+/// Compare two payloads field by field, in the order fields appear.
+pub fn field_changes(before: &Value, after: &Value) -> Vec<FieldChange> {
+    let empty = serde_json::Map::new();
+    let before = before.as_object().unwrap_or(&empty);
+    let after = after.as_object().unwrap_or(&empty);
+
+    let removed = before.keys().filter(|key| !after.contains_key(*key));
+    after
+        .keys()
+        .chain(removed)
+        .filter_map(|key| {
+            let from = before.get(key).cloned().unwrap_or(Value::Null);
+            let to = after.get(key).cloned().unwrap_or(Value::Null);
+
+            (from != to).then(|| FieldChange {
+                field: key.clone(),
+                from,
+                to,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn changed_fields() {
+        let before = json!({"type": "story", "title": "Old", "score": 1, "dead": true});
+        let after = json!({"type": "story", "title": "New", "score": 5, "url": "https://a.b"});
+
+        let got = field_changes(&before, &after);
+        let want = vec![
+            FieldChange {
+                field: "title".into(),
+                from: json!("Old"),
+                to: json!("New"),
+            },
+            FieldChange {
+                field: "score".into(),
+                from: json!(1),
+                to: json!(5),
+            },
+            FieldChange {
+                field: "url".into(),
+                from: Value::Null,
+                to: json!("https://a.b"),
+            },
+            FieldChange {
+                field: "dead".into(),
+                from: json!(true),
+                to: Value::Null,
+            },
+        ];
+
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn no_changes() {
+        let item = json!({"type": "story", "title": "Same"});
+
+        assert!(field_changes(&item, &item).is_empty());
+    }
+}
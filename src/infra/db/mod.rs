@@ -1,5 +1,15 @@
 use anyhow::Result;
-use duckdb::DuckdbConnectionManager;
+use chrono::{DateTime, TimeZone, Utc};
+use duckdb::{params, DuckdbConnectionManager, OptionalExt};
+
+use std::collections::HashMap;
+
+use crate::domain::{
+    decode::FieldIssue,
+    snapshot::{snapshot_delta, KEYFRAME_INTERVAL},
+};
+
+pub mod query;
 
 type Pool = r2d2::Pool<DuckdbConnectionManager>;
 type Conn = r2d2::PooledConnection<DuckdbConnectionManager>;
@@ -28,10 +38,11 @@ impl Duck {
 
     pub fn migrate(&self) -> Result<()> {
         let mut conn = self.get()?;
+
         let tx = conn.transaction()?;
-        let version = 1;
+        let version = schema_version(&tx)?;
 
-        if version <= 1 {
+        if version < 1 {
             tx.execute_batch(
                 r"
                 CREATE TABLE IF NOT EXISTS item (
@@ -94,12 +105,450 @@ impl Duck {
             )?;
         }
 
+        if version < 2 {
+            // Score snapshots also track the comment count
+            if !column_exists(&tx, "item_score", "descendants")? {
+                tx.execute_batch("ALTER TABLE item_score ADD COLUMN descendants INTEGER;")?;
+            }
+        }
+
+        if version < 3 {
+            tx.execute_batch(
+                r"
+                CREATE TABLE IF NOT EXISTS item_list_exit (
+                    id INTEGER NOT NULL,
+                    category VARCHAR NOT NULL,
+                    ts TIMESTAMP NOT NULL,
+                    PRIMARY KEY (id, category, ts)
+                );
+                ",
+            )?;
+        }
+
+        if version < 4 {
+            // Every fetched list, only storing the ranks that changed since
+            // the previous snapshot of the category
+            tx.execute_batch(
+                r"
+                CREATE TABLE IF NOT EXISTS list_snapshot (
+                    category VARCHAR NOT NULL,
+                    ts TIMESTAMP NOT NULL,
+                    size INTEGER NOT NULL,
+                    PRIMARY KEY (category, ts)
+                );
+
+                CREATE TABLE IF NOT EXISTS list_snapshot_rank (
+                    category VARCHAR NOT NULL,
+                    ts TIMESTAMP NOT NULL,
+                    rank INTEGER NOT NULL,
+                    id INTEGER NOT NULL,
+                    PRIMARY KEY (category, ts, rank)
+                );
+                ",
+            )?;
+        }
+
+        if version < 5 {
+            // Items used to be inserted on every fetch, so keep only the
+            // latest row per id and seed the history with it
+            tx.execute_batch(
+                r"
+                DELETE FROM item
+                WHERE rowid NOT IN (SELECT max(rowid) FROM item GROUP BY id);
+
+                CREATE TABLE IF NOT EXISTS item_version (
+                    id INTEGER NOT NULL,
+                    ts TIMESTAMP NOT NULL,
+                    original VARCHAR NOT NULL,
+                    changes VARCHAR NOT NULL, -- Serialized vec of field changes
+                    PRIMARY KEY (id, ts)
+                );
+
+                INSERT INTO item_version (id, ts, original, changes)
+                SELECT id, coalesce(ts, now()), original, '[]'
+                FROM item
+                WHERE id NOT IN (SELECT id FROM item_version);
+                ",
+            )?;
+        }
+
+        if version < 6 {
+            // Deleted and dead items are stored as tombstones
+            if !column_exists(&tx, "item", "deleted")? {
+                tx.execute_batch("ALTER TABLE item ADD COLUMN deleted BOOLEAN DEFAULT FALSE;")?;
+            }
+            if !column_exists(&tx, "item", "dead")? {
+                tx.execute_batch("ALTER TABLE item ADD COLUMN dead BOOLEAN DEFAULT FALSE;")?;
+            }
+        }
+
+        if version < 7 {
+            // Payloads that didn't decode cleanly
+            tx.execute_batch(
+                r"
+                CREATE TABLE IF NOT EXISTS raw_item (
+                    id INTEGER NOT NULL,
+                    ts TIMESTAMP NOT NULL,
+                    raw VARCHAR NOT NULL,
+                    issues VARCHAR NOT NULL, -- Serialized vec of field issues
+                    decoded BOOLEAN NOT NULL,
+                    PRIMARY KEY (id, ts)
+                );
+                ",
+            )?;
+        }
+
+        if version < 8 {
+            // Ids that couldn't be fetched, until they are
+            tx.execute_batch(
+                r"
+                CREATE TABLE IF NOT EXISTS fetch_failure (
+                    id INTEGER NOT NULL,
+                    kind VARCHAR NOT NULL,
+                    message VARCHAR NOT NULL,
+                    attempts INTEGER NOT NULL,
+                    first_failed TIMESTAMP NOT NULL,
+                    next_attempt TIMESTAMP NOT NULL,
+                    PRIMARY KEY (id)
+                );
+                ",
+            )?;
+        }
+
+        if version < 9 {
+            // Karma over time, a row whenever it changes
+            tx.execute_batch(
+                r"
+                CREATE TABLE IF NOT EXISTS user_karma (
+                    id VARCHAR NOT NULL,
+                    ts TIMESTAMP NOT NULL,
+                    karma INTEGER NOT NULL,
+                    PRIMARY KEY (id, ts)
+                );
+                ",
+            )?;
+        }
+
+        if version < 10 {
+            // Payloads used to be stored again every time they were polled,
+            // and issues are split out so they can be counted in SQL
+            tx.execute_batch(
+                r"
+                DELETE FROM raw_item
+                WHERE rowid NOT IN (SELECT min(rowid) FROM raw_item GROUP BY id, raw);
+
+                CREATE TABLE IF NOT EXISTS raw_item_issue (
+                    id INTEGER NOT NULL,
+                    ts TIMESTAMP NOT NULL,
+                    field VARCHAR NOT NULL,
+                    problem VARCHAR NOT NULL,
+                    PRIMARY KEY (id, ts, field, problem)
+                );
+                DELETE FROM raw_item_issue;
+                ",
+            )?;
+
+            let raw_items = tx
+                .prepare("SELECT id, ts, issues FROM raw_item")?
+                .query_map([], |row| {
+                    let ts: DateTime<Utc> = row.get(1)?;
+                    let issues: String = row.get(2)?;
+                    Ok((row.get::<_, u32>(0)?, ts, issues))
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            for (id, ts, issues) in raw_items {
+                for issue in serde_json::from_str::<Vec<FieldIssue>>(&issues)? {
+                    tx.execute(
+                        "INSERT INTO raw_item_issue (id, ts, field, problem) VALUES (?1, ?2, ?3, ?4)",
+                        params![id, ts, issue.field, issue.problem],
+                    )?;
+                }
+            }
+        }
+
+        if version < 11 {
+            // Failures that are given up on stay in the ledger, marked as
+            // such, with those already out of the default attempts marked
+            if !column_exists(&tx, "fetch_failure", "abandoned")? {
+                tx.execute_batch(
+                    r"
+                    ALTER TABLE fetch_failure ADD COLUMN abandoned BOOLEAN DEFAULT FALSE;
+                    UPDATE fetch_failure SET abandoned = attempts >= 10;
+                    ",
+                )?;
+            }
+        }
+
+        if version < 12 {
+            // Ids HN returned null for, so gap scans don't queue them again
+            tx.execute_batch(
+                r"
+                CREATE TABLE IF NOT EXISTS missing_item (
+                    id INTEGER NOT NULL,
+                    ts TIMESTAMP NOT NULL,
+                    PRIMARY KEY (id)
+                );
+                ",
+            )?;
+        }
+
+        if version < 13 {
+            // The latest crawl of each user's submissions
+            tx.execute_batch(
+                r"
+                CREATE TABLE IF NOT EXISTS user_crawl (
+                    id VARCHAR NOT NULL,
+                    status VARCHAR NOT NULL,
+                    submitted INTEGER NOT NULL,
+                    stored INTEGER NOT NULL,
+                    unavailable INTEGER NOT NULL,
+                    failed INTEGER NOT NULL,
+                    error VARCHAR,
+                    updated TIMESTAMP NOT NULL,
+                    PRIMARY KEY (id)
+                );
+                ",
+            )?;
+        }
+
+        if version < 14 {
+            // Concurrent writers could each delete and insert the same id, so
+            // keep the latest row per id and let a primary key rule it out.
+            // Keys can't be added in place, so the table is rebuilt
+            tx.execute_batch(
+                r"
+                CREATE TABLE item_keyed (
+                    id INTEGER NOT NULL,
+                    original VARCHAR NOT NULL,
+                    descendants INTEGER,
+                    username VARCHAR,
+                    score INTEGER,
+                    title VARCHAR,
+                    url VARCHAR,
+                    body VARCHAR,
+                    ts TIMESTAMP,
+                    deleted BOOLEAN DEFAULT FALSE,
+                    dead BOOLEAN DEFAULT FALSE,
+                    PRIMARY KEY (id)
+                );
+
+                INSERT INTO item_keyed
+                SELECT id, original, descendants, username, score, title, url, body, ts, deleted, dead
+                FROM item
+                WHERE rowid IN (SELECT max(rowid) FROM item GROUP BY id);
+
+                DROP TABLE item;
+                ALTER TABLE item_keyed RENAME TO item;
+                ",
+            )?;
+        }
+
+        if version < 15 {
+            // Date filters are on when an item was posted, which only the
+            // payload had until now
+            if !column_exists(&tx, "item", "time")? {
+                tx.execute_batch("ALTER TABLE item ADD COLUMN time TIMESTAMP;")?;
+            }
+
+            let items = tx
+                .prepare("SELECT id, original FROM item WHERE time IS NULL")?
+                .query_map([], |row| {
+                    let original: String = row.get(1)?;
+                    Ok((row.get::<_, u32>(0)?, original))
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            for (id, original) in items {
+                let value: serde_json::Value = serde_json::from_str(&original)?;
+                if let Some(time) = value["time"].as_i64() {
+                    tx.execute(
+                        "UPDATE item SET time = ?1 WHERE id = ?2",
+                        params![Utc.timestamp(time, 0), id],
+                    )?;
+                }
+            }
+        }
+
+        if version < 16 {
+            // Snapshots were deltas by rank, so a story entering near the top
+            // rewrote every rank below it. They're now keyframes of the full
+            // list every so often, with deltas keyed by id in between
+            if !column_exists(&tx, "list_snapshot", "keyframe")? {
+                tx.execute_batch(
+                    "ALTER TABLE list_snapshot ADD COLUMN keyframe BOOLEAN DEFAULT FALSE;",
+                )?;
+            }
+            tx.execute_batch(
+                r"
+                CREATE TABLE IF NOT EXISTS list_snapshot_entry (
+                    category VARCHAR NOT NULL,
+                    ts TIMESTAMP NOT NULL,
+                    rank INTEGER NOT NULL,
+                    id INTEGER NOT NULL,
+                    PRIMARY KEY (category, ts, rank)
+                );
+
+                CREATE TABLE IF NOT EXISTS list_snapshot_exit (
+                    category VARCHAR NOT NULL,
+                    ts TIMESTAMP NOT NULL,
+                    id INTEGER NOT NULL,
+                    PRIMARY KEY (category, ts, id)
+                );
+                ",
+            )?;
+
+            reencode_list_snapshots(&tx)?;
+            tx.execute_batch("DROP TABLE IF EXISTS list_snapshot_rank;")?;
+        }
+
+        tx.execute("DELETE FROM config WHERE key = ?1", [SCHEMA_VERSION_KEY])?;
+        tx.execute(
+            "INSERT INTO config (key, value) VALUES (?1, ?2)",
+            params![SCHEMA_VERSION_KEY, SCHEMA_VERSION.to_string()],
+        )?;
         tx.commit()?;
 
         Ok(())
     }
 }
 
+// Bump along with every new block in `migrate`
+const SCHEMA_VERSION: u32 = 16;
+const SCHEMA_VERSION_KEY: &str = "schema_version";
+
+// Databases from before the version was stored start from 0, which is safe
+// as every block is written to run over an already migrated schema
+fn schema_version(conn: &duckdb::Connection) -> Result<u32> {
+    conn.execute_batch(
+        r"
+        CREATE TABLE IF NOT EXISTS config (
+            key VARCHAR,
+            value TEXT,
+            PRIMARY KEY (key)
+        );
+        ",
+    )?;
+
+    let value: Option<String> = conn
+        .query_row(
+            "SELECT value FROM config WHERE key = ?1",
+            [SCHEMA_VERSION_KEY],
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    Ok(match value {
+        Some(value) => serde_json::from_str(&value)?,
+        None => 0,
+    })
+}
+
+// Replays the snapshots stored by rank and writes them out again as keyframes
+// and deltas by id
+fn reencode_list_snapshots(conn: &duckdb::Connection) -> Result<()> {
+    if !table_exists(conn, "list_snapshot_rank")? {
+        return Ok(());
+    }
+
+    let snapshots = conn
+        .prepare("SELECT category, ts, size FROM list_snapshot ORDER BY category, ts")?
+        .query_map([], |row| {
+            let ts: DateTime<Utc> = row.get(1)?;
+            Ok((row.get::<_, String>(0)?, ts, row.get::<_, u32>(2)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut ranks: HashMap<(String, DateTime<Utc>), Vec<(u32, u32)>> = HashMap::new();
+    let rows = conn
+        .prepare("SELECT category, ts, rank, id FROM list_snapshot_rank")?
+        .query_map([], |row| {
+            let ts: DateTime<Utc> = row.get(1)?;
+            Ok((row.get::<_, String>(0)?, ts, row.get(2)?, row.get(3)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    for (category, ts, rank, id) in rows {
+        ranks.entry((category, ts)).or_default().push((rank, id));
+    }
+
+    let mut current_category = None;
+    let mut slots: Vec<u32> = vec![];
+    let mut previous: Vec<u32> = vec![];
+    let mut since_keyframe = 0;
+    for (category, ts, size) in snapshots {
+        if current_category.as_ref() != Some(&category) {
+            current_category = Some(category.clone());
+            slots.clear();
+            previous.clear();
+            since_keyframe = 0;
+        }
+
+        // Each rank held the id from the latest delta that touched it
+        for (rank, id) in ranks.remove(&(category.clone(), ts)).unwrap_or_default() {
+            let slot = rank as usize - 1;
+            if slots.len() <= slot {
+                slots.resize(slot + 1, 0);
+            }
+            slots[slot] = id;
+        }
+        let list = slots
+            .iter()
+            .take(size as usize)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let keyframe = since_keyframe % KEYFRAME_INTERVAL == 0;
+        let delta = if keyframe {
+            snapshot_delta(&[], &list)
+        } else {
+            snapshot_delta(&previous, &list)
+        };
+
+        conn.execute(
+            "UPDATE list_snapshot SET keyframe = ?1 WHERE category = ?2 AND ts = ?3",
+            params![keyframe, category, ts],
+        )?;
+        for (rank, id) in delta.ranked {
+            conn.execute(
+                "INSERT INTO list_snapshot_entry (category, ts, rank, id) VALUES (?1, ?2, ?3, ?4)",
+                params![category, ts, rank, id],
+            )?;
+        }
+        for id in delta.exited {
+            conn.execute(
+                "INSERT INTO list_snapshot_exit (category, ts, id) VALUES (?1, ?2, ?3)",
+                params![category, ts, id],
+            )?;
+        }
+
+        since_keyframe += 1;
+        previous = list;
+    }
+
+    Ok(())
+}
+
+fn table_exists(conn: &duckdb::Connection, table: &str) -> Result<bool> {
+    let count: u32 = conn.query_row(
+        "SELECT count(*) FROM sqlite_master WHERE type = 'table' AND name = ?1",
+        [table],
+        |row| row.get(0),
+    )?;
+
+    Ok(count > 0)
+}
+
+fn column_exists(conn: &duckdb::Connection, table: &str, column: &str) -> Result<bool> {
+    let count: u32 = conn.query_row(
+        &format!(
+            "SELECT count(*) FROM pragma_table_info('{}') WHERE name = ?1",
+            table
+        ),
+        [column],
+        |row| row.get(0),
+    )?;
+
+    Ok(count > 0)
+}
+
 #[cfg(test)]
 pub mod test {
     use super::*;
@@ -118,6 +567,37 @@ pub mod test {
         conn
     }
 
+    #[test]
+    fn migrate_runs_each_version_once() {
+        let db = setup();
+        let conn = db.get().unwrap();
+        let version: String = conn
+            .query_row(
+                "SELECT value FROM config WHERE key = ?1",
+                [SCHEMA_VERSION_KEY],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(version, SCHEMA_VERSION.to_string());
+
+        // The v10 dedupe would drop one of these if it ran again
+        for ts in &["2020-01-01T00:00:00Z", "2020-01-02T00:00:00Z"] {
+            conn.execute(
+                "INSERT INTO raw_item (id, ts, raw, issues, decoded) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![1, ts, "{}", "[]", false],
+            )
+            .unwrap();
+        }
+        db.migrate().unwrap();
+
+        let count: u32 = conn
+            .query_row("SELECT count(*) FROM raw_item WHERE id = 1", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(count, 2);
+    }
+
     #[test]
     fn test_setup() {
         let got = Duck::setup("test.db").is_ok();
@@ -211,6 +691,394 @@ pub mod test {
         assert_eq!(got, want);
     }
 
+    #[test]
+    fn migrate_v2_insert_item_score_with_descendants() {
+        let conn = setup_conn();
+        let got = conn
+            .execute(
+                r#"
+                    INSERT INTO item_score (id, score, descendants, ts)
+                    VALUES
+                    (?1, ?2, ?3, ?4)
+                "#,
+                params![1, 1, Some(2), "2020-01-01T00:00:00Z"],
+            )
+            .unwrap();
+
+        let want = 1;
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn migrate_v3_insert_item_list_exit() {
+        let conn = setup_conn();
+        let got = conn
+            .execute(
+                r#"
+                    INSERT INTO item_list_exit (id, category, ts)
+                    VALUES
+                    (?1, ?2, ?3)
+                "#,
+                params![1, "top", "2020-01-01T00:00:00Z"],
+            )
+            .unwrap();
+
+        let want = 1;
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn migrate_v4_insert_list_snapshot() {
+        let conn = setup_conn();
+        let got = conn
+            .execute(
+                r#"
+                    INSERT INTO list_snapshot (category, ts, size)
+                    VALUES
+                    (?1, ?2, ?3)
+                "#,
+                params!["top", "2020-01-01T00:00:00Z", 1],
+            )
+            .unwrap();
+
+        let want = 1;
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn migrate_v5_insert_item_version() {
+        let conn = setup_conn();
+        let got = conn
+            .execute(
+                r#"
+                    INSERT INTO item_version (id, ts, original, changes)
+                    VALUES
+                    (?1, ?2, ?3, ?4)
+                "#,
+                params![1, "2020-01-01T00:00:00Z", "{}", "[]"],
+            )
+            .unwrap();
+
+        let want = 1;
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn migrate_v6_insert_item_tombstone() {
+        let conn = setup_conn();
+        let got = conn
+            .execute(
+                r#"
+                    INSERT INTO item (id, original, deleted, dead)
+                    VALUES
+                    (?1, ?2, ?3, ?4)
+                "#,
+                params![1, "{}", true, false],
+            )
+            .unwrap();
+
+        let want = 1;
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn migrate_v7_insert_raw_item() {
+        let conn = setup_conn();
+        let got = conn
+            .execute(
+                r#"
+                    INSERT INTO raw_item (id, ts, raw, issues, decoded)
+                    VALUES
+                    (?1, ?2, ?3, ?4, ?5)
+                "#,
+                params![1, "2020-01-01T00:00:00Z", "{}", "[]", false],
+            )
+            .unwrap();
+
+        let want = 1;
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn migrate_v8_insert_fetch_failure() {
+        let conn = setup_conn();
+        let got = conn
+            .execute(
+                r#"
+                    INSERT INTO fetch_failure (id, kind, message, attempts, first_failed, next_attempt)
+                    VALUES
+                    (?1, ?2, ?3, ?4, ?5, ?6)
+                "#,
+                params![
+                    1,
+                    "timeout",
+                    "timed out",
+                    1,
+                    "2020-01-01T00:00:00Z",
+                    "2020-01-01T00:01:00Z"
+                ],
+            )
+            .unwrap();
+
+        let want = 1;
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn migrate_v9_insert_user_karma() {
+        let conn = setup_conn();
+        let got = conn
+            .execute(
+                r#"
+                    INSERT INTO user_karma (id, ts, karma)
+                    VALUES
+                    (?1, ?2, ?3)
+                "#,
+                params!["pg", "2020-01-01T00:00:00Z", 155111],
+            )
+            .unwrap();
+
+        let want = 1;
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn migrate_v10_insert_raw_item_issue() {
+        let conn = setup_conn();
+        let got = conn
+            .execute(
+                r#"
+                    INSERT INTO raw_item_issue (id, ts, field, problem)
+                    VALUES
+                    (?1, ?2, ?3, ?4)
+                "#,
+                params![1, "2020-01-01T00:00:00Z", "time", "missing"],
+            )
+            .unwrap();
+
+        let want = 1;
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn migrate_v11_insert_abandoned_fetch_failure() {
+        let conn = setup_conn();
+        let got = conn
+            .execute(
+                r#"
+                    INSERT INTO fetch_failure (id, kind, message, attempts, first_failed, next_attempt, abandoned)
+                    VALUES
+                    (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                "#,
+                params![
+                    1,
+                    "status 404",
+                    "not found",
+                    1,
+                    "2020-01-01T00:00:00Z",
+                    "2020-01-01T00:01:00Z",
+                    true
+                ],
+            )
+            .unwrap();
+
+        let want = 1;
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn migrate_v12_insert_missing_item() {
+        let conn = setup_conn();
+        let got = conn
+            .execute(
+                r#"
+                    INSERT INTO missing_item (id, ts)
+                    VALUES
+                    (?1, ?2)
+                "#,
+                params![1, "2020-01-01T00:00:00Z"],
+            )
+            .unwrap();
+
+        let want = 1;
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn migrate_v13_insert_user_crawl() {
+        let conn = setup_conn();
+        let got = conn
+            .execute(
+                r#"
+                    INSERT INTO user_crawl (id, status, submitted, stored, unavailable, failed, error, updated)
+                    VALUES
+                    (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                "#,
+                params!["pg", "done", 3, 2, 1, 0, None::<String>, "2020-01-01T00:00:00Z"],
+            )
+            .unwrap();
+
+        let want = 1;
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn migrate_v14_dedupe_item() {
+        let db = setup();
+        let conn = db.get().unwrap();
+
+        // Back to the unkeyed table, with an id stored twice
+        conn.execute_batch(
+            r"
+            DROP TABLE item;
+            CREATE TABLE item (
+                id INTEGER NOT NULL,
+                original VARCHAR NOT NULL,
+                descendants INTEGER,
+                username VARCHAR,
+                score INTEGER,
+                title VARCHAR,
+                url VARCHAR,
+                body VARCHAR,
+                ts TIMESTAMP,
+                deleted BOOLEAN DEFAULT FALSE,
+                dead BOOLEAN DEFAULT FALSE
+            );
+            INSERT INTO item (id, original) VALUES (1, 'old');
+            INSERT INTO item (id, original) VALUES (1, 'new');
+            UPDATE config SET value = '13' WHERE key = 'schema_version';
+            ",
+        )
+        .unwrap();
+        db.migrate().unwrap();
+
+        let got: Vec<String> = conn
+            .prepare("SELECT original FROM item WHERE id = 1")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(got, vec!["new".to_string()]);
+
+        assert!(conn
+            .execute(
+                "INSERT INTO item (id, original) VALUES (?1, ?2)",
+                params![1, "{}"],
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn migrate_v15_insert_item_time() {
+        let conn = setup_conn();
+        let got = conn
+            .execute(
+                r#"
+                    INSERT INTO item (id, original, time)
+                    VALUES
+                    (?1, ?2, ?3)
+                "#,
+                params![1, "{}", "2020-01-01T00:00:00Z"],
+            )
+            .unwrap();
+
+        let want = 1;
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn migrate_v16_insert_list_snapshot_entry_and_exit() {
+        let conn = setup_conn();
+        let got = conn
+            .execute(
+                r#"
+                    INSERT INTO list_snapshot_entry (category, ts, rank, id)
+                    VALUES
+                    (?1, ?2, ?3, ?4)
+                "#,
+                params!["top", "2020-01-01T00:00:00Z", 1, 1],
+            )
+            .unwrap()
+            + conn
+                .execute(
+                    r#"
+                    INSERT INTO list_snapshot_exit (category, ts, id)
+                    VALUES
+                    (?1, ?2, ?3)
+                "#,
+                    params!["top", "2020-01-01T00:00:00Z", 2],
+                )
+                .unwrap();
+
+        let want = 2;
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn migrate_v16_reencode_list_snapshots() {
+        let db = setup();
+        let conn = db.get().unwrap();
+
+        // Back to snapshots by rank: [1, 2, 3] and then [2, 1]
+        conn.execute_batch(
+            r"
+            CREATE TABLE list_snapshot_rank (
+                category VARCHAR NOT NULL,
+                ts TIMESTAMP NOT NULL,
+                rank INTEGER NOT NULL,
+                id INTEGER NOT NULL,
+                PRIMARY KEY (category, ts, rank)
+            );
+            INSERT INTO list_snapshot (category, ts, size) VALUES ('top', '2020-01-01 01:00:00', 3);
+            INSERT INTO list_snapshot (category, ts, size) VALUES ('top', '2020-01-01 02:00:00', 2);
+            INSERT INTO list_snapshot_rank VALUES ('top', '2020-01-01 01:00:00', 1, 1);
+            INSERT INTO list_snapshot_rank VALUES ('top', '2020-01-01 01:00:00', 2, 2);
+            INSERT INTO list_snapshot_rank VALUES ('top', '2020-01-01 01:00:00', 3, 3);
+            INSERT INTO list_snapshot_rank VALUES ('top', '2020-01-01 02:00:00', 1, 2);
+            INSERT INTO list_snapshot_rank VALUES ('top', '2020-01-01 02:00:00', 2, 1);
+            UPDATE config SET value = '15' WHERE key = 'schema_version';
+            ",
+        )
+        .unwrap();
+        db.migrate().unwrap();
+
+        let keyframes: Vec<bool> = conn
+            .prepare("SELECT keyframe FROM list_snapshot ORDER BY ts")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(keyframes, vec![true, false]);
+
+        let entries: u32 = conn
+            .query_row("SELECT count(*) FROM list_snapshot_entry", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(entries, 4);
+
+        let exited: u32 = conn
+            .query_row(
+                "SELECT id FROM list_snapshot_exit WHERE ts = '2020-01-01 02:00:00'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(exited, 3);
+        assert!(!table_exists(&conn, "list_snapshot_rank").unwrap());
+    }
+
+    #[test]
+    fn migrate_twice() {
+        let db = setup();
+        let got = db.migrate().is_ok();
+        let want = true;
+
+        assert_eq!(got, want);
+    }
+
     #[test]
     fn migrate_v1_insert_item_bookmark() {
         let conn = setup_conn();
@@ -0,0 +1,138 @@
+use duckdb::ToSql;
+
+use crate::domain::query::{Comparison, Filter, ItemType, Query};
+
+/// Compile a query into a `WHERE` clause over the `item` table and its
+/// positional parameters.
+pub fn compile(query: &Query) -> (String, Vec<Box<dyn ToSql>>) {
+    let mut params: Vec<Box<dyn ToSql>> = vec![];
+    let clauses = query
+        .terms
+        .iter()
+        .map(|term| {
+            let clause = compile_filter(&term.filter, &mut params);
+            if term.negated {
+                // A NULL column shouldn't make a negated term drop the row
+                format!("NOT coalesce({}, FALSE)", clause)
+            } else {
+                clause
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if clauses.is_empty() {
+        ("TRUE".to_string(), params)
+    } else {
+        (clauses.join(" AND "), params)
+    }
+}
+
+fn compile_filter(filter: &Filter, params: &mut Vec<Box<dyn ToSql>>) -> String {
+    match filter {
+        Filter::Text(text) => {
+            let text = text.to_lowercase();
+            params.push(Box::new(text.clone()));
+            params.push(Box::new(text));
+            "(contains(lower(coalesce(title, '')), ?) OR contains(lower(coalesce(body, '')), ?))"
+                .to_string()
+        }
+        Filter::Author(author) => {
+            params.push(Box::new(author.clone()));
+            "(username = ?)".to_string()
+        }
+        Filter::Type(item_type) => {
+            // The type tag is always the first key of the stored original
+            params.push(Box::new(format!(
+                "{{\"type\":\"{}\"%",
+                type_tag(item_type)
+            )));
+            "(original LIKE ?)".to_string()
+        }
+        Filter::Site(site) => {
+            params.push(Box::new(format!(
+                "^[a-zA-Z]+://([^/]*\\.)?{}([:/?#]|$)",
+                regex_escape(site)
+            )));
+            "regexp_matches(coalesce(url, ''), ?)".to_string()
+        }
+        Filter::Score(comparison, score) => {
+            params.push(Box::new(*score));
+            format!("(score {} ?)", operator(comparison))
+        }
+        Filter::Comments(comparison, count) => {
+            params.push(Box::new(*count));
+            format!("(descendants {} ?)", operator(comparison))
+        }
+        Filter::Before(date) => {
+            params.push(Box::new(date.format("%Y-%m-%d").to_string()));
+            "(time < CAST(? AS TIMESTAMP))".to_string()
+        }
+        Filter::After(date) => {
+            params.push(Box::new(date.format("%Y-%m-%d").to_string()));
+            "(time >= CAST(? AS TIMESTAMP))".to_string()
+        }
+        Filter::Deleted => "coalesce(deleted, FALSE)".to_string(),
+        Filter::Dead => "coalesce(dead, FALSE)".to_string(),
+    }
+}
+
+fn operator(comparison: &Comparison) -> &'static str {
+    match comparison {
+        Comparison::Eq => "=",
+        Comparison::Lt => "<",
+        Comparison::Lte => "<=",
+        Comparison::Gt => ">",
+        Comparison::Gte => ">=",
+    }
+}
+
+fn type_tag(item_type: &ItemType) -> &'static str {
+    match item_type {
+        ItemType::Story => "story",
+        ItemType::Comment => "comment",
+        ItemType::Job => "job",
+        ItemType::Poll => "poll",
+        ItemType::Pollopt => "pollopt",
+    }
+}
+
+fn regex_escape(input: &str) -> String {
+    input
+        .chars()
+        .flat_map(|c| {
+            let escape = "\\.+*?()|[]{}^$".contains(c);
+            escape.then(|| '\\').into_iter().chain(Some(c))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::domain::query::parse;
+
+    #[test]
+    fn compile_empty() {
+        let (sql, params) = compile(&Query::default());
+
+        assert_eq!(sql, "TRUE");
+        assert!(params.is_empty());
+    }
+
+    #[test]
+    fn compile_terms() {
+        let query = parse("author:pg -site:github.com score>=10").unwrap();
+        let (sql, params) = compile(&query);
+
+        assert_eq!(
+            sql,
+            "(username = ?) AND NOT coalesce(regexp_matches(coalesce(url, ''), ?), FALSE) AND (score >= ?)"
+        );
+        assert_eq!(params.len(), 3);
+    }
+
+    #[test]
+    fn escape_site() {
+        assert_eq!(regex_escape("github.com"), "github\\.com");
+    }
+}
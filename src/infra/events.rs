@@ -0,0 +1,120 @@
+use anyhow::{anyhow, Result};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    mpsc::{channel, Receiver, Sender},
+    Arc, Mutex,
+};
+
+use crate::domain::Event;
+
+/// Streams open at once. Each one holds a Rocket worker for as long as it's
+/// connected, so this leaves the rest of the pool to the other routes.
+pub const MAX_SUBSCRIBERS: usize = 8;
+
+/// In-process fan out of events to every live subscriber.
+#[derive(Clone)]
+pub struct EventBus {
+    senders: Arc<Mutex<Vec<Sender<Event>>>>,
+    subscribers: Arc<AtomicUsize>,
+    max_subscribers: usize,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::with_max_subscribers(MAX_SUBSCRIBERS)
+    }
+}
+
+/// A live subscription, giving up its place on the bus when dropped.
+pub struct Subscription {
+    pub receiver: Receiver<Event>,
+    subscribers: Arc<AtomicUsize>,
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.subscribers.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl EventBus {
+    pub fn with_max_subscribers(max_subscribers: usize) -> Self {
+        Self {
+            senders: Arc::default(),
+            subscribers: Arc::default(),
+            max_subscribers,
+        }
+    }
+
+    /// Subscribe, unless the bus already has as many subscribers as it takes.
+    pub fn subscribe(&self) -> Result<Option<Subscription>> {
+        // Claimed before checking, so racing subscribers can't both get the
+        // last place
+        if self.subscribers.fetch_add(1, Ordering::SeqCst) >= self.max_subscribers {
+            self.subscribers.fetch_sub(1, Ordering::SeqCst);
+            return Ok(None);
+        }
+
+        let (sender, receiver) = channel();
+        let subscription = Subscription {
+            receiver,
+            subscribers: self.subscribers.clone(),
+        };
+        self.senders
+            .lock()
+            .map_err(|_| anyhow!("Event bus poisoned"))?
+            .push(sender);
+
+        Ok(Some(subscription))
+    }
+
+    pub fn publish(&self, event: Event) -> Result<()> {
+        let mut senders = self
+            .senders
+            .lock()
+            .map_err(|_| anyhow!("Event bus poisoned"))?;
+
+        // Sending only fails once the receiver is gone, so drop those
+        senders.retain(|sender| sender.send(event.clone()).is_ok());
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use chrono::Utc;
+
+    #[test]
+    fn publish_to_subscribers() {
+        let bus = EventBus::default();
+        let first = bus.subscribe().unwrap().unwrap();
+        let second = bus.subscribe().unwrap().unwrap();
+        drop(second);
+
+        let event = Event::ItemUpdated {
+            id: 1,
+            score: Some(10),
+            descendants: None,
+            ts: Utc::now(),
+        };
+        bus.publish(event.clone()).unwrap();
+
+        assert_eq!(first.receiver.try_recv().unwrap(), event);
+        assert_eq!(bus.senders.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn subscribers_are_capped() {
+        let bus = EventBus::with_max_subscribers(2);
+        let first = bus.subscribe().unwrap();
+        let second = bus.subscribe().unwrap();
+        assert!(first.is_some() && second.is_some());
+        assert!(bus.subscribe().unwrap().is_none());
+
+        // A place frees up once a subscriber goes away
+        drop(first);
+        assert!(bus.subscribe().unwrap().is_some());
+    }
+}
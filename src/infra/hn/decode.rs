@@ -0,0 +1,245 @@
+//! Tolerant decoding of API items.
+//!
+//! Payloads are checked field by field against what the API documents, so a
+//! failure says which fields drifted instead of just the first serde error.
+
+use serde_json::Value;
+
+use crate::{domain::decode::FieldIssue, infra::hn::types::Item};
+
+#[derive(Clone, Copy)]
+enum Kind {
+    Integer,
+    Text,
+    Boolean,
+    Ids,
+}
+
+impl Kind {
+    fn name(&self) -> &'static str {
+        match self {
+            Kind::Integer => "integer",
+            Kind::Text => "string",
+            Kind::Boolean => "boolean",
+            Kind::Ids => "list of ids",
+        }
+    }
+
+    fn matches(&self, value: &Value) -> bool {
+        match self {
+            Kind::Integer => value.is_u64(),
+            Kind::Text => value.is_string(),
+            Kind::Boolean => value.is_boolean(),
+            Kind::Ids => value
+                .as_array()
+                .map_or(false, |ids| ids.iter().all(Value::is_u64)),
+        }
+    }
+}
+
+/// How a field is expected to appear.
+#[derive(Clone, Copy, PartialEq)]
+enum Presence {
+    /// Decoding fails without it.
+    Required,
+    /// Only deleted items leave it out.
+    Live,
+    Optional,
+}
+
+use Kind::*;
+use Presence::*;
+
+type Schema = &'static [(&'static str, Kind, Presence)];
+
+const COMMON: Schema = &[
+    ("id", Integer, Required),
+    ("type", Text, Required),
+    ("time", Integer, Required),
+    ("deleted", Boolean, Optional),
+    ("dead", Boolean, Optional),
+];
+
+fn schema(item_type: &str) -> Option<Schema> {
+    match item_type {
+        "story" => Some(&[
+            ("by", Text, Live),
+            ("descendants", Integer, Live),
+            ("kids", Ids, Optional),
+            ("score", Integer, Live),
+            ("title", Text, Live),
+            ("url", Text, Optional),
+            ("text", Text, Optional),
+        ]),
+        "comment" => Some(&[
+            ("by", Text, Live),
+            ("kids", Ids, Optional),
+            ("parent", Integer, Required),
+            ("text", Text, Live),
+        ]),
+        "job" => Some(&[
+            // Not modelled, but always there
+            ("by", Text, Optional),
+            ("score", Integer, Live),
+            ("text", Text, Optional),
+            ("title", Text, Live),
+            ("url", Text, Optional),
+        ]),
+        "poll" => Some(&[
+            ("by", Text, Live),
+            ("descendants", Integer, Live),
+            ("kids", Ids, Optional),
+            ("parts", Ids, Optional),
+            ("score", Integer, Live),
+            ("title", Text, Live),
+            ("text", Text, Optional),
+        ]),
+        "pollopt" => Some(&[
+            ("by", Text, Live),
+            ("poll", Integer, Required),
+            ("score", Integer, Live),
+            ("text", Text, Optional),
+        ]),
+        _ => None,
+    }
+}
+
+/// Decode a payload, returning the item if it could be decoded at all and
+/// every issue found along the way. A clean payload has no issues.
+pub fn decode(value: &Value) -> (Option<Item>, Vec<FieldIssue>) {
+    let issues = check(value);
+    match serde_json::from_value::<Item>(value.clone()) {
+        Ok(item) => (Some(item), issues),
+        // Something the schema doesn't know about, keep serde's word for it
+        Err(e) if issues.is_empty() => (
+            None,
+            vec![FieldIssue {
+                field: String::new(),
+                problem: e.to_string(),
+            }],
+        ),
+        Err(_) => (None, issues),
+    }
+}
+
+fn check(value: &Value) -> Vec<FieldIssue> {
+    let issue = |field: &str, problem: String| FieldIssue {
+        field: field.to_string(),
+        problem,
+    };
+
+    let object = match value.as_object() {
+        Some(object) => object,
+        None => return vec![issue("", "expected an object".to_string())],
+    };
+
+    let item_type = object.get("type").and_then(Value::as_str).unwrap_or("");
+    let fields = match schema(item_type) {
+        Some(fields) => fields,
+        None => return vec![issue("type", format!("unknown type '{}'", item_type))],
+    };
+    let deleted = object.get("deleted").and_then(Value::as_bool).unwrap_or(false);
+
+    let mut issues = vec![];
+    for (field, kind, presence) in COMMON.iter().chain(fields.iter()) {
+        match object.get(*field).filter(|value| !value.is_null()) {
+            Some(value) if !kind.matches(value) => issues.push(issue(
+                field,
+                format!("expected {}, found {}", kind.name(), type_name(value)),
+            )),
+            Some(_) => (),
+            None if *presence == Required || (*presence == Live && !deleted) => {
+                issues.push(issue(field, "missing".to_string()))
+            }
+            None => (),
+        }
+    }
+
+    for field in object.keys() {
+        let known = COMMON.iter().chain(fields.iter()).any(|(f, _, _)| f == field);
+        if !known {
+            issues.push(issue(field, "unknown field".to_string()));
+        }
+    }
+
+    issues
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::infra::hn::types::tests::sample_items;
+    use serde_json::json;
+
+    fn issue(field: &str, problem: &str) -> FieldIssue {
+        FieldIssue {
+            field: field.into(),
+            problem: problem.into(),
+        }
+    }
+
+    #[test]
+    fn clean_items() {
+        for item in sample_items() {
+            let (got, issues) = decode(&serde_json::to_value(&item).unwrap());
+
+            assert_eq!(got, Some(item));
+            assert_eq!(issues, vec![]);
+        }
+    }
+
+    #[test]
+    fn deleted_items_are_clean() {
+        let value = json!({"deleted": true, "id": 1, "parent": 2, "time": 3, "type": "comment"});
+        let (got, issues) = decode(&value);
+
+        assert!(got.unwrap().is_deleted());
+        assert_eq!(issues, vec![]);
+    }
+
+    #[test]
+    fn drift_is_reported_but_decoded() {
+        let value = json!({"id": 1, "by": "pg", "time": 3, "type": "story", "title": "Hi", "score": 1, "flair": "new"});
+        let (got, issues) = decode(&value);
+
+        assert!(got.is_some());
+        assert_eq!(
+            issues,
+            vec![issue("descendants", "missing"), issue("flair", "unknown field")]
+        );
+    }
+
+    #[test]
+    fn failures_say_which_fields() {
+        let value = json!({"id": 1, "by": "pg", "type": "comment", "text": "Hi", "parent": "2"});
+        let (got, issues) = decode(&value);
+
+        assert_eq!(got, None);
+        assert_eq!(
+            issues,
+            vec![
+                issue("time", "missing"),
+                issue("parent", "expected integer, found string")
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_type() {
+        let (got, issues) = decode(&json!({"id": 1, "type": "launch", "time": 3}));
+
+        assert_eq!(got, None);
+        assert_eq!(issues, vec![issue("type", "unknown type 'launch'")]);
+    }
+}
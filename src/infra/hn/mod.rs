@@ -1,9 +1,20 @@
-use rayon::prelude::*;
-use std::time::Duration;
+use futures::{
+    future::{self, loop_fn, Loop},
+    stream,
+    sync::oneshot,
+    Future, Stream,
+};
+use lazy_static::lazy_static;
+use serde::de::DeserializeOwned;
+use std::{sync::Mutex, time::Instant};
+use tokio::{runtime::Runtime, timer::Delay};
 
-use crate::domain::ListCategory;
-use reqwest::{self, Client};
+use crate::domain::{fetch::FailureKind, ListCategory};
+use reqwest::{self, r#async::Client};
+use throttle::{ClientConfig, InFlight, RateLimiter};
 
+pub mod decode;
+pub mod throttle;
 pub mod types;
 
 #[cfg(not(test))]
@@ -16,10 +27,33 @@ fn get_url() -> String {
     mockito::server_url()
 }
 
+/// A request in flight.
+pub type Fetch<T> = Box<dyn Future<Item = T, Error = reqwest::Error> + Send>;
+
+lazy_static! {
+    static ref RUNTIME: Mutex<Runtime> =
+        Mutex::new(Runtime::new().expect("Could not start the HN client runtime"));
+}
+
+/// Drive a client future to completion from synchronous code.
+pub fn block_on<T: Send + 'static>(fetch: Fetch<T>) -> reqwest::Result<T> {
+    let (tx, rx) = oneshot::channel();
+    RUNTIME
+        .lock()
+        .unwrap()
+        .executor()
+        .spawn(fetch.then(|result| tx.send(result).map_err(|_| ())));
+
+    rx.wait().expect("The HN client runtime dropped a request")
+}
+
 /// The API client.
 #[derive(Clone)]
 pub struct HnClient {
     client: Client,
+    config: ClientConfig,
+    limiter: RateLimiter,
+    in_flight: InFlight,
 }
 
 impl ToString for ListCategory {
@@ -31,71 +65,288 @@ impl ToString for ListCategory {
             Self::Ask => "ask".into(),
             Self::Show => "show".into(),
             Self::Job => "job".into(),
+            Self::Rising => "rising".into(),
         }
     }
 }
 
+/// Sort a request error into what the failure ledger tracks.
+pub fn failure_kind(error: &reqwest::Error) -> FailureKind {
+    if error.is_timeout() {
+        FailureKind::Timeout
+    } else if let Some(status) = error.status() {
+        FailureKind::Status(status.as_u16())
+    } else if error.is_serialization() {
+        FailureKind::Body
+    } else {
+        FailureKind::Connection
+    }
+}
+
+/// Whether a later attempt could succeed.
+fn is_transient(error: &reqwest::Error) -> bool {
+    match failure_kind(error) {
+        FailureKind::Timeout | FailureKind::Connection => true,
+        FailureKind::Status(status) => status == 429 || status >= 500,
+        FailureKind::Body => false,
+    }
+}
+
 impl HnClient {
     /// Create a new `` instance.
     pub fn init() -> reqwest::Result<Self> {
-        let client = reqwest::Client::builder()
-            .timeout(Duration::from_secs(10))
-            .build()?;
-        Ok(Self { client })
+        Self::with_config(ClientConfig::default())
+    }
+
+    /// Create a client with custom timeout, retry and rate limits.
+    pub fn with_config(config: ClientConfig) -> reqwest::Result<Self> {
+        let client = Client::builder().timeout(config.timeout).build()?;
+        Ok(Self {
+            client,
+            limiter: RateLimiter::new(config.requests_per_second),
+            in_flight: InFlight::new(config.max_concurrent),
+            config,
+        })
+    }
+
+    /// GET a path under the API, within the rate and in-flight limits,
+    /// retrying transient errors with backoff.
+    fn get<T: DeserializeOwned + Send + 'static>(&self, path: &str) -> Fetch<T> {
+        let url = format!("{}/{}", get_url(), path);
+        let client = self.client.clone();
+        let config = self.config.clone();
+        let limiter = self.limiter.clone();
+        let in_flight = self.in_flight.clone();
+
+        Box::new(loop_fn(0, move |retry| {
+            let config = config.clone();
+            let limiter = limiter.clone();
+            let request = client.get(&url);
+
+            // The slot is only held while the request is out, not during
+            // backoff. Neither the slot nor the timer fail unless the
+            // runtime shuts down.
+            in_flight
+                .acquire()
+                .then(move |permit| {
+                    Delay::new(limiter.reserve())
+                        .then(move |_| request.send())
+                        .and_then(|response| response.error_for_status())
+                        .and_then(|mut response| response.json::<T>())
+                        .then(move |result| {
+                            drop(permit);
+                            result
+                        })
+                })
+                .then(move |result| -> Fetch<Loop<T, u32>> {
+                    match result {
+                        Err(error) if retry < config.max_retries && is_transient(&error) => {
+                            Box::new(
+                                Delay::new(Instant::now() + config.backoff(retry)).then(
+                                    move |_| Ok::<_, reqwest::Error>(Loop::Continue(retry + 1)),
+                                ),
+                            )
+                        }
+                        result => Box::new(future::result(result.map(Loop::Break))),
+                    }
+                })
+        }))
     }
 
     /// Return the item with the specified id.
     ///
     /// May return `None` if item id is invalid.
-    pub fn get_item(&self, id: u32) -> reqwest::Result<Option<types::Item>> {
-        self.client
-            .get(&format!("{}/item/{}.json", get_url(), id))
-            .send()?
-            .json()
-    }
-
-    /// Par get items
-    pub fn get_items(&self, ids: Vec<u32>) -> anyhow::Result<Vec<types::Item>> {
-        Ok(ids
-            .into_par_iter()
-            .filter_map(|id| self.get_item(id).ok().flatten())
-            .collect::<Vec<types::Item>>())
+    pub fn get_item(&self, id: u32) -> Fetch<Option<types::Item>> {
+        self.get(&format!("item/{}.json", id))
     }
 
     /// Return the user with the specified username.
     ///
     /// May return `None` if username is invalid.
-    pub fn get_user(&self, username: &str) -> reqwest::Result<Option<types::User>> {
-        self.client
-            .get(&format!("{}/user/{}.json", get_url(), username))
-            .send()?
-            .json()
+    pub fn get_user(&self, username: &str) -> Fetch<Option<types::User>> {
+        self.get(&format!("user/{}.json", username))
     }
 
     /// Return the id of the newest item.
     ///
     /// To get the 10 latest items, you can decrement the id 10 times.
-    pub fn get_max_item_id(&self) -> reqwest::Result<u32> {
-        self.client
-            .get(&format!("{}/maxitem.json", get_url()))
-            .send()?
-            .json()
+    pub fn get_max_item_id(&self) -> Fetch<u32> {
+        self.get("maxitem.json")
     }
 
     /// Return a list of top story item ids.
-    pub fn get_stories_list(&self, category: ListCategory) -> reqwest::Result<Vec<u32>> {
+    pub fn get_stories_list(&self, category: ListCategory) -> Fetch<Vec<u32>> {
         let category = category.to_string();
-        self.client
-            .get(&format!("{}/{}stories.json", get_url(), category))
-            .send()?
-            .json()
+        self.get(&format!("{}stories.json", category))
+    }
+
+    /// Return the item with the specified id as raw JSON, for decoding
+    /// with `decode::decode`.
+    ///
+    /// May return `None` if item id is invalid.
+    pub fn get_raw_item(&self, id: u32) -> Fetch<Option<serde_json::Value>> {
+        Box::new(
+            self.get::<serde_json::Value>(&format!("item/{}.json", id))
+                .map(|value| Some(value).filter(|value| !value.is_null())),
+        )
+    }
+
+    // Run a request per key, pairing each result with its key in the order
+    // given. The client-wide in-flight cap keeps the batch in check.
+    fn get_many<K, T, F>(&self, keys: Vec<K>, get: F) -> Fetch<Vec<(K, reqwest::Result<T>)>>
+    where
+        K: Send + 'static,
+        T: Send + 'static,
+        F: Fn(&HnClient, &K) -> Fetch<T> + Send + 'static,
+    {
+        let client = self.clone();
+
+        Box::new(
+            stream::iter_ok::<_, reqwest::Error>(keys)
+                .map(move |key| get(&client, &key).then(move |result| Ok((key, result))))
+                .buffered(self.config.max_concurrent.max(1))
+                .collect(),
+        )
+    }
+
+    /// Get items concurrently, paired with the id requested and in the order
+    /// requested.
+    pub fn get_items(
+        &self,
+        ids: Vec<u32>,
+    ) -> Fetch<Vec<(u32, reqwest::Result<Option<types::Item>>)>> {
+        self.get_many(ids, |client, id| client.get_item(*id))
+    }
+
+    /// Get raw items concurrently like `get_items`.
+    pub fn get_raw_items(
+        &self,
+        ids: Vec<u32>,
+    ) -> Fetch<Vec<(u32, reqwest::Result<Option<serde_json::Value>>)>> {
+        self.get_many(ids, |client, id| client.get_raw_item(*id))
+    }
+
+    /// Get users concurrently like `get_items`, paired with the username
+    /// requested.
+    pub fn get_users(
+        &self,
+        usernames: Vec<String>,
+    ) -> Fetch<Vec<(String, reqwest::Result<Option<types::User>>)>> {
+        self.get_many(usernames, |client, username| client.get_user(username))
     }
 
     /// Return a list of items and users that have been updated recently.
-    pub fn get_updates(&self) -> reqwest::Result<types::Updates> {
-        self.client
-            .get(&format!("{}/updates.json", get_url()))
-            .send()?
-            .json()
+    pub fn get_updates(&self) -> Fetch<types::Updates> {
+        self.get("updates.json")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mockito::mock;
+    use std::time::Duration;
+
+    fn client() -> HnClient {
+        HnClient::with_config(ClientConfig {
+            max_retries: 2,
+            base_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(5),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn retries_transient_errors() {
+        let unavailable = mock("GET", "/item/20001.json")
+            .with_status(503)
+            .expect(3)
+            .create();
+
+        let got = block_on(client().get_raw_item(20001));
+
+        unavailable.assert();
+        assert_eq!(failure_kind(&got.unwrap_err()), FailureKind::Status(503));
+    }
+
+    #[test]
+    fn does_not_retry_client_errors() {
+        let not_found = mock("GET", "/item/20002.json")
+            .with_status(404)
+            .expect(1)
+            .create();
+
+        let got = block_on(client().get_raw_item(20002));
+
+        not_found.assert();
+        assert_eq!(failure_kind(&got.unwrap_err()), FailureKind::Status(404));
+    }
+
+    #[test]
+    fn gets_within_limits() {
+        let found = mock("GET", "/maxitem.json")
+            .with_status(200)
+            .with_body("20003")
+            .expect(1)
+            .create();
+
+        let got = block_on(client().get_max_item_id()).unwrap();
+
+        found.assert();
+        assert_eq!(got, 20003);
+    }
+
+    #[test]
+    fn gets_many_in_order() {
+        let ids = (21001..21010).collect::<Vec<u32>>();
+        let mocks = ids
+            .iter()
+            .map(|id| {
+                mock("GET", format!("/item/{}.json", id).as_str())
+                    .with_status(200)
+                    .with_body(format!(r#"{{"id": {}, "type": "story"}}"#, id))
+                    .create()
+            })
+            .collect::<Vec<_>>();
+
+        let client = HnClient::with_config(ClientConfig {
+            max_concurrent: 3,
+            ..Default::default()
+        })
+        .unwrap();
+        let got = block_on(client.get_raw_items(ids.clone())).unwrap();
+
+        for mock in mocks {
+            mock.assert();
+        }
+        let got_ids = got
+            .into_iter()
+            .map(|(id, result)| {
+                let value = result.unwrap().unwrap();
+                assert_eq!(value["id"], id);
+                id
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(got_ids, ids);
+    }
+
+    #[test]
+    fn gets_items() {
+        let _found = mock("GET", "/item/22001.json")
+            .with_status(200)
+            .with_body(r#"{"id": 22001, "by": "pg", "parent": 1, "text": "Hi", "time": 3, "type": "comment"}"#)
+            .create();
+        let _missing = mock("GET", "/item/22002.json")
+            .with_status(200)
+            .with_body("null")
+            .create();
+
+        let got = block_on(client().get_items(vec![22001, 22002])).unwrap();
+
+        assert_eq!(got[0].0, 22001);
+        assert_eq!(got[0].1.as_ref().unwrap().as_ref().unwrap().id(), 22001);
+        assert_eq!(got[1].0, 22002);
+        assert!(got[1].1.as_ref().unwrap().is_none());
     }
 }
@@ -0,0 +1,241 @@
+//! How hard the client is allowed to hit the HN API.
+
+use futures::{
+    future::{self, Future},
+    sync::oneshot,
+};
+use std::{
+    collections::{hash_map::RandomState, VecDeque},
+    hash::{BuildHasher, Hasher},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Tunables for `HnClient`.
+#[derive(Clone, Debug)]
+pub struct ClientConfig {
+    pub timeout: Duration,
+    /// Retries after the first attempt, only for transient errors.
+    pub max_retries: u32,
+    pub base_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Shared by every clone of the client. Zero turns the limiter off.
+    pub requests_per_second: u32,
+    /// Requests in flight at once, shared by every clone of the client.
+    pub max_concurrent: usize,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(10),
+            max_retries: 3,
+            base_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(10),
+            requests_per_second: 50,
+            max_concurrent: 16,
+        }
+    }
+}
+
+impl ClientConfig {
+    /// Exponential backoff with full jitter: a random wait up to
+    /// `base_backoff * 2^retry`, capped at `max_backoff`.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let ceiling = 2u32
+            .checked_pow(retry)
+            .and_then(|factor| self.base_backoff.checked_mul(factor))
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff);
+
+        ceiling.mul_f64(jitter())
+    }
+}
+
+// A number in [0, 1]. Each `RandomState` is keyed differently, which is
+// random enough for spreading out retries without pulling in a crate.
+fn jitter() -> f64 {
+    RandomState::new().build_hasher().finish() as f64 / u64::MAX as f64
+}
+
+/// Spaces requests out evenly so they stay under a rate.
+#[derive(Clone)]
+pub struct RateLimiter {
+    interval: Duration,
+    next: Arc<Mutex<Instant>>,
+}
+
+impl RateLimiter {
+    pub fn new(requests_per_second: u32) -> Self {
+        let interval = match requests_per_second {
+            0 => Duration::ZERO,
+            n => Duration::from_secs(1) / n,
+        };
+
+        Self {
+            interval,
+            next: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    /// Take the next free slot, returning when it comes up.
+    pub fn reserve(&self) -> Instant {
+        let mut next = self.next.lock().unwrap();
+        let slot = (*next).max(Instant::now());
+        *next = slot + self.interval;
+
+        slot
+    }
+}
+
+/// Caps how many requests are in flight at once. Waiting for a slot parks
+/// the task, not the thread, so the runtime keeps driving everything else.
+#[derive(Clone)]
+pub struct InFlight {
+    state: Arc<Mutex<InFlightState>>,
+}
+
+struct InFlightState {
+    available: usize,
+    waiting: VecDeque<oneshot::Sender<Permit>>,
+}
+
+/// A slot in flight, given back when dropped.
+pub struct Permit {
+    state: Arc<Mutex<InFlightState>>,
+}
+
+impl InFlight {
+    pub fn new(max_concurrent: usize) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(InFlightState {
+                available: max_concurrent.max(1),
+                waiting: VecDeque::new(),
+            })),
+        }
+    }
+
+    /// Wait for a free slot, first come first served.
+    pub fn acquire(&self) -> Box<dyn Future<Item = Permit, Error = ()> + Send> {
+        let mut state = self.state.lock().unwrap();
+        if state.available > 0 {
+            state.available -= 1;
+            return Box::new(future::ok(Permit {
+                state: self.state.clone(),
+            }));
+        }
+
+        let (tx, rx) = oneshot::channel();
+        state.waiting.push_back(tx);
+
+        Box::new(rx.map_err(|_| ()))
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        let waiter = {
+            let mut state = self.state.lock().unwrap();
+            match state.waiting.pop_front() {
+                Some(waiter) => waiter,
+                None => {
+                    state.available += 1;
+                    return;
+                }
+            }
+        };
+
+        // Hand the slot straight over. If that waiter has given up, the
+        // permit comes back and drops again for the next one in line.
+        let _ = waiter.send(Permit {
+            state: self.state.clone(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn backoff_grows_and_caps() {
+        let config = ClientConfig {
+            base_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(1),
+            ..Default::default()
+        };
+
+        for _ in 0..100 {
+            assert!(config.backoff(0) <= Duration::from_millis(100));
+            assert!(config.backoff(2) <= Duration::from_millis(400));
+            assert!(config.backoff(10) <= Duration::from_secs(1));
+            assert!(config.backoff(100) <= Duration::from_secs(1));
+        }
+    }
+
+    #[test]
+    fn backoff_is_jittered() {
+        let config = ClientConfig::default();
+        let waits = (0..10).map(|_| config.backoff(3)).collect::<Vec<_>>();
+
+        assert!(waits.iter().any(|wait| *wait != waits[0]));
+    }
+
+    #[test]
+    fn rate_limiter_spaces_requests() {
+        let limiter = RateLimiter::new(50);
+        let start = Instant::now();
+        let slots = (0..6).map(|_| limiter.reserve()).collect::<Vec<_>>();
+
+        // The first goes straight away, the next five wait 20ms each
+        assert!(slots[0] - start < Duration::from_millis(20));
+        assert!(slots[5] - start >= Duration::from_millis(100));
+        for pair in slots.windows(2) {
+            assert_eq!(pair[1] - pair[0], Duration::from_millis(20));
+        }
+    }
+
+    #[test]
+    fn in_flight_caps_and_hands_over() {
+        use std::{sync::mpsc, thread};
+
+        let in_flight = InFlight::new(2);
+        let first = in_flight.acquire().wait().unwrap();
+        let _second = in_flight.acquire().wait().unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let waiting = in_flight.acquire();
+        thread::spawn(move || {
+            let permit = waiting.wait();
+            tx.send(permit.is_ok()).unwrap();
+        });
+
+        // Full until one of the two is done
+        assert!(rx.recv_timeout(Duration::from_millis(50)).is_err());
+        drop(first);
+        assert_eq!(rx.recv_timeout(Duration::from_secs(1)), Ok(true));
+    }
+
+    #[test]
+    fn in_flight_skips_waiters_that_gave_up() {
+        let in_flight = InFlight::new(1);
+        let permit = in_flight.acquire().wait().unwrap();
+
+        drop(in_flight.acquire());
+        drop(permit);
+
+        // The slot went back rather than to the waiter that left
+        assert!(in_flight.acquire().wait().is_ok());
+    }
+
+    #[test]
+    fn rate_limiter_off() {
+        let limiter = RateLimiter::new(0);
+        let start = Instant::now();
+        let slots = (0..1000).map(|_| limiter.reserve()).collect::<Vec<_>>();
+
+        assert!(slots
+            .iter()
+            .all(|slot| *slot - start < Duration::from_secs(1)));
+    }
+}
@@ -3,6 +3,9 @@
 use serde::{Deserialize, Serialize};
 
 /// An API item, for example a story or a comment.
+///
+/// Deleted items come back with little more than an id, type and time, so
+/// the fields they drop are optional.
 #[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
 #[serde(tag = "type")]
 #[serde(rename_all = "lowercase")]
@@ -19,6 +22,10 @@ pub enum Item {
     Pollopt(Pollopt),
 }
 
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
 impl Item {
     /// Return the id of this item.
     pub fn id(&self) -> u32 {
@@ -31,12 +38,34 @@ impl Item {
         }
     }
 
+    /// Return whether this item has been deleted.
+    pub fn is_deleted(&self) -> bool {
+        match self {
+            Item::Story(story) => story.deleted,
+            Item::Comment(comment) => comment.deleted,
+            Item::Job(job) => job.deleted,
+            Item::Poll(poll) => poll.deleted,
+            Item::Pollopt(pollopt) => pollopt.deleted,
+        }
+    }
+
+    /// Return whether this item is dead, i.e. killed by flags or moderators.
+    pub fn is_dead(&self) -> bool {
+        match self {
+            Item::Story(story) => story.dead,
+            Item::Comment(comment) => comment.dead,
+            Item::Job(job) => job.dead,
+            Item::Poll(poll) => poll.dead,
+            Item::Pollopt(pollopt) => pollopt.dead,
+        }
+    }
+
     /// Return the title of this item, if available.
     pub fn title(&self) -> Option<&str> {
         match self {
-            Item::Story(story) => Some(&story.title),
-            Item::Job(job) => Some(&job.title),
-            Item::Poll(poll) => Some(&poll.title),
+            Item::Story(story) => story.title.as_deref(),
+            Item::Job(job) => job.title.as_deref(),
+            Item::Poll(poll) => poll.title.as_deref(),
             _ => None,
         }
     }
@@ -44,29 +73,41 @@ impl Item {
     /// Return the author of this item, if available.
     pub fn author(&self) -> Option<&str> {
         match self {
-            Item::Story(story) => Some(&story.by),
-            Item::Comment(comment) => Some(&comment.by),
-            Item::Poll(poll) => Some(&poll.by),
-            Item::Pollopt(pollopt) => Some(&pollopt.by),
+            Item::Story(story) => story.by.as_deref(),
+            Item::Comment(comment) => comment.by.as_deref(),
+            Item::Poll(poll) => poll.by.as_deref(),
+            Item::Pollopt(pollopt) => pollopt.by.as_deref(),
             _ => None,
         }
     }
 
+    /// Return the ids of the direct replies to this item.
+    pub fn kids(&self) -> &[u32] {
+        let kids = match self {
+            Item::Story(story) => &story.kids,
+            Item::Comment(comment) => &comment.kids,
+            Item::Poll(poll) => &poll.kids,
+            _ => &None,
+        };
+
+        kids.as_deref().unwrap_or_default()
+    }
+
     pub fn descendants(&self) -> Option<u32> {
         match self {
-            Item::Story(story) => Some(story.descendants),
-            Item::Poll(poll) => Some(poll.descendants),
+            Item::Story(story) => story.descendants,
+            Item::Poll(poll) => poll.descendants,
             _ => None,
         }
     }
 
     pub fn body(&self) -> Option<&str> {
         match self {
-            Item::Story(story) => story.text.as_ref().map(|s| s.as_str()),
-            Item::Comment(comment) => Some(&comment.text),
-            Item::Job(job) => job.text.as_ref().map(|s| s.as_str()),
-            Item::Poll(poll) => poll.text.as_ref().map(|s| s.as_str()),
-            Item::Pollopt(pollopt) => pollopt.text.as_ref().map(|s| s.as_str()),
+            Item::Story(story) => story.text.as_deref(),
+            Item::Comment(comment) => comment.text.as_deref(),
+            Item::Job(job) => job.text.as_deref(),
+            Item::Poll(poll) => poll.text.as_deref(),
+            Item::Pollopt(pollopt) => pollopt.text.as_deref(),
         }
     }
 
@@ -80,19 +121,9 @@ impl Item {
 
     pub fn score(&self) -> Option<u32> {
         match self {
-            Item::Story(story) => Some(story.score),
-            Item::Job(job) => Some(job.score),
-            Item::Poll(poll) => Some(poll.score),
-            _ => None,
-        }
-    }
-
-    pub fn username(&self) -> Option<&str> {
-        match self {
-            Item::Story(story) => Some(&story.by),
-            Item::Comment(comment) => Some(&comment.by),
-            Item::Poll(poll) => Some(&poll.by),
-            Item::Pollopt(pollopt) => Some(&pollopt.by),
+            Item::Story(story) => story.score,
+            Item::Job(job) => job.score,
+            Item::Poll(poll) => poll.score,
             _ => None,
         }
     }
@@ -101,9 +132,9 @@ impl Item {
         match self {
             Item::Story(story) => Some(&story.time),
             Item::Comment(comment) => Some(&comment.time),
+            Item::Job(job) => Some(&job.time),
             Item::Poll(poll) => Some(&poll.time),
             Item::Pollopt(pollopt) => Some(&pollopt.time),
-            _ => None,
         }
     }
 }
@@ -114,21 +145,31 @@ pub struct Story {
     /// The item's unique id.
     pub id: u32,
     /// The total comment count.
-    pub descendants: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub descendants: Option<u32>,
     /// The username of the item's author.
-    pub by: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub by: Option<String>,
     /// The ids of the item's comments, in ranked display order.
     pub kids: Option<Vec<u32>>,
     /// The story's score.
-    pub score: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<u32>,
     /// The title of the story.
-    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
     /// The URL of the story.
     pub url: Option<String>,
     /// The story text. HTML.
     pub text: Option<String>,
     /// Creation date of the item, in Unix Time.
     pub time: u64,
+    /// `true` if the item is deleted.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub deleted: bool,
+    /// `true` if the item is dead.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub dead: bool,
 }
 
 /// A comment.
@@ -137,15 +178,23 @@ pub struct Comment {
     /// The item's unique id.
     pub id: u32,
     /// The username of the item's author.
-    pub by: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub by: Option<String>,
     /// The ids of the item's comments, in ranked display order.
     pub kids: Option<Vec<u32>>,
     /// The comment's parent: either another comment or the relevant story.
     pub parent: u32,
     /// The comment text. HTML.
-    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
     /// Creation date of the item, in Unix Time.
     pub time: u64,
+    /// `true` if the item is deleted.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub deleted: bool,
+    /// `true` if the item is dead.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub dead: bool,
 }
 
 /// A job.
@@ -154,15 +203,23 @@ pub struct Job {
     /// The item's unique id.
     pub id: u32,
     /// The story's score, or the votes for a pollopt.
-    pub score: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<u32>,
     /// The job text. HTML.
     pub text: Option<String>,
     /// Creation date of the item, in Unix Time.
     pub time: u64,
     /// The title of the job.
-    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
     /// The URL of the story.
     pub url: Option<String>,
+    /// `true` if the item is deleted.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub deleted: bool,
+    /// `true` if the item is dead.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub dead: bool,
 }
 
 /// A poll.
@@ -171,21 +228,31 @@ pub struct Poll {
     /// The item's unique id.
     pub id: u32,
     /// The username of the item's author.
-    pub by: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub by: Option<String>,
     /// The total comment count.
-    pub descendants: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub descendants: Option<u32>,
     /// The ids of the item's comments, in ranked display order.
     pub kids: Option<Vec<u32>>,
     /// A list of related pollopts, in display order.
     pub parts: Option<Vec<u32>>,
     /// The story's score.
-    pub score: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<u32>,
     /// The title of the story.
-    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
     /// The story text. HTML.
     pub text: Option<String>,
     /// Creation date of the item, in Unix Time.
     pub time: u64,
+    /// `true` if the item is deleted.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub deleted: bool,
+    /// `true` if the item is dead.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub dead: bool,
 }
 
 /// A poll option belonging to a poll.
@@ -194,15 +261,23 @@ pub struct Pollopt {
     /// The item's unique id.
     pub id: u32,
     /// The username of the item's author.
-    pub by: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub by: Option<String>,
     /// The pollopt's associated poll.
     pub poll: u32,
     /// The votes for a pollopt.
-    pub score: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<u32>,
     /// The story text. HTML.
     pub text: Option<String>,
     /// Creation date of the item, in Unix Time.
     pub time: u64,
+    /// `true` if the item is deleted.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub deleted: bool,
+    /// `true` if the item is dead.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub dead: bool,
 }
 
 /// A user profile.
@@ -236,6 +311,17 @@ pub struct Updates {
 pub(crate) mod tests {
     use super::*;
 
+    pub fn sample_user() -> User {
+        User {
+            id: "pg".into(),
+            created: 1160418092,
+            karma: 155111,
+            delay: None,
+            about: Some("Bug fixer.".into()),
+            submitted: vec![160705, 126809],
+        }
+    }
+
     pub fn sample_items() -> Vec<Item> {
         let json = r#"
         [{
@@ -408,4 +494,53 @@ pub(crate) mod tests {
         let _pollopt: Pollopt = serde_json::from_str(&json).unwrap();
         let _item: Item = serde_json::from_str(&json).unwrap();
     }
+
+    #[test]
+    fn test_deleted_comment() {
+        let json = r#"
+        {
+          "deleted" : true,
+          "id" : 2921984,
+          "parent" : 2921506,
+          "time" : 1314211130,
+          "type" : "comment"
+        }"#;
+        let item: Item = serde_json::from_str(&json).unwrap();
+
+        assert!(item.is_deleted());
+        assert_eq!(item.author(), None);
+        assert_eq!(item.body(), None);
+
+        let json = serde_json::to_string(&item).unwrap();
+        assert!(!json.contains("by"));
+        assert!(!json.contains("text"));
+    }
+
+    #[test]
+    fn test_dead_story() {
+        let json = r#"
+        {
+          "by" : "spammer",
+          "dead" : true,
+          "id" : 8864,
+          "time" : 1175714300,
+          "title" : "Buy now",
+          "type" : "story"
+        }"#;
+        let item: Item = serde_json::from_str(&json).unwrap();
+
+        assert!(item.is_dead());
+        assert!(!item.is_deleted());
+        assert_eq!(item.score(), None);
+        assert_eq!(item.descendants(), None);
+    }
+
+    #[test]
+    fn test_flags_not_serialized_when_false() {
+        let item = &sample_items()[0];
+        let json = serde_json::to_string(item).unwrap();
+
+        assert!(!json.contains("deleted"));
+        assert!(!json.contains("dead"));
+    }
 }
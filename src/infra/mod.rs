@@ -1,2 +1,4 @@
 pub mod db;
+pub mod events;
 pub mod hn;
+pub mod queue;
@@ -0,0 +1,116 @@
+use anyhow::{anyhow, Result};
+use std::{
+    collections::{HashSet, VecDeque},
+    hash::Hash,
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+};
+
+/// Work for a single worker, first in first out. An item is held from being
+/// pushed until the worker is done with it, and pushing it again meanwhile
+/// does nothing.
+#[derive(Clone)]
+pub struct WorkQueue<T> {
+    state: Arc<Mutex<State<T>>>,
+    ready: Arc<Condvar>,
+}
+
+struct State<T> {
+    queue: VecDeque<T>,
+    pending: HashSet<T>,
+}
+
+impl<T> Default for WorkQueue<T> {
+    fn default() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(State {
+                queue: VecDeque::new(),
+                pending: HashSet::new(),
+            })),
+            ready: Arc::new(Condvar::new()),
+        }
+    }
+}
+
+impl<T: Clone + Eq + Hash> WorkQueue<T> {
+    /// Queue an item unless it's already queued or being worked on, and
+    /// return whether it was. `on_queued` runs before the worker can see the
+    /// item, and it isn't queued if that fails.
+    pub fn push(&self, item: T, on_queued: impl FnOnce() -> Result<()>) -> Result<bool> {
+        let mut state = self.lock()?;
+        if state.pending.contains(&item) {
+            return Ok(false);
+        }
+
+        on_queued()?;
+        state.pending.insert(item.clone());
+        state.queue.push_back(item);
+        self.ready.notify_one();
+
+        Ok(true)
+    }
+
+    /// Hand items to `work` one at a time, waiting for more when the queue is
+    /// empty. Only returns if the queue is poisoned.
+    pub fn work(&self, mut work: impl FnMut(&T)) -> Result<()> {
+        loop {
+            let item = self.next()?;
+            work(&item);
+            self.finish(&item)?;
+        }
+    }
+
+    fn next(&self) -> Result<T> {
+        let mut state = self.lock()?;
+        loop {
+            if let Some(item) = state.queue.pop_front() {
+                return Ok(item);
+            }
+            state = self
+                .ready
+                .wait(state)
+                .map_err(|_| anyhow!("Work queue poisoned"))?;
+        }
+    }
+
+    fn finish(&self, item: &T) -> Result<()> {
+        self.lock()?.pending.remove(item);
+
+        Ok(())
+    }
+
+    fn lock(&self) -> Result<MutexGuard<State<T>>> {
+        self.state
+            .lock()
+            .map_err(|_| anyhow!("Work queue poisoned"))
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    #[test]
+    fn holds_each_item_once() {
+        let queue = WorkQueue::default();
+
+        assert!(queue.push("pg", || Ok(())).unwrap());
+        assert!(queue.push("tel", || Ok(())).unwrap());
+        assert!(!queue.push("pg", || panic!("Already queued")).unwrap());
+
+        // Still held while it's worked on
+        assert_eq!(queue.next().unwrap(), "pg");
+        assert!(!queue.push("pg", || Ok(())).unwrap());
+
+        queue.finish(&"pg").unwrap();
+        assert!(queue.push("pg", || Ok(())).unwrap());
+        assert_eq!(queue.next().unwrap(), "tel");
+    }
+
+    #[test]
+    fn not_queued_if_on_queued_fails() {
+        let queue = WorkQueue::default();
+
+        assert!(queue.push("pg", || Err(anyhow!("No space"))).is_err());
+        assert!(queue.push("pg", || Ok(())).unwrap());
+    }
+}
@@ -1,6 +1,7 @@
 #![feature(proc_macro_hygiene, decl_macro)]
 
 mod adapters;
+mod api;
 mod capabilities;
 mod domain;
 mod infra;
@@ -8,28 +9,90 @@ mod use_cases;
 
 use adapters::AppCapabilities;
 use anyhow::Result;
-use infra::{db::Duck, hn::HnClient};
+use capabilities::{RefreshSearchIndex, WorkUserCrawls};
+use chrono::Utc;
+use infra::{
+    db::Duck,
+    hn::{throttle::ClientConfig, HnClient},
+};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 #[macro_use]
 extern crate rocket;
 
-#[get("/hello/<name>/<age>")]
-fn hello(name: String, age: u8) -> String {
-    format!("Hello, {} year old named {}!", age, name)
-}
-
 fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if args.first().map(String::as_str) == Some("backfill") {
+        return bulk_backfill(&args[1..]);
+    }
+
     let client = HnClient::init().unwrap();
     let duck = Duck::setup("data.db").expect("Could not connect to database");
     duck.migrate().expect("Failed to migrate the database");
     let app = AppCapabilities::new(duck, client);
+    let cron_app = app.clone();
     thread::spawn(move || {
-        cron(app);
+        cron(cron_app);
+    });
+    let crawl_app = app.clone();
+    thread::spawn(move || {
+        crawl_users(crawl_app);
+    });
+
+    api::rocket(app).launch();
+}
+
+// `backfill [--batch-size N] [--parallel N] [--rps N]` works through the whole
+// backfill queue and exits. Run it again after a crash and it carries on from
+// the last batch it stored. Requests are only bounded by `--parallel` unless
+// `--rps` is given.
+fn bulk_backfill(args: &[String]) {
+    let flag = |name: &str, default: u32| -> u32 {
+        args.iter()
+            .position(|arg| arg == name)
+            .and_then(|i| args.get(i + 1))
+            .map(|value| value.parse().expect("Expected a number"))
+            .unwrap_or(default)
+    };
+    let batch_size = flag("--batch-size", 10_000);
+    let parallel = flag("--parallel", 256);
+    let rps = flag("--rps", 0);
+
+    let client = HnClient::with_config(ClientConfig {
+        max_concurrent: parallel as usize,
+        requests_per_second: rps,
+        ..Default::default()
+    })
+    .unwrap();
+    let duck = Duck::setup("data.db").expect("Could not connect to database");
+    duck.migrate().expect("Failed to migrate the database");
+    let app = AppCapabilities::new(duck, client);
+
+    let result = use_cases::bulk_backfill::run(&app, batch_size, |progress| {
+        let now = Utc::now();
+        let eta = progress
+            .eta(&now)
+            .map(|eta| format!("{}h{:02}m", eta.num_hours(), eta.num_minutes() % 60))
+            .unwrap_or_else(|| "unknown".into());
+        println!(
+            "Backfilled {} ids at {:.0}/s, {} left, ETA {}",
+            progress.done,
+            progress.per_second(&now),
+            progress.remaining,
+            eta
+        );
     });
 
-    rocket::ignite().mount("/", routes![hello]).launch();
+    if result.is_err() {
+        println!("{:?}", result);
+        std::process::exit(1);
+    }
+
+    println!("Rebuilding the search index");
+    if let Err(e) = app.refresh_search_index() {
+        println!("{:?}", e);
+    }
 }
 
 // Tasks
@@ -38,29 +101,70 @@ fn main() {
 // [X] Track change in rank
 // [X] Backfill all items
 // [X] Poll for updates
-// [ ] GraphQL api
+// [X] GraphQL api
 // --------
 // Future
 // --------
-// [ ] Search API
+// [X] Search API
 // [ ] Store valid HTML
-// [ ] Track change in score, comment count, etc.
+// [X] Track change in score, comment count, etc.
+// Rebuilding the search index rereads the whole table, so between rebuilds
+// it's left to go stale for a while. Bulk backfills rebuild it when done.
+const SEARCH_INDEX_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
 fn cron(app: AppCapabilities) {
-    let do_work = || -> Result<()> {
-        //use_cases::download_lists::run(&app)?;
-        use_cases::backfill_items::run(&app, 10)?;
-        //use_cases::poll_for_updates::run(&app)?;
-        Ok(())
-    };
+    let mut indexed: Option<Instant> = None;
 
-    loop {
-        let result = do_work();
+    for round in 0.. {
+        // Each job runs whatever happened to the ones before it
+        run_job("download lists", || use_cases::download_lists::run(&app));
+        run_job("poll for updates", || {
+            use_cases::poll_for_updates::run(&app)
+        });
+        run_job("backfill", || use_cases::backfill_recent::run(&app, 100));
+        run_job("retry failed fetches", || {
+            use_cases::retry_failed_fetches::run(&app, 50)
+        });
+
+        // Scanning reads every id, so not every round
+        if round % 100 == 0 {
+            run_job("scan gaps", || use_cases::scan_gaps::run(&app));
+        }
+        if indexed.map_or(true, |at| at.elapsed() >= SEARCH_INDEX_INTERVAL) {
+            // Not retried every round if it fails, it could be downloading fts
+            indexed = Some(Instant::now());
+            run_job("refresh search index", || {
+                app.refresh_search_index().map(|_| ())
+            });
+        }
         println!("Finished cron loop");
 
+        thread::sleep(Duration::from_secs(3));
+    }
+}
+
+fn run_job(name: &str, job: impl FnOnce() -> Result<()>) {
+    if let Err(e) = job() {
+        println!("Failed to {}: {:?}", name, e);
+    }
+}
+
+// Crawls queued from the API, one at a time so they share the client with
+// cron rather than competing with each other. How each one went is in its
+// crawl state.
+fn crawl_users(app: AppCapabilities) {
+    // The queue only lives in memory, the crawl states say what it held
+    match use_cases::resume_user_crawls::run(&app) {
+        Ok(queued) => println!("Resumed {} user crawls", queued),
+        Err(e) => println!("Couldn't resume user crawls: {:?}", e),
+    }
+
+    let result = app.work_user_crawls(|username| {
+        let result = use_cases::crawl_user::run(&app, username, 500);
         if result.is_err() {
             println!("{:?}", result);
         }
+    });
 
-        thread::sleep(Duration::from_secs(3));
-    }
+    println!("Crawl worker stopped: {:?}", result);
 }
@@ -0,0 +1,91 @@
+use crate::{
+    capabilities::*,
+    domain::{
+        backfill::{schedule, sort_queue, BackfillRange, IdRange, RANGE_KEY_PREFIX},
+        fetch::FetchOutcome,
+    },
+    use_cases::track_users,
+};
+use anyhow::Result;
+
+/// Backfill from `maxitem` down, so recent history fills in first. New ids
+/// since the last run jump to the front of the queue.
+pub fn run(
+    app: &(impl StoreItems
+          + FetchItemOutcomes
+          + FetchMaxItemId
+          + FetchUsers
+          + StoreUsers
+          + LoadStoredUsernames
+          + LoadConfigValue
+          + LoadConfigValues
+          + StoreConfigValue
+          + DeleteConfigValue),
+    fetch_count: u32,
+) -> Result<()> {
+    let mut queue = load_queue(app)?;
+
+    let range = match queue.first_mut() {
+        Some(range) => range,
+        None => return Ok(()),
+    };
+    let ids = range.batch(fetch_count);
+    let outcomes = app.fetch_item_outcomes(ids.clone())?;
+    range.advance(&ids);
+
+    let items = outcomes
+        .into_iter()
+        .filter_map(FetchOutcome::into_item)
+        .collect::<Vec<_>>();
+    app.store_items(items.clone())?;
+    store_range(app, range)?;
+
+    // Last, so profiles that fail don't cost the batch
+    track_users::run(app, &[], &items)?;
+
+    Ok(())
+}
+
+/// The backfill queue, with any ids new since the last run at the front.
+pub fn load_queue(
+    app: &(impl FetchMaxItemId
+          + LoadConfigValue
+          + LoadConfigValues
+          + StoreConfigValue
+          + DeleteConfigValue),
+) -> Result<Vec<BackfillRange>> {
+    let mut queue: Vec<BackfillRange> = app.load_config_values_as(RANGE_KEY_PREFIX)?;
+    sort_queue(&mut queue);
+    let head: u32 = app.load_config_value_as("backfill_head")?.unwrap_or(0);
+
+    // Queue anything new before noting we've seen it, a crash in between
+    // just queues it twice
+    let max_id = app.fetch_max_item_id()?;
+    if max_id > head {
+        let added = schedule(
+            &mut queue,
+            &[IdRange {
+                start: head + 1,
+                end: max_id,
+            }],
+        );
+        for range in &added {
+            store_range(app, range)?;
+        }
+        app.store_config_value("backfill_head", max_id)?;
+    }
+
+    Ok(queue)
+}
+
+/// Checkpoint a range under its own key, or drop it once it's done.
+pub fn store_range(
+    app: &(impl StoreConfigValue + DeleteConfigValue),
+    range: &BackfillRange,
+) -> Result<()> {
+    if range.is_done() {
+        app.delete_config_value(&range.key())
+    } else {
+        app.store_config_value(&range.key(), range)
+    }
+}
@@ -0,0 +1,54 @@
+use crate::{
+    capabilities::*,
+    domain::{
+        backfill::{remaining, Progress},
+        fetch::FetchOutcome,
+    },
+    use_cases::backfill_recent::{load_queue, store_range},
+};
+use anyhow::Result;
+use chrono::Utc;
+
+/// Work through the whole backfill queue in large batches, checkpointing the
+/// range after each one so a restart picks up from the last batch. Storing a
+/// batch again is a no-op, so a crash before the checkpoint only costs the
+/// refetch. Ids that fail are left in the ledger for `retry_failed_fetches`
+/// and don't stop the run.
+pub fn run(
+    app: &(impl StoreItemsBulk
+          + FetchItemOutcomes
+          + FetchMaxItemId
+          + LoadConfigValue
+          + LoadConfigValues
+          + StoreConfigValue
+          + DeleteConfigValue),
+    batch_size: u32,
+    mut report: impl FnMut(&Progress),
+) -> Result<()> {
+    let mut queue = load_queue(app)?;
+    let mut progress = Progress::new(Utc::now(), remaining(&queue));
+
+    while let Some(range) = queue.first_mut() {
+        let next = range.next;
+        let ids = range.batch(batch_size);
+        let outcomes = app.fetch_item_outcomes(ids.clone())?;
+        range.advance(&ids);
+
+        app.store_items_bulk(
+            outcomes
+                .into_iter()
+                .filter_map(FetchOutcome::into_item)
+                .collect(),
+        )?;
+
+        store_range(app, range)?;
+
+        let done = (next - range.next) as u64;
+        queue.retain(|range| !range.is_done());
+
+        progress.record(done, remaining(&queue));
+        report(&progress);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,223 @@
+use crate::{
+    capabilities::*,
+    domain::{
+        crawl::{CrawlProgress, CrawlStatus, UserCrawl},
+        fetch::FetchOutcome,
+    },
+};
+use anyhow::Result;
+use chrono::Utc;
+
+/// Refresh a user's profile and fetch everything they've submitted that isn't
+/// stored yet, `batch_size` at a time. Each batch is stored before the next is
+/// fetched, so running it again carries on with whatever is still missing.
+///
+/// Progress is recorded after every batch, and whatever stops the crawl ends
+/// up in its state rather than being returned. The error is only for when
+/// that state can't be stored.
+pub fn run(
+    app: &(impl FetchUser
+          + StoreUser
+          + LoadStoredItemIds
+          + FetchItemOutcomes
+          + StoreItems
+          + StoreUserCrawl),
+    username: &str,
+    batch_size: usize,
+) -> Result<UserCrawl> {
+    let mut crawl = UserCrawl::new(username, CrawlStatus::Running, &Utc::now());
+    app.store_user_crawl(crawl.clone())?;
+
+    crawl.status = match fetch_submitted(app, &mut crawl, batch_size) {
+        Ok(true) => CrawlStatus::Done,
+        Ok(false) => CrawlStatus::NoSuchUser,
+        Err(e) => {
+            crawl.error = Some(format!("{:#}", e));
+            CrawlStatus::Failed
+        }
+    };
+    crawl.updated = Utc::now();
+    app.store_user_crawl(crawl.clone())?;
+
+    Ok(crawl)
+}
+
+// Whether HN has the user
+fn fetch_submitted(
+    app: &(impl FetchUser
+          + StoreUser
+          + LoadStoredItemIds
+          + FetchItemOutcomes
+          + StoreItems
+          + StoreUserCrawl),
+    crawl: &mut UserCrawl,
+    batch_size: usize,
+) -> Result<bool> {
+    let user = match app.fetch_user(&crawl.username)? {
+        Some(user) => user,
+        None => return Ok(false),
+    };
+    let submitted = user.submitted.clone();
+    app.store_user(user)?;
+
+    let stored = app.load_stored_item_ids(submitted.clone())?;
+    let missing = submitted
+        .iter()
+        .filter(|id| !stored.contains(id))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    crawl.progress = CrawlProgress {
+        submitted: submitted.len(),
+        stored: stored.len(),
+        unavailable: 0,
+        failed: 0,
+    };
+    for batch in missing.chunks(batch_size.max(1)) {
+        let mut items = vec![];
+        for outcome in app.fetch_item_outcomes(batch.to_vec())? {
+            match outcome {
+                FetchOutcome::Found(item) => items.push(item),
+                FetchOutcome::Failed { .. } => crawl.progress.failed += 1,
+                _ => crawl.progress.unavailable += 1,
+            }
+        }
+
+        crawl.progress.stored += items.len();
+        app.store_items(items)?;
+
+        crawl.updated = Utc::now();
+        app.store_user_crawl(crawl.clone())?;
+    }
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        domain::fetch::FailureKind,
+        infra::hn::types::{
+            tests::{sample_items, sample_user},
+            Item, User,
+        },
+    };
+    use anyhow::anyhow;
+    use std::collections::HashSet;
+
+    mockall::mock! {
+        App {}
+        impl FetchUser for App {
+            fn fetch_user(&self, username: &str) -> Result<Option<User>>;
+        }
+        impl StoreUser for App {
+            fn store_user(&self, user: User) -> Result<()>;
+        }
+        impl LoadStoredItemIds for App {
+            fn load_stored_item_ids(&self, ids: Vec<u32>) -> Result<HashSet<u32>>;
+        }
+        impl FetchItemOutcomes for App {
+            fn fetch_item_outcomes(&self, ids: Vec<u32>) -> Result<Vec<FetchOutcome>>;
+        }
+        impl StoreItems for App {
+            fn store_items(&self, items: Vec<Item>) -> Result<Vec<u32>>;
+        }
+        impl StoreUserCrawl for App {
+            fn store_user_crawl(&self, crawl: UserCrawl) -> Result<()>;
+        }
+    }
+
+    #[test]
+    fn fetches_what_is_missing() {
+        let mut mock = MockApp::new();
+
+        mock.expect_fetch_user().times(1).returning(|_| {
+            Ok(Some(User {
+                submitted: vec![1, 121003, 2, 8863, 3],
+                ..sample_user()
+            }))
+        });
+        mock.expect_store_user().times(1).returning(|_| Ok(()));
+        mock.expect_load_stored_item_ids()
+            .times(1)
+            .returning(|_| Ok(vec![1].into_iter().collect()));
+        mock.expect_fetch_item_outcomes()
+            .withf(|ids| ids == &vec![121003, 2])
+            .times(1)
+            .returning(|_| {
+                Ok(vec![
+                    FetchOutcome::Found(sample_items()[1].clone()),
+                    FetchOutcome::Missing(2),
+                ])
+            });
+        mock.expect_fetch_item_outcomes()
+            .withf(|ids| ids == &vec![8863, 3])
+            .times(1)
+            .returning(|_| {
+                Ok(vec![
+                    FetchOutcome::Found(sample_items()[0].clone()),
+                    FetchOutcome::Failed {
+                        id: 3,
+                        kind: FailureKind::Timeout,
+                        message: "timed out".into(),
+                    },
+                ])
+            });
+        mock.expect_store_items()
+            .withf(|items| items.len() == 1)
+            .times(2)
+            .returning(|items| Ok(items.iter().map(Item::id).collect()));
+        // Running, after each batch, and done
+        mock.expect_store_user_crawl()
+            .times(4)
+            .returning(|_| Ok(()));
+
+        let got = run(&mock, "pg", 2).unwrap();
+        let want = CrawlProgress {
+            submitted: 5,
+            stored: 3,
+            unavailable: 1,
+            failed: 1,
+        };
+
+        assert_eq!(got.status, CrawlStatus::Done);
+        assert_eq!(got.progress, want);
+    }
+
+    #[test]
+    fn unknown_user() {
+        let mut mock = MockApp::new();
+        mock.expect_fetch_user().times(1).returning(|_| Ok(None));
+        mock.expect_store_user().times(0);
+        mock.expect_store_user_crawl().returning(|_| Ok(()));
+
+        assert_eq!(
+            run(&mock, "nobody", 10).unwrap().status,
+            CrawlStatus::NoSuchUser
+        );
+    }
+
+    #[test]
+    fn records_what_stopped_it() {
+        let mut mock = MockApp::new();
+        mock.expect_fetch_user()
+            .times(1)
+            .returning(|_| Err(anyhow!("Connection refused")));
+        mock.expect_store_user_crawl()
+            .withf(|crawl| crawl.status == CrawlStatus::Running)
+            .times(1)
+            .returning(|_| Ok(()));
+        mock.expect_store_user_crawl()
+            .withf(|crawl| {
+                crawl.status == CrawlStatus::Failed
+                    && crawl.error == Some("Connection refused".to_string())
+            })
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let got = run(&mock, "pg", 10).unwrap();
+
+        assert_eq!(got.status, CrawlStatus::Failed);
+    }
+}
@@ -1,9 +1,11 @@
 use crate::{
     capabilities::*,
-    domain::{ItemRank, ListCategory},
+    domain::{tracking::TrackingConfig, Event, ItemRank, ListCategory, ListExit},
+    use_cases::track_scores,
 };
 use anyhow::Result;
 use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
 use strum::IntoEnumIterator;
 
 pub fn run(
@@ -12,50 +14,119 @@ pub fn run(
           + FetchItems
           + FetchList
           + StoreItemRanks
-          + LoadLatestItemRank
-          + ReplaceList),
+          + LoadLatestItemRanks
+          + ReplaceList
+          + LoadList
+          + StoreListExits
+          + LoadLatestItemScore
+          + StoreItemScores
+          + PublishEvents
+          + LoadConfigValue),
 ) -> Result<()> {
-    for category in ListCategory::iter() {
+    let config: TrackingConfig = app
+        .load_config_value_as("tracking_config")?
+        .unwrap_or_default();
+
+    for category in ListCategory::iter().filter(|c| !c.is_derived()) {
         let ids = app.fetch_list(category.clone())?;
-        fetch_and_store(app, ids, category)?;
+        fetch_and_store(app, ids, category, &config)?;
     }
 
     Ok(())
 }
 
 fn fetch_and_store(
-    app: &(impl StoreList + ReplaceList + StoreItems + FetchItems + StoreItemRanks + LoadLatestItemRank),
+    app: &(impl StoreList
+          + ReplaceList
+          + StoreItems
+          + FetchItems
+          + StoreItemRanks
+          + LoadLatestItemRanks
+          + LoadList
+          + StoreListExits
+          + LoadLatestItemScore
+          + StoreItemScores
+          + PublishEvents),
     ids: Vec<u32>,
     category: ListCategory,
+    config: &TrackingConfig,
 ) -> Result<()> {
-    let ids = ids.into_iter().take(30).collect::<Vec<_>>();
-    let item_ranks = item_ranks_from_vec(app, &category, &ids, &Utc::now())?;
+    let ts = Utc::now();
+    let ids = ids
+        .into_iter()
+        .take(config.depth(&category))
+        .collect::<Vec<_>>();
+    let hydrate_ids = ids
+        .iter()
+        .cloned()
+        .take(config.hydrate(&category))
+        .collect::<Vec<_>>();
+    let previous = app.load_list(category.clone())?.into_iter().collect::<HashSet<_>>();
+    let current = ids.iter().cloned().collect::<HashSet<_>>();
+
+    // Loaded before the new ranks are stored so these are still the previous ones
+    let latest = app.load_latest_item_ranks(ids.clone(), category.clone())?;
+    let mut item_ranks = item_ranks_from_vec(&latest, &category, &ids, &ts);
+    item_ranks.extend(reentered_ranks(&previous, &item_ranks, &category, &ids, &ts));
+    let mut events = rank_events(&latest, &previous, &item_ranks);
+
+    let list_exits = previous
+        .difference(&current)
+        .map(|id| ListExit {
+            id: *id,
+            category: category.clone(),
+            ts,
+        })
+        .collect::<Vec<_>>();
 
-    // Fetch from HN API
-    let items = app.fetch_items(ids.clone())?;
+    // Fetch from HN API, only the top of the list
+    let items = app.fetch_items(hydrate_ids)?;
 
     // Replaces list
-    app.replace_list(category, &ids)?;
+    app.replace_list(category, &ids, &ts)?;
 
-    // Store change in item
-    app.store_items(items)?;
+    // Store change in item, only the items that changed are news
+    let changed = app
+        .store_items(items.clone())?
+        .into_iter()
+        .collect::<HashSet<_>>();
+    events.extend(
+        items
+            .iter()
+            .filter(|item| changed.contains(&item.id()))
+            .map(|item| Event::ItemUpdated {
+                id: item.id(),
+                score: item.score(),
+                descendants: item.descendants(),
+                ts,
+            }),
+    );
 
     // Now store the item ranks
     app.store_item_ranks(item_ranks)?;
 
+    // And whatever dropped off
+    app.store_list_exits(list_exits)?;
+
+    // And any change in score
+    track_scores::run(app, &items, &ts)?;
+
+    // Let live subscribers know
+    app.publish_events(events)?;
+
     Ok(())
 }
 
 fn item_ranks_from_vec(
-    app: &impl LoadLatestItemRank,
+    latest: &HashMap<u32, ItemRank>,
     category: &ListCategory,
     input: &[u32],
     ts: &DateTime<Utc>,
-) -> Result<Vec<ItemRank>> {
+) -> Vec<ItemRank> {
     let mut results = vec![];
 
     for (rank, id) in input.iter().enumerate() {
-        let maybe_item_rank = app.load_latest_item_rank(*id, category.clone())?;
+        let maybe_item_rank = latest.get(id);
         let rank = rank as u32 + 1; // Take care of the off by one nature here
 
         // If the rank has changed, include it in the results
@@ -80,32 +151,90 @@ fn item_ranks_from_vec(
         };
     }
 
-    Ok(results)
+    results
+}
+
+// An item coming back at the rank it left with still needs a row to mark
+// the start of its new stay on the list
+fn reentered_ranks(
+    previous: &HashSet<u32>,
+    item_ranks: &[ItemRank],
+    category: &ListCategory,
+    input: &[u32],
+    ts: &DateTime<Utc>,
+) -> Vec<ItemRank> {
+    input
+        .iter()
+        .enumerate()
+        .filter(|(_, id)| !previous.contains(id) && !item_ranks.iter().any(|r| r.id == **id))
+        .map(|(rank, id)| ItemRank {
+            id: *id,
+            rank: rank as u32 + 1,
+            category: category.clone(),
+            ts: *ts,
+        })
+        .collect()
+}
+
+fn rank_events(
+    latest: &HashMap<u32, ItemRank>,
+    previous_ids: &HashSet<u32>,
+    item_ranks: &[ItemRank],
+) -> Vec<Event> {
+    let mut results = vec![];
+
+    for item_rank in item_ranks {
+        let previous = latest
+            .get(&item_rank.id)
+            .filter(|_| previous_ids.contains(&item_rank.id));
+
+        results.push(match previous {
+            Some(previous) => Event::RankChanged {
+                id: item_rank.id,
+                category: item_rank.category.clone(),
+                from: previous.rank,
+                to: item_rank.rank,
+                ts: item_rank.ts,
+            },
+            None => Event::EnteredList {
+                id: item_rank.id,
+                category: item_rank.category.clone(),
+                rank: item_rank.rank,
+                ts: item_rank.ts,
+            },
+        });
+    }
+
+    results
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
 
+    fn latest(ranks: Vec<(u32, u32)>, ts: &DateTime<Utc>) -> HashMap<u32, ItemRank> {
+        ranks
+            .into_iter()
+            .map(|(id, rank)| {
+                let item_rank = ItemRank {
+                    id,
+                    rank,
+                    category: ListCategory::Top,
+                    ts: ts.clone(),
+                };
+                (id, item_rank)
+            })
+            .collect()
+    }
+
     #[test]
     fn item_ranks_from_vec_when_rank_changed() {
-        let mut mock = MockLoadLatestItemRank::new();
         let ts = Utc::now();
-
-        mock.expect_load_latest_item_rank()
-            .times(1)
-            .returning(move |id, category| {
-                Ok(Some(ItemRank {
-                    id,
-                    category,
-                    rank: 2,
-                    ts: ts.clone(),
-                }))
-            });
+        let latest = latest(vec![(100, 2)], &ts);
 
         let input = vec![100];
 
-        let got = item_ranks_from_vec(&mock, &ListCategory::Top, &input, &ts).unwrap();
+        let got = item_ranks_from_vec(&latest, &ListCategory::Top, &input, &ts);
         let want = vec![ItemRank {
             id: 100,
             rank: 1, // New rank is 1
@@ -118,16 +247,11 @@ mod test {
 
     #[test]
     fn item_ranks_from_vec_when_none_exists() {
-        let mut mock = MockLoadLatestItemRank::new();
         let ts = Utc::now();
 
-        mock.expect_load_latest_item_rank()
-            .times(1)
-            .returning(move |_, _| Ok(None));
-
         let input = vec![100];
 
-        let got = item_ranks_from_vec(&mock, &ListCategory::Top, &input, &ts).unwrap();
+        let got = item_ranks_from_vec(&HashMap::new(), &ListCategory::Top, &input, &ts);
         let want = vec![ItemRank {
             id: 100,
             rank: 1,
@@ -140,33 +264,13 @@ mod test {
 
     #[test]
     fn item_ranks_from_vec_with_mixed_cases() {
-        let mut mock = MockLoadLatestItemRank::new();
         let ts = Utc::now();
-
-        mock.expect_load_latest_item_rank()
-            .times(3)
-            .returning(move |id, category| match id {
-                // 100 has changed rank
-                100 => Ok(Some(ItemRank {
-                    id,
-                    category,
-                    rank: 2,
-                    ts: ts.clone(),
-                })),
-                // 200 wasn't there
-                // 300 stayed the same
-                300 => Ok(Some(ItemRank {
-                    id,
-                    category,
-                    rank: 3,
-                    ts: ts.clone(),
-                })),
-                _ => Ok(None),
-            });
+        // 100 has changed rank, 200 wasn't there, 300 stayed the same
+        let latest = latest(vec![(100, 2), (300, 3)], &ts);
 
         let input = vec![100, 200, 300];
 
-        let got = item_ranks_from_vec(&mock, &ListCategory::Top, &input, &ts).unwrap();
+        let got = item_ranks_from_vec(&latest, &ListCategory::Top, &input, &ts);
         let want = vec![
             ItemRank {
                 id: 100,
@@ -185,4 +289,98 @@ mod test {
 
         assert_eq!(got, want);
     }
+
+    #[test]
+    fn rank_events_for_changed_and_new_items() {
+        let ts = Utc::now();
+        let latest = latest(vec![(100, 2)], &ts);
+
+        let item_ranks = vec![
+            ItemRank {
+                id: 100,
+                rank: 1,
+                category: ListCategory::Top,
+                ts: ts.clone(),
+            },
+            ItemRank {
+                id: 200,
+                rank: 2,
+                category: ListCategory::Top,
+                ts: ts.clone(),
+            },
+        ];
+
+        let previous = vec![100].into_iter().collect();
+        let got = rank_events(&latest, &previous, &item_ranks);
+        let want = vec![
+            Event::RankChanged {
+                id: 100,
+                category: ListCategory::Top,
+                from: 2,
+                to: 1,
+                ts: ts.clone(),
+            },
+            Event::EnteredList {
+                id: 200,
+                category: ListCategory::Top,
+                rank: 2,
+                ts: ts.clone(),
+            },
+        ];
+
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn rank_events_for_reentered_items() {
+        let ts = Utc::now();
+        let latest = latest(vec![(100, 3)], &ts);
+
+        let item_ranks = vec![ItemRank {
+            id: 100,
+            rank: 1,
+            category: ListCategory::Top,
+            ts: ts.clone(),
+        }];
+
+        // 100 has a rank from an earlier stay but wasn't on the list last time
+        let got = rank_events(&latest, &HashSet::new(), &item_ranks);
+        let want = vec![Event::EnteredList {
+            id: 100,
+            category: ListCategory::Top,
+            rank: 1,
+            ts: ts.clone(),
+        }];
+
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn reentered_ranks_at_unchanged_rank() {
+        let ts = Utc::now();
+        let previous = vec![100].into_iter().collect();
+        let item_ranks = vec![ItemRank {
+            id: 300,
+            rank: 3,
+            category: ListCategory::Top,
+            ts: ts.clone(),
+        }];
+
+        // 100 stayed, 200 came back at its old rank, 300 already has a new rank
+        let got = reentered_ranks(
+            &previous,
+            &item_ranks,
+            &ListCategory::Top,
+            &[100, 200, 300],
+            &ts,
+        );
+        let want = vec![ItemRank {
+            id: 200,
+            rank: 2,
+            category: ListCategory::Top,
+            ts: ts.clone(),
+        }];
+
+        assert_eq!(got, want);
+    }
 }
@@ -0,0 +1,171 @@
+use crate::{
+    capabilities::*,
+    infra::hn::types::{Comment, Item},
+};
+use anyhow::Result;
+use std::collections::HashMap;
+
+pub const DEFAULT_DEPTH: u32 = 3;
+pub const DEFAULT_LIMIT: u32 = 10;
+pub const MAX_DEPTH: u32 = 10;
+pub const MAX_LIMIT: u32 = 100;
+
+/// A comment within a thread with its replies, in display order.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ThreadComment {
+    pub comment: Comment,
+    pub replies: Vec<ThreadComment>,
+}
+
+/// Resolve the comment tree below `kids`, `depth` levels deep and at most
+/// `limit` replies per parent.
+pub fn run(
+    app: &(impl LoadItems + FetchItems + StoreItems),
+    kids: &[u32],
+    depth: u32,
+    limit: u32,
+    fetch_missing: bool,
+) -> Result<Vec<ThreadComment>> {
+    let depth = depth.min(MAX_DEPTH);
+    let limit = limit.min(MAX_LIMIT) as usize;
+
+    // Load one level at a time so each level is a single lookup
+    let mut comments = HashMap::new();
+    let mut level = kids.iter().take(limit).cloned().collect::<Vec<_>>();
+    for _ in 0..depth {
+        if level.is_empty() {
+            break;
+        }
+
+        let loaded = load_level(app, level, fetch_missing)?;
+        level = loaded
+            .iter()
+            .flat_map(|comment| comment.kids.iter().flatten().take(limit).cloned())
+            .collect();
+        comments.extend(loaded.into_iter().map(|comment| (comment.id, comment)));
+    }
+
+    Ok(nest(&comments, kids, limit))
+}
+
+fn load_level(
+    app: &(impl LoadItems + FetchItems + StoreItems),
+    ids: Vec<u32>,
+    fetch_missing: bool,
+) -> Result<Vec<Comment>> {
+    let mut items = app.load_items(ids.clone())?;
+
+    if fetch_missing {
+        let missing = ids
+            .into_iter()
+            .filter(|id| !items.iter().any(|item| item.id() == *id))
+            .collect::<Vec<_>>();
+
+        if !missing.is_empty() {
+            let fetched = app.fetch_items(missing)?;
+            app.store_items(fetched.clone())?;
+            items.extend(fetched);
+        }
+    }
+
+    Ok(items
+        .into_iter()
+        .filter_map(|item| match item {
+            Item::Comment(comment) => Some(comment),
+            _ => None,
+        })
+        .collect())
+}
+
+fn nest(comments: &HashMap<u32, Comment>, ids: &[u32], limit: usize) -> Vec<ThreadComment> {
+    ids.iter()
+        .take(limit)
+        .filter_map(|id| comments.get(id))
+        .map(|comment| ThreadComment {
+            comment: comment.clone(),
+            replies: comment
+                .kids
+                .as_ref()
+                .map(|kids| nest(comments, kids, limit))
+                .unwrap_or_default(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn comment(id: u32, kids: Vec<u32>) -> Comment {
+        Comment {
+            id,
+            by: Some("norvig".into()),
+            kids: Some(kids),
+            parent: 0,
+            text: Some(format!("comment {}", id)),
+            time: 1314211127,
+            deleted: false,
+            dead: false,
+        }
+    }
+
+    fn setup() -> crate::adapters::AppCapabilities {
+        let app = crate::adapters::test::setup();
+        let items = vec![
+            Item::Comment(comment(1, vec![3, 5])),
+            Item::Comment(comment(2, vec![])),
+            Item::Comment(comment(3, vec![4])),
+            Item::Comment(comment(4, vec![])),
+            Item::Comment(comment(5, vec![])),
+        ];
+        app.store_items(items).unwrap();
+        app
+    }
+
+    // Each comment as its id and the ids below it
+    #[derive(Debug, PartialEq)]
+    struct Node(u32, Vec<Node>);
+
+    fn shape(thread: Vec<ThreadComment>) -> Vec<Node> {
+        thread
+            .into_iter()
+            .map(|c| Node(c.comment.id, shape(c.replies)))
+            .collect()
+    }
+
+    #[test]
+    fn resolves_nested_tree_in_display_order() {
+        let app = setup();
+
+        let got = shape(run(&app, &[1, 2], 3, 10, false).unwrap());
+        let want = vec![
+            Node(1, vec![Node(3, vec![Node(4, vec![])]), Node(5, vec![])]),
+            Node(2, vec![]),
+        ];
+
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn respects_depth_limit() {
+        let app = setup();
+
+        let got = shape(run(&app, &[1, 2], 2, 10, false).unwrap());
+        let want = vec![
+            Node(1, vec![Node(3, vec![]), Node(5, vec![])]),
+            Node(2, vec![]),
+        ];
+
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn respects_breadth_limit() {
+        let app = setup();
+
+        let got = shape(run(&app, &[1, 2], 3, 1, false).unwrap());
+        let want = vec![Node(1, vec![Node(3, vec![Node(4, vec![])])])];
+
+        assert_eq!(got, want);
+    }
+}
@@ -0,0 +1,103 @@
+use crate::{
+    capabilities::*,
+    domain::{ItemScore, ListCategory},
+    infra::hn::types::Item,
+};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ListEntry {
+    pub rank: u32,
+    pub item: Item,
+    /// The last score snapshot at or before the requested time, if any.
+    pub score: Option<ItemScore>,
+}
+
+/// Reconstruct a list as it was at `ts`. Items that were never stored are
+/// left out, but the rest keep the rank they had at the time.
+pub fn run(
+    app: &(impl LoadListAt + LoadItems + LoadItemScoreAt),
+    category: ListCategory,
+    ts: DateTime<Utc>,
+) -> Result<Vec<ListEntry>> {
+    let ids = app.load_list_at(category, ts)?;
+    let items = app.load_items(ids.clone())?;
+
+    let mut results = vec![];
+    for (rank, id) in ids.iter().enumerate() {
+        let item = match items.iter().find(|item| item.id() == *id) {
+            Some(item) => item.clone(),
+            None => continue,
+        };
+
+        results.push(ListEntry {
+            rank: rank as u32 + 1,
+            score: app.load_item_score_at(*id, ts)?,
+            item,
+        });
+    }
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::infra::hn::types::tests::sample_items;
+    use chrono::TimeZone;
+
+    mockall::mock! {
+        App {}
+        impl LoadListAt for App {
+            fn load_list_at(&self, category: ListCategory, ts: DateTime<Utc>) -> Result<Vec<u32>>;
+        }
+        impl LoadItems for App {
+            fn load_items(&self, ids: Vec<u32>) -> Result<Vec<Item>>;
+        }
+        impl LoadItemScoreAt for App {
+            fn load_item_score_at(&self, id: u32, ts: DateTime<Utc>) -> Result<Option<ItemScore>>;
+        }
+    }
+
+    #[test]
+    fn entries_keep_their_rank() {
+        let mut mock = MockApp::new();
+        let ts = Utc.ymd(2020, 1, 1).and_hms(9, 0, 0);
+
+        // 404 was on the list but never stored
+        mock.expect_load_list_at()
+            .times(1)
+            .returning(|_, _| Ok(vec![404, 121003]));
+        mock.expect_load_items().times(1).returning(|ids| {
+            Ok(sample_items()
+                .into_iter()
+                .filter(|item| ids.contains(&item.id()))
+                .collect())
+        });
+        mock.expect_load_item_score_at()
+            .times(1)
+            .returning(|id, ts| {
+                Ok(Some(ItemScore {
+                    id,
+                    score: 20,
+                    descendants: Some(10),
+                    ts,
+                }))
+            });
+
+        let got = run(&mock, ListCategory::Top, ts).unwrap();
+        let want = vec![ListEntry {
+            rank: 2,
+            item: sample_items()[1].clone(),
+            score: Some(ItemScore {
+                id: 121003,
+                score: 20,
+                descendants: Some(10),
+                ts,
+            }),
+        }];
+
+        assert_eq!(got, want);
+    }
+}
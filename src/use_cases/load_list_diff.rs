@@ -0,0 +1,62 @@
+use crate::{
+    capabilities::*,
+    domain::{
+        list_diff::{list_diff, ListDiff},
+        ListCategory,
+    },
+};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+
+/// What changed in a list between the snapshots in effect at `from` and `to`.
+pub fn run(
+    app: &impl LoadListAt,
+    category: ListCategory,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<ListDiff> {
+    let before = app.load_list_at(category.clone(), from)?;
+    let after = app.load_list_at(category, to)?;
+
+    Ok(list_diff(&before, &after))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::domain::list_diff::RankChange;
+    use chrono::TimeZone;
+
+    #[test]
+    fn diff_between_times() {
+        let mut mock = MockLoadListAt::new();
+        let from = Utc.ymd(2020, 1, 1).and_hms(9, 0, 0);
+        let to = Utc.ymd(2020, 1, 1).and_hms(10, 0, 0);
+
+        mock.expect_load_list_at()
+            .times(2)
+            .returning(move |_, ts| Ok(if ts == from { vec![1, 2] } else { vec![2, 3] }));
+
+        let got = run(&mock, ListCategory::Top, from, to).unwrap();
+        let want = ListDiff {
+            entered: vec![RankChange {
+                id: 3,
+                from: None,
+                to: Some(2),
+            }],
+            exited: vec![RankChange {
+                id: 1,
+                from: Some(1),
+                to: None,
+            }],
+            moved_up: vec![RankChange {
+                id: 2,
+                from: Some(2),
+                to: Some(1),
+            }],
+            moved_down: vec![],
+        };
+
+        assert_eq!(got, want);
+    }
+}
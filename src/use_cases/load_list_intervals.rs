@@ -0,0 +1,74 @@
+use crate::{
+    capabilities::*,
+    domain::{
+        dwell::{list_intervals, ListInterval},
+        ListCategory,
+    },
+};
+use anyhow::Result;
+
+/// Every stay an item has had on a list, oldest first.
+pub fn run(
+    app: &(impl LoadItemRanks + LoadListExits),
+    id: u32,
+    category: ListCategory,
+) -> Result<Vec<ListInterval>> {
+    let ranks = app.load_item_ranks(id, category.clone())?;
+    let exits = app.load_list_exits(id, category)?;
+
+    Ok(list_intervals(&ranks, &exits))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::domain::{ItemRank, ListExit};
+    use chrono::{TimeZone, Utc};
+
+    mockall::mock! {
+        App {}
+        impl LoadItemRanks for App {
+            fn load_item_ranks(&self, id: u32, category: ListCategory) -> Result<Vec<ItemRank>>;
+        }
+        impl LoadListExits for App {
+            fn load_list_exits(&self, id: u32, category: ListCategory) -> Result<Vec<ListExit>>;
+        }
+    }
+
+    #[test]
+    fn intervals_for_item() {
+        let mut mock = MockApp::new();
+        let entered = Utc.ymd(2020, 1, 1).and_hms(0, 0, 0);
+        let exited = Utc.ymd(2020, 1, 1).and_hms(5, 0, 0);
+
+        mock.expect_load_item_ranks()
+            .times(1)
+            .returning(move |id, category| {
+                Ok(vec![ItemRank {
+                    id,
+                    rank: 1,
+                    category,
+                    ts: entered,
+                }])
+            });
+        mock.expect_load_list_exits()
+            .times(1)
+            .returning(move |id, category| {
+                Ok(vec![ListExit {
+                    id,
+                    category,
+                    ts: exited,
+                }])
+            });
+
+        let got = run(&mock, 1, ListCategory::Top).unwrap();
+        let want = vec![ListInterval {
+            id: 1,
+            category: ListCategory::Top,
+            entered,
+            exited: Some(exited),
+        }];
+
+        assert_eq!(got, want);
+    }
+}
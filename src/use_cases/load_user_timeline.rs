@@ -0,0 +1,159 @@
+use crate::{
+    capabilities::*,
+    domain::{crawl::UserCrawl, ItemScore},
+    infra::hn::types::{Item, User},
+};
+use anyhow::Result;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Timeline {
+    pub user: User,
+    pub stories: usize,
+    pub comments: usize,
+    /// Points across every stored submission.
+    pub points: u32,
+    /// Submitted ids with no stored item by the user, crawl the user to fill
+    /// them in. Deleted items have no author so they stay in here.
+    pub missing: usize,
+    /// The latest crawl of the user, if there was one.
+    pub crawl: Option<UserCrawl>,
+    /// Newest first.
+    pub entries: Vec<TimelineEntry>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct TimelineEntry {
+    pub item: Item,
+    /// The latest score snapshot, if the item was tracked.
+    pub score: Option<ItemScore>,
+}
+
+/// A user's submissions as stored locally, a page of `limit` of them from
+/// `offset` with their latest scores. Returns `None` if the user isn't
+/// stored.
+pub fn run(
+    app: &(impl LoadUser + LoadUserActivity + LoadUserItems + LoadUserCrawl + LoadLatestItemScore),
+    username: &str,
+    limit: u32,
+    offset: u32,
+) -> Result<Option<Timeline>> {
+    let user = match app.load_user(username)? {
+        Some(user) => user,
+        None => return Ok(None),
+    };
+    let activity = app.load_user_activity(username)?;
+
+    let mut entries = vec![];
+    for item in app.load_user_items(username, limit, offset)? {
+        entries.push(TimelineEntry {
+            score: app.load_latest_item_score(item.id())?,
+            item,
+        });
+    }
+
+    Ok(Some(Timeline {
+        stories: activity.stories,
+        comments: activity.comments,
+        points: activity.points,
+        missing: user.submitted.len().saturating_sub(activity.stored),
+        crawl: app.load_user_crawl(username)?,
+        user,
+        entries,
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        domain::{crawl::CrawlStatus, UserActivity},
+        infra::hn::types::tests::{sample_items, sample_user},
+    };
+    use chrono::{TimeZone, Utc};
+
+    mockall::mock! {
+        App {}
+        impl LoadUser for App {
+            fn load_user(&self, username: &str) -> Result<Option<User>>;
+        }
+        impl LoadUserActivity for App {
+            fn load_user_activity(&self, username: &str) -> Result<UserActivity>;
+        }
+        impl LoadUserItems for App {
+            fn load_user_items(&self, username: &str, limit: u32, offset: u32) -> Result<Vec<Item>>;
+        }
+        impl LoadUserCrawl for App {
+            fn load_user_crawl(&self, username: &str) -> Result<Option<UserCrawl>>;
+        }
+        impl LoadLatestItemScore for App {
+            fn load_latest_item_score(&self, id: u32) -> Result<Option<ItemScore>>;
+        }
+    }
+
+    #[test]
+    fn timeline() {
+        let mut mock = MockApp::new();
+        let ts = Utc.ymd(2020, 1, 1).and_hms(0, 0, 0);
+        let crawl = UserCrawl::new("pg", CrawlStatus::Running, &ts);
+        let stored_crawl = crawl.clone();
+
+        // One of three submissions was never stored
+        mock.expect_load_user().times(1).returning(|_| {
+            Ok(Some(User {
+                submitted: vec![160705, 404, 126809],
+                ..sample_user()
+            }))
+        });
+        mock.expect_load_user_activity().times(1).returning(|_| {
+            Ok(UserActivity {
+                stored: 2,
+                stories: 0,
+                comments: 0,
+                points: 382,
+            })
+        });
+        mock.expect_load_user_items()
+            .withf(|username, limit, offset| username == "pg" && *limit == 1 && *offset == 1)
+            .times(1)
+            .returning(|_, _, _| Ok(vec![sample_items()[4].clone()]));
+        mock.expect_load_user_crawl()
+            .times(1)
+            .returning(move |_| Ok(Some(stored_crawl.clone())));
+        mock.expect_load_latest_item_score()
+            .times(1)
+            .returning(move |id| {
+                Ok(Some(ItemScore {
+                    id,
+                    score: 20,
+                    descendants: Some(10),
+                    ts,
+                }))
+            });
+
+        let got = run(&mock, "pg", 1, 1).unwrap().unwrap();
+
+        assert_eq!(got.points, 382);
+        assert_eq!(got.missing, 1);
+        assert_eq!(got.crawl, Some(crawl));
+        assert_eq!(
+            got.entries,
+            vec![TimelineEntry {
+                item: sample_items()[4].clone(),
+                score: Some(ItemScore {
+                    id: 126809,
+                    score: 20,
+                    descendants: Some(10),
+                    ts,
+                }),
+            }]
+        );
+    }
+
+    #[test]
+    fn unknown_user() {
+        let mut mock = MockApp::new();
+        mock.expect_load_user().times(1).returning(|_| Ok(None));
+
+        assert_eq!(run(&mock, "nobody", 10, 0).unwrap(), None);
+    }
+}
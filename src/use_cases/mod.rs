@@ -1,3 +1,16 @@
-pub mod backfill_items;
+pub mod backfill_recent;
+pub mod bulk_backfill;
+pub mod crawl_user;
 pub mod download_lists;
+pub mod load_comment_tree;
+pub mod load_list_at;
+pub mod load_list_diff;
+pub mod load_list_intervals;
+pub mod load_user_timeline;
 pub mod poll_for_updates;
+pub mod resume_user_crawls;
+pub mod retry_failed_fetches;
+pub mod scan_gaps;
+pub mod search_items;
+pub mod track_scores;
+pub mod track_users;
@@ -1,10 +1,49 @@
-use crate::capabilities::*;
+use crate::{
+    capabilities::*,
+    domain::Event,
+    use_cases::{track_scores, track_users},
+};
 use anyhow::Result;
+use chrono::Utc;
+use std::collections::HashSet;
 
-pub fn run(app: &(impl StoreItems + FetchItems + FetchUpdates)) -> Result<()> {
-    let updated_item_ids = app.fetch_updates()?;
-    let items = app.fetch_items(updated_item_ids)?;
-    app.store_items(items)?;
+pub fn run(
+    app: &(impl StoreItems
+          + FetchItems
+          + FetchUpdates
+          + LoadLatestItemScore
+          + StoreItemScores
+          + FetchUsers
+          + StoreUsers
+          + LoadStoredUsernames
+          + PublishEvents),
+) -> Result<()> {
+    let updates = app.fetch_updates()?;
+    let items = app.fetch_items(updates.items)?;
+
+    let ts = Utc::now();
+    track_scores::run(app, &items, &ts)?;
+    let changed = app
+        .store_items(items.clone())?
+        .into_iter()
+        .collect::<HashSet<_>>();
+
+    // HN lists items as updated that often haven't changed since they were
+    // last stored, subscribers only hear about the ones that did
+    let events = items
+        .iter()
+        .filter(|item| changed.contains(&item.id()))
+        .map(|item| Event::ItemUpdated {
+            id: item.id(),
+            score: item.score(),
+            descendants: item.descendants(),
+            ts,
+        })
+        .collect();
+    app.publish_events(events)?;
+
+    // Last, so profiles that fail don't hold up the items
+    track_users::run(app, &updates.profiles, &items)?;
 
     Ok(())
 }
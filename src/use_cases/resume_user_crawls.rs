@@ -0,0 +1,45 @@
+use crate::capabilities::*;
+use anyhow::Result;
+
+/// Queue the crawls that were queued or running when the app last stopped,
+/// returning how many were queued again. Call it before the worker starts.
+pub fn run(app: &(impl LoadUnfinishedUserCrawls + QueueUserCrawl)) -> Result<usize> {
+    let mut queued = 0;
+    for username in app.load_unfinished_user_crawls()? {
+        if app.queue_user_crawl(&username)? {
+            queued += 1;
+        }
+    }
+
+    Ok(queued)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    mockall::mock! {
+        App {}
+        impl LoadUnfinishedUserCrawls for App {
+            fn load_unfinished_user_crawls(&self) -> Result<Vec<String>>;
+        }
+        impl QueueUserCrawl for App {
+            fn queue_user_crawl(&self, username: &str) -> Result<bool>;
+        }
+    }
+
+    #[test]
+    fn queues_unfinished_crawls() {
+        let mut mock = MockApp::new();
+
+        mock.expect_load_unfinished_user_crawls()
+            .times(1)
+            .returning(|| Ok(vec!["pg".to_string(), "tel".to_string()]));
+        mock.expect_queue_user_crawl()
+            .withf(|username| username == "pg" || username == "tel")
+            .times(2)
+            .returning(|_| Ok(true));
+
+        assert_eq!(run(&mock).unwrap(), 2);
+    }
+}
@@ -0,0 +1,81 @@
+use crate::capabilities::*;
+use anyhow::Result;
+use chrono::Utc;
+
+/// Fetch ids from the failure ledger whose backoff has passed. Fetching
+/// updates the ledger, so ids that fail again are pushed further back.
+pub fn run(app: &(impl LoadDueFetchFailures + FetchItems + StoreItems), limit: u32) -> Result<()> {
+    let due = app.load_due_fetch_failures(Utc::now(), limit)?;
+    if due.is_empty() {
+        return Ok(());
+    }
+
+    let items = app.fetch_items(due.iter().map(|failure| failure.id).collect())?;
+    app.store_items(items)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        domain::fetch::{FailureKind, FetchFailure},
+        infra::hn::types::{tests::sample_items, Item},
+    };
+    use chrono::DateTime;
+
+    mockall::mock! {
+        App {}
+        impl LoadDueFetchFailures for App {
+            fn load_due_fetch_failures(&self, now: DateTime<Utc>, limit: u32) -> Result<Vec<FetchFailure>>;
+        }
+        impl FetchItems for App {
+            fn fetch_items(&self, ids: Vec<u32>) -> Result<Vec<Item>>;
+        }
+        impl StoreItems for App {
+            fn store_items(&self, items: Vec<Item>) -> Result<Vec<u32>>;
+        }
+    }
+
+    #[test]
+    fn retries_due_failures() {
+        let mut mock = MockApp::new();
+
+        mock.expect_load_due_fetch_failures()
+            .times(1)
+            .returning(|now, _| {
+                Ok(vec![FetchFailure {
+                    id: 8863,
+                    kind: FailureKind::Timeout,
+                    message: "timed out".into(),
+                    attempts: 1,
+                    first_failed: now,
+                    next_attempt: now,
+                    abandoned: false,
+                }])
+            });
+        mock.expect_fetch_items()
+            .withf(|ids| ids == &vec![8863])
+            .times(1)
+            .returning(|_| Ok(vec![sample_items()[0].clone()]));
+        mock.expect_store_items()
+            .withf(|items| items.len() == 1)
+            .times(1)
+            .returning(|items| Ok(items.iter().map(Item::id).collect()));
+
+        run(&mock, 10).unwrap();
+    }
+
+    #[test]
+    fn nothing_due() {
+        let mut mock = MockApp::new();
+
+        mock.expect_load_due_fetch_failures()
+            .times(1)
+            .returning(|_, _| Ok(vec![]));
+        mock.expect_fetch_items().times(0);
+
+        run(&mock, 10).unwrap();
+    }
+}
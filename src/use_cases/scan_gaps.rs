@@ -0,0 +1,32 @@
+use crate::{
+    capabilities::*,
+    domain::backfill::{schedule, BackfillRange, RANGE_KEY_PREFIX},
+    use_cases::backfill_recent::store_range,
+};
+use anyhow::Result;
+
+/// Queue ids that were never accounted for, such as items stored before
+/// `backfill_recent` ran or ranges lost to a crash. Ids that were stored,
+/// quarantined, failed or null on HN already have an outcome and aren't
+/// queued again.
+pub fn run(
+    app: &(impl LoadItemGaps
+          + LoadConfigValue
+          + LoadConfigValues
+          + StoreConfigValue
+          + DeleteConfigValue),
+) -> Result<()> {
+    // Only what backfill has already seen, ids above it get queued there
+    let head: u32 = app.load_config_value_as("backfill_head")?.unwrap_or(0);
+    let gaps = app.load_item_gaps(head)?;
+    if gaps.is_empty() {
+        return Ok(());
+    }
+
+    let mut queue: Vec<BackfillRange> = app.load_config_values_as(RANGE_KEY_PREFIX)?;
+    for range in schedule(&mut queue, &gaps) {
+        store_range(app, &range)?;
+    }
+
+    Ok(())
+}
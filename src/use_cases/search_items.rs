@@ -0,0 +1,175 @@
+use crate::{capabilities::*, infra::hn::types::Item};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use std::cmp::Ordering;
+
+pub const DEFAULT_LIMIT: u32 = 30;
+pub const MAX_LIMIT: u32 = 200;
+
+// How many BM25 matches to consider per result when boosts can reorder them
+const CANDIDATES_PER_RESULT: u32 = 5;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct SearchQuery {
+    pub text: String,
+    pub limit: u32,
+    /// Weight of the item's points, 0 to rank by text relevance alone.
+    pub score_boost: f64,
+    /// Weight of the item's age in days, 0 to ignore age.
+    pub recency_boost: f64,
+}
+
+impl SearchQuery {
+    pub fn new(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            limit: DEFAULT_LIMIT,
+            score_boost: 0.0,
+            recency_boost: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct SearchResult {
+    pub item: Item,
+    pub relevance: f64,
+}
+
+pub fn run(
+    app: &(impl SearchItems + LoadItems),
+    query: &SearchQuery,
+    now: &DateTime<Utc>,
+) -> Result<Vec<SearchResult>> {
+    let limit = query.limit.min(MAX_LIMIT);
+    let boosted = query.score_boost != 0.0 || query.recency_boost != 0.0;
+    let candidates = if boosted {
+        limit * CANDIDATES_PER_RESULT
+    } else {
+        limit
+    };
+
+    let hits = app.search_items(&query.text, candidates)?;
+    let items = app.load_items(hits.iter().map(|hit| hit.id).collect())?;
+
+    let mut results = hits
+        .into_iter()
+        .filter_map(|hit| {
+            let item = items.iter().find(|item| item.id() == hit.id)?.clone();
+            Some(SearchResult {
+                relevance: boosted_relevance(hit.relevance, &item, query, now),
+                item,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    results.sort_by(|a, b| {
+        b.relevance
+            .partial_cmp(&a.relevance)
+            .unwrap_or(Ordering::Equal)
+    });
+    results.truncate(limit as usize);
+
+    Ok(results)
+}
+
+fn boosted_relevance(bm25: f64, item: &Item, query: &SearchQuery, now: &DateTime<Utc>) -> f64 {
+    let score = item.score().unwrap_or(0) as f64;
+    let age_days = item
+        .ts()
+        .map(|ts| (now.timestamp() - *ts as i64).max(0) as f64 / 86_400.0)
+        .unwrap_or(0.0);
+
+    bm25 * (1.0 + query.score_boost * score.ln_1p()) / (1.0 + age_days).powf(query.recency_boost)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{domain::SearchHit, infra::hn::types::tests::sample_items};
+    use chrono::TimeZone;
+
+    mockall::mock! {
+        App {}
+        impl SearchItems for App {
+            fn search_items(&self, text: &str, limit: u32) -> Result<Vec<SearchHit>>;
+        }
+        impl LoadItems for App {
+            fn load_items(&self, ids: Vec<u32>) -> Result<Vec<Item>>;
+        }
+    }
+
+    fn setup() -> MockApp {
+        let mut mock = MockApp::new();
+        let items = sample_items();
+
+        // The Ask HN post (25 points) out-ranks Dropbox (104 points) on text alone
+        mock.expect_search_items().returning(|_, _| {
+            Ok(vec![
+                SearchHit {
+                    id: 121003,
+                    relevance: 1.2,
+                },
+                SearchHit {
+                    id: 8863,
+                    relevance: 1.0,
+                },
+            ])
+        });
+        mock.expect_load_items().returning(move |ids| {
+            Ok(ids
+                .iter()
+                .filter_map(|id| items.iter().find(|item| item.id() == *id).cloned())
+                .collect())
+        });
+
+        mock
+    }
+
+    fn ids(results: Vec<SearchResult>) -> Vec<u32> {
+        results.into_iter().map(|r| r.item.id()).collect()
+    }
+
+    #[test]
+    fn ranks_by_bm25() {
+        let mock = setup();
+        let now = Utc.ymd(2010, 1, 1).and_hms(0, 0, 0);
+
+        let got = ids(run(&mock, &SearchQuery::new("hn"), &now).unwrap());
+        let want = vec![121003, 8863];
+
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn boosts_by_score() {
+        let mock = setup();
+        let now = Utc.ymd(2010, 1, 1).and_hms(0, 0, 0);
+        let query = SearchQuery {
+            score_boost: 1.0,
+            ..SearchQuery::new("hn")
+        };
+
+        let got = ids(run(&mock, &query, &now).unwrap());
+        let want = vec![8863, 121003];
+
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn boosts_by_recency() {
+        let mock = setup();
+        // A day after the Ask HN post, nearly a year after Dropbox
+        let now = Utc.ymd(2008, 2, 23).and_hms(0, 0, 0);
+        let query = SearchQuery {
+            recency_boost: 1.0,
+            limit: 1,
+            ..SearchQuery::new("hn")
+        };
+
+        let got = ids(run(&mock, &query, &now).unwrap());
+        let want = vec![121003];
+
+        assert_eq!(got, want);
+    }
+}
@@ -0,0 +1,144 @@
+use crate::{capabilities::*, domain::ItemScore, infra::hn::types::Item};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+
+/// Snapshot the score and comment count of freshly fetched items, skipping
+/// any that haven't changed since the last snapshot.
+pub fn run(
+    app: &(impl LoadLatestItemScore + StoreItemScores),
+    items: &[Item],
+    ts: &DateTime<Utc>,
+) -> Result<()> {
+    let item_scores = item_scores_from_vec(app, items, ts)?;
+    app.store_item_scores(item_scores)?;
+
+    Ok(())
+}
+
+fn item_scores_from_vec(
+    app: &impl LoadLatestItemScore,
+    items: &[Item],
+    ts: &DateTime<Utc>,
+) -> Result<Vec<ItemScore>> {
+    let mut results = vec![];
+
+    for item in items {
+        // Only items with points are tracked
+        let score = match item.score() {
+            Some(score) => score,
+            None => continue,
+        };
+        let descendants = item.descendants();
+        let maybe_item_score = app.load_latest_item_score(item.id())?;
+
+        // If the score or comment count has changed, include it in the results
+        match maybe_item_score {
+            Some(item_score)
+                if item_score.score == score && item_score.descendants == descendants => {}
+            _ => results.push(ItemScore {
+                id: item.id(),
+                score,
+                descendants,
+                ts: ts.clone(),
+            }),
+        };
+    }
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::infra::hn::types::tests::sample_items;
+
+    #[test]
+    fn item_scores_from_vec_when_score_changed() {
+        let mut mock = MockLoadLatestItemScore::new();
+        let ts = Utc::now();
+
+        mock.expect_load_latest_item_score()
+            .times(1)
+            .returning(move |id| {
+                Ok(Some(ItemScore {
+                    id,
+                    score: 100,
+                    descendants: Some(71),
+                    ts: ts.clone(),
+                }))
+            });
+
+        let input = sample_items()[..1].to_vec();
+
+        let got = item_scores_from_vec(&mock, &input, &ts).unwrap();
+        let want = vec![ItemScore {
+            id: 8863,
+            score: 104, // New score
+            descendants: Some(71),
+            ts: ts.clone(),
+        }];
+
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn item_scores_from_vec_when_none_exists() {
+        let mut mock = MockLoadLatestItemScore::new();
+        let ts = Utc::now();
+
+        mock.expect_load_latest_item_score()
+            .times(1)
+            .returning(move |_| Ok(None));
+
+        let input = sample_items()[..1].to_vec();
+
+        let got = item_scores_from_vec(&mock, &input, &ts).unwrap();
+        let want = vec![ItemScore {
+            id: 8863,
+            score: 104,
+            descendants: Some(71),
+            ts: ts.clone(),
+        }];
+
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn item_scores_from_vec_with_mixed_cases() {
+        let mut mock = MockLoadLatestItemScore::new();
+        let ts = Utc::now();
+
+        // The comment has no score so is never looked up
+        mock.expect_load_latest_item_score()
+            .times(2)
+            .returning(move |id| match id {
+                // 8863 stayed the same
+                8863 => Ok(Some(ItemScore {
+                    id,
+                    score: 104,
+                    descendants: Some(71),
+                    ts: ts.clone(),
+                })),
+                // 121003 has a new comment
+                121003 => Ok(Some(ItemScore {
+                    id,
+                    score: 25,
+                    descendants: Some(15),
+                    ts: ts.clone(),
+                })),
+                _ => Ok(None),
+            });
+
+        let input = sample_items()[..3].to_vec();
+
+        let got = item_scores_from_vec(&mock, &input, &ts).unwrap();
+        let want = vec![ItemScore {
+            id: 121003,
+            score: 25,
+            descendants: Some(16),
+            ts: ts.clone(),
+        }];
+
+        assert_eq!(got, want);
+    }
+}
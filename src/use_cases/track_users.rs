@@ -0,0 +1,160 @@
+use crate::{capabilities::*, infra::hn::types::Item};
+use anyhow::{anyhow, Result};
+use std::collections::HashSet;
+
+/// Refresh the profiles HN says have changed, along with the authors of
+/// freshly fetched items whose profile isn't stored yet. Profiles that
+/// couldn't be fetched are named in the error once the rest are stored.
+pub fn run(
+    app: &(impl FetchUsers + StoreUsers + LoadStoredUsernames),
+    profiles: &[String],
+    items: &[Item],
+) -> Result<()> {
+    let usernames = usernames_to_fetch(app, profiles, items)?;
+    if usernames.is_empty() {
+        return Ok(());
+    }
+
+    let mut users = vec![];
+    let mut failed = vec![];
+    for (username, result) in app.fetch_users(usernames)? {
+        match result {
+            Ok(user) => users.extend(user),
+            Err(e) => failed.push(format!("{} ({})", username, e)),
+        }
+    }
+    app.store_users(users)?;
+
+    if !failed.is_empty() {
+        return Err(anyhow!("Couldn't fetch users: {}", failed.join(", ")));
+    }
+
+    Ok(())
+}
+
+fn usernames_to_fetch(
+    app: &impl LoadStoredUsernames,
+    profiles: &[String],
+    items: &[Item],
+) -> Result<Vec<String>> {
+    let mut seen = HashSet::new();
+    let mut results = profiles
+        .iter()
+        .map(String::as_str)
+        .filter(|username| seen.insert(*username))
+        .map(String::from)
+        .collect::<Vec<_>>();
+    let authors = items
+        .iter()
+        .filter_map(Item::author)
+        .filter(|username| seen.insert(*username))
+        .map(String::from)
+        .collect::<Vec<_>>();
+
+    // Authors are looked up together, only the ones not stored yet are new
+    let known = app.load_stored_usernames(authors.clone())?;
+    results.extend(
+        authors
+            .into_iter()
+            .filter(|username| !known.contains(username)),
+    );
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::infra::hn::types::{
+        tests::{sample_items, sample_user},
+        User,
+    };
+
+    #[test]
+    fn usernames_to_fetch_skips_known_authors() {
+        let mut mock = MockLoadStoredUsernames::new();
+
+        mock.expect_load_stored_usernames()
+            .withf(|usernames| {
+                usernames
+                    == &vec![
+                        "dhouston".to_string(),
+                        "tel".to_string(),
+                        "norvig".to_string(),
+                    ]
+            })
+            .times(1)
+            .returning(|_| Ok(vec!["dhouston".to_string()].into_iter().collect()));
+
+        let profiles = vec!["pg".to_string(), "pg".to_string()];
+        let items = sample_items()[..3].to_vec();
+
+        let got = usernames_to_fetch(&mock, &profiles, &items).unwrap();
+        let want = vec!["pg".to_string(), "tel".to_string(), "norvig".to_string()];
+
+        assert_eq!(got, want);
+    }
+
+    mockall::mock! {
+        App {}
+        impl FetchUsers for App {
+            fn fetch_users(&self, usernames: Vec<String>) -> Result<Vec<(String, Result<Option<User>>)>>;
+        }
+        impl StoreUsers for App {
+            fn store_users(&self, users: Vec<User>) -> Result<()>;
+        }
+        impl LoadStoredUsernames for App {
+            fn load_stored_usernames(&self, usernames: Vec<String>) -> Result<HashSet<String>>;
+        }
+    }
+
+    #[test]
+    fn fetches_and_stores() {
+        let mut mock = MockApp::new();
+
+        mock.expect_load_stored_usernames()
+            .returning(|_| Ok(HashSet::new()));
+        mock.expect_fetch_users()
+            .withf(|usernames| usernames == &vec!["pg".to_string()])
+            .times(1)
+            .returning(|_| Ok(vec![("pg".to_string(), Ok(Some(sample_user())))]));
+        mock.expect_store_users()
+            .withf(|users| users == &vec![sample_user()])
+            .times(1)
+            .returning(|_| Ok(()));
+
+        run(&mock, &["pg".to_string()], &[]).unwrap();
+    }
+
+    #[test]
+    fn stores_the_rest_and_names_failures() {
+        let mut mock = MockApp::new();
+
+        mock.expect_load_stored_usernames()
+            .returning(|_| Ok(HashSet::new()));
+        mock.expect_fetch_users().times(1).returning(|_| {
+            Ok(vec![
+                ("pg".to_string(), Ok(Some(sample_user()))),
+                ("tel".to_string(), Err(anyhow!("timed out"))),
+            ])
+        });
+        mock.expect_store_users()
+            .withf(|users| users == &vec![sample_user()])
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let got = run(&mock, &["pg".to_string(), "tel".to_string()], &[]).unwrap_err();
+
+        assert!(got.to_string().contains("tel (timed out)"));
+    }
+
+    #[test]
+    fn nothing_to_fetch() {
+        let mut mock = MockApp::new();
+        mock.expect_load_stored_usernames()
+            .returning(|usernames| Ok(usernames.into_iter().collect()));
+        mock.expect_fetch_users().times(0);
+
+        run(&mock, &[], &sample_items()).unwrap();
+    }
+}